@@ -0,0 +1,42 @@
+use std::{cell::Cell, rc::Rc};
+
+use fltk::app::{self, TimeoutHandle};
+
+/// Seconds without edits before the window state is saved.
+const DELAY: f64 = 1.5;
+
+/// Saves a window's state once its user stops typing, so edits survive a quit even
+/// when the request was never sent.
+#[derive(Clone)]
+pub struct Autosave {
+    pending: Rc<Cell<Option<TimeoutHandle>>>,
+    save: Rc<dyn Fn()>,
+}
+
+impl Autosave {
+    pub fn new(save: Rc<dyn Fn()>) -> Self {
+        Self {
+            pending: Rc::new(Cell::new(None)),
+            save,
+        }
+    }
+
+    /// Something was edited, (re)starts the delay.
+    pub fn touch(&self) {
+        self.cancel();
+        let pending = self.pending.clone();
+        let save = self.save.clone();
+        let handle = app::add_timeout3(DELAY, move |_| {
+            pending.set(None);
+            save();
+        });
+        self.pending.set(Some(handle));
+    }
+
+    /// Drops a pending save, e.g. when the window is closed and its state deleted.
+    pub fn cancel(&self) {
+        if let Some(handle) = self.pending.take() {
+            app::remove_timeout3(handle);
+        }
+    }
+}
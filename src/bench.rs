@@ -0,0 +1,290 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use reqwest::{Method, header::HeaderMap};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot, watch};
+
+const HISTOGRAM_BUCKETS: usize = 20;
+/// Key in [`Report::statuses`] for requests that got no response.
+pub const TRANSPORT_ERROR: &str = "error";
+
+/// The request to repeat, variables already substituted.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub method: Method,
+    pub uri: String,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+/// Zero means unlimited, at least one of `requests` and `duration_s` has to be set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    pub concurrency: usize,
+    pub requests: usize,
+    pub duration_s: f64,
+    /// Requests per second over all workers.
+    pub rate: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Report {
+    pub requests: usize,
+    pub elapsed_ms: f64,
+    pub throughput: f64,
+    /// Count per status code, transport failures under [`TRANSPORT_ERROR`].
+    pub statuses: BTreeMap<String, usize>,
+    pub errors: usize,
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// Upper bound of each latency bucket and its count.
+    pub histogram: Vec<(f64, usize)>,
+}
+
+struct Sample {
+    status: Option<u16>,
+    latency: Duration,
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.concurrency == 0 {
+            return Err("concurrency must be at least 1".to_string());
+        }
+        if self.requests == 0 && self.duration_s <= 0.0 {
+            return Err("set a number of requests or a duration".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Repeats `target` with `config.concurrency` workers until the request count or the
+/// duration is reached or `stop` fires, which also abandons the requests in flight and the
+/// rate limiting waits. `on_progress` gets the number of completed requests
+/// a few times per second.
+pub async fn run(
+    target: Target,
+    config: Config,
+    mut on_progress: impl FnMut(usize),
+    mut stop: oneshot::Receiver<()>,
+) -> Report {
+    let client = reqwest::Client::new();
+    let target = Arc::new(target);
+    let issued = Arc::new(AtomicUsize::new(0));
+    let (cancel, _) = watch::channel(false);
+    let start = Instant::now();
+    let deadline =
+        (config.duration_s > 0.0).then(|| start + Duration::from_secs_f64(config.duration_s));
+    let (tx, mut rx) = mpsc::unbounded_channel::<Sample>();
+
+    for _ in 0..config.concurrency {
+        let (client, target, issued, tx) =
+            (client.clone(), target.clone(), issued.clone(), tx.clone());
+        let mut cancelled = cancel.subscribe();
+        let config = config.clone();
+        tokio::spawn(async move {
+            loop {
+                let n = issued.fetch_add(1, Ordering::SeqCst);
+                if config.requests > 0 && n >= config.requests {
+                    break;
+                }
+
+                let send = async {
+                    if config.rate > 0.0 {
+                        let at = start + Duration::from_secs_f64(n as f64 / config.rate);
+                        tokio::time::sleep_until(at.into()).await;
+                    }
+                    if deadline.is_some_and(|d| Instant::now() >= d) {
+                        return None;
+                    }
+
+                    let sent_at = Instant::now();
+                    let sent = client
+                        .request(target.method.clone(), &target.uri)
+                        .headers(target.headers.clone())
+                        .body(target.body.clone())
+                        .send()
+                        .await;
+                    // the body is read so the latency covers the whole response
+                    let status = match sent {
+                        Ok(resp) => {
+                            let status = resp.status().as_u16();
+                            resp.bytes().await.ok().map(|_| status)
+                        }
+                        Err(_) => None,
+                    };
+                    Some(Sample {
+                        status,
+                        latency: sent_at.elapsed(),
+                    })
+                };
+                let sample = tokio::select! {
+                    biased;
+                    _ = cancelled.wait_for(|cancelled| *cancelled) => None,
+                    sample = send => sample,
+                };
+                let Some(sample) = sample else {
+                    break;
+                };
+                if tx.send(sample).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    let mut samples = Vec::new();
+    let mut last_progress = Instant::now();
+    loop {
+        tokio::select! {
+            sample = rx.recv() => match sample {
+                Some(sample) => samples.push(sample),
+                None => break,
+            },
+            _ = &mut stop, if !*cancel.borrow() => {
+                cancel.send_replace(true);
+            }
+        }
+        if last_progress.elapsed() >= Duration::from_millis(200) {
+            on_progress(samples.len());
+            last_progress = Instant::now();
+        }
+    }
+    on_progress(samples.len());
+
+    summarize(&samples, start.elapsed())
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn summarize(samples: &[Sample], elapsed: Duration) -> Report {
+    let mut report = Report {
+        requests: samples.len(),
+        elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+        throughput: samples.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        ..Report::default()
+    };
+    for s in samples {
+        let key = s
+            .status
+            .map_or(TRANSPORT_ERROR.to_string(), |s| s.to_string());
+        *report.statuses.entry(key).or_default() += 1;
+        if s.status.is_none_or(|s| s >= 400) {
+            report.errors += 1;
+        }
+    }
+    if samples.is_empty() {
+        return report;
+    }
+
+    let mut latencies: Vec<f64> = samples
+        .iter()
+        .map(|s| s.latency.as_secs_f64() * 1000.0)
+        .collect();
+    latencies.sort_by(f64::total_cmp);
+    report.min_ms = latencies[0];
+    report.max_ms = latencies[latencies.len() - 1];
+    report.mean_ms = latencies.iter().sum::<f64>() / latencies.len() as f64;
+    report.p50_ms = percentile(&latencies, 0.5);
+    report.p90_ms = percentile(&latencies, 0.9);
+    report.p99_ms = percentile(&latencies, 0.99);
+
+    let width = ((report.max_ms - report.min_ms) / HISTOGRAM_BUCKETS as f64).max(0.001);
+    report.histogram = (1..=HISTOGRAM_BUCKETS)
+        .map(|i| (report.min_ms + width * i as f64, 0))
+        .collect();
+    for l in &latencies {
+        let i = (((l - report.min_ms) / width) as usize).min(HISTOGRAM_BUCKETS - 1);
+        report.histogram[i].1 += 1;
+    }
+
+    report
+}
+
+impl Report {
+    /// Summary and latency histogram as text.
+    pub fn display(&self) -> String {
+        let mut text = format!(
+            "Requests:   {}\nElapsed:    {:.1} s\nThroughput: {:.1} req/s\nErrors:     {}\n\n",
+            self.requests,
+            self.elapsed_ms / 1000.0,
+            self.throughput,
+            self.errors
+        );
+        for (status, count) in &self.statuses {
+            text.push_str(&format!("  {status:>6}: {count}\n"));
+        }
+        text.push_str(&format!(
+            "\nLatency ms  min {:.1}  mean {:.1}  p50 {:.1}  p90 {:.1}  p99 {:.1}  max {:.1}\n\n",
+            self.min_ms, self.mean_ms, self.p50_ms, self.p90_ms, self.p99_ms, self.max_ms
+        ));
+
+        let widest = self.histogram.iter().map(|(_, c)| *c).max().unwrap_or(0);
+        for (upper, count) in &self.histogram {
+            let bar = (count * 50).checked_div(widest).unwrap_or(0);
+            text.push_str(&format!(
+                "<= {upper:>9.1} ms | {:<50} {count}\n",
+                "#".repeat(bar)
+            ));
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stop_abandons_requests_in_flight() {
+        // accepts connections and never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                open.push(stream);
+            }
+        });
+
+        let target = Target {
+            method: Method::GET,
+            uri: format!("http://{addr}/"),
+            headers: HeaderMap::new(),
+            body: String::new(),
+        };
+        let config = Config {
+            concurrency: 4,
+            requests: 0,
+            duration_s: 60.0,
+            rate: 0.0,
+        };
+        let (stop, stopped) = oneshot::channel();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let _ = stop.send(());
+        });
+
+        let report =
+            tokio::time::timeout(Duration::from_secs(5), run(target, config, |_| (), stopped))
+                .await
+                .expect("stop did not end the run");
+        assert_eq!(report.requests, 0);
+    }
+}
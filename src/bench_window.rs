@@ -0,0 +1,250 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use fltk::{
+    app, browser, button,
+    enums::{self, Event},
+    frame, group, input,
+    prelude::*,
+    text::{self, TextBuffer},
+    window::{self, DoubleWindow},
+};
+use tokio::sync::oneshot;
+
+use crate::{
+    AppWindow, GlobalAppMsg, HasId,
+    bench::{self, Target},
+    db::BenchRun,
+    next_window_id,
+};
+
+/// Load test of one request, earlier runs against the same URI are listed for comparison.
+pub struct BenchWindow {
+    id: usize,
+    window: DoubleWindow,
+    runs: Rc<RefCell<Vec<BenchRun>>>,
+    list: browser::HoldBrowser,
+    report_buf: TextBuffer,
+    stop: Rc<RefCell<Option<oneshot::Sender<()>>>>,
+}
+
+fn show_run(buf: &mut TextBuffer, run: &BenchRun) {
+    let config = &run.config.0;
+    buf.set_text(&format!(
+        "{} {}\n{}  concurrency {}  requests {}  duration {} s  rate {}/s\n\n{}",
+        run.method,
+        run.uri,
+        run.ran_at.format("%Y-%m-%d %H:%M:%S"),
+        config.concurrency,
+        config.requests,
+        config.duration_s,
+        config.rate,
+        run.report.0.display()
+    ));
+}
+
+impl BenchWindow {
+    pub fn new(target: Target) -> Self {
+        let id = next_window_id();
+
+        let mut win = window::DoubleWindow::default()
+            .with_size(1200, 800)
+            .with_label(&format!("Le Grillon - Load test {}", target.uri));
+
+        let mut col = group::Flex::default_fill().column();
+        let mut row = group::Flex::default().row();
+        let concurrency_label = frame::Frame::default().with_label("Concurrency:");
+        row.fixed(&concurrency_label, 96);
+        let mut concurrency_input = input::IntInput::default();
+        concurrency_input.set_value("10");
+        row.fixed(&concurrency_input, 64);
+        let requests_label = frame::Frame::default().with_label("Requests:");
+        row.fixed(&requests_label, 80);
+        let mut requests_input = input::IntInput::default();
+        requests_input.set_value("100");
+        requests_input.set_tooltip("0 runs until the duration is over");
+        row.fixed(&requests_input, 80);
+        let duration_label = frame::Frame::default().with_label("Duration s:");
+        row.fixed(&duration_label, 88);
+        let mut duration_input = input::FloatInput::default();
+        duration_input.set_value("0");
+        duration_input.set_tooltip("0 runs until all requests are sent");
+        row.fixed(&duration_input, 64);
+        let rate_label = frame::Frame::default().with_label("Rate/s:");
+        row.fixed(&rate_label, 64);
+        let mut rate_input = input::FloatInput::default();
+        rate_input.set_value("0");
+        rate_input.set_tooltip("Requests per second over all workers, 0 is unlimited");
+        row.fixed(&rate_input, 64);
+        let mut run_btn = button::Button::default().with_label("Run");
+        row.fixed(&run_btn, 96);
+        let mut status = frame::Frame::default();
+        status.set_frame(enums::FrameType::FlatBox);
+        row.end();
+        col.fixed(&row, 32);
+
+        let mut row = group::Flex::default_fill().row();
+        let mut list = browser::HoldBrowser::default();
+        list.set_column_char('\t');
+        list.set_column_widths(&[150, 48, 64, 80, 64, 64]);
+        list.set_tooltip("Run, concurrency, requests, req/s, p50 ms, p99 ms, errors");
+        row.fixed(&list, 540);
+        let report_buf = text::TextBuffer::default();
+        let mut report = text::TextDisplay::default();
+        report.set_buffer(report_buf.clone());
+        report.set_text_font(enums::Font::Courier);
+        row.end();
+        col.end();
+
+        win.end();
+        win.make_resizable(true);
+        win.show();
+
+        let runs: Rc<RefCell<Vec<BenchRun>>> = Rc::new(RefCell::new(Vec::new()));
+        let (s, _) = app::channel::<GlobalAppMsg>();
+        s.send(GlobalAppMsg::LoadBenchRuns(id, target.uri.clone()));
+
+        let p_runs = runs.clone();
+        let mut p_report_buf = report_buf.clone();
+        list.set_callback(move |l| {
+            let line = l.value();
+            if line < 1 {
+                return;
+            }
+            if let Some(run) = p_runs.borrow().get(line as usize - 1) {
+                show_run(&mut p_report_buf, run);
+            }
+        });
+
+        let running = Arc::new(AtomicBool::new(false));
+        let stop: Rc<RefCell<Option<oneshot::Sender<()>>>> = Rc::new(RefCell::new(None));
+        let p_stop = stop.clone();
+        let p_report_buf = report_buf.clone();
+        run_btn.set_callback(move |btn| {
+            if running.load(Ordering::SeqCst) {
+                if let Some(tx) = p_stop.borrow_mut().take() {
+                    let _ = tx.send(());
+                }
+                return;
+            }
+
+            let config = bench::Config {
+                concurrency: concurrency_input.value().parse().unwrap_or(0),
+                requests: requests_input.value().parse().unwrap_or(0),
+                duration_s: duration_input.value().parse().unwrap_or(0.0),
+                rate: rate_input.value().parse().unwrap_or(0.0),
+            };
+            if let Err(e) = config.validate() {
+                status.set_label(&e);
+                return;
+            }
+
+            let (stop_tx, stop_rx) = oneshot::channel();
+            *p_stop.borrow_mut() = Some(stop_tx);
+            running.store(true, Ordering::SeqCst);
+            btn.set_label("Stop");
+            status.set_label("Running...");
+
+            let target = target.clone();
+            let running = running.clone();
+            let mut btn = btn.clone();
+            let mut status = status.clone();
+            let mut report_buf = p_report_buf.clone();
+            tokio::spawn(async move {
+                let mut progress = status.clone();
+                let report = bench::run(
+                    target.clone(),
+                    config.clone(),
+                    |done| {
+                        progress.set_label(&format!("{done} requests done"));
+                        app::awake();
+                    },
+                    stop_rx,
+                )
+                .await;
+
+                let run = BenchRun {
+                    ran_at: chrono::Local::now(),
+                    method: target.method.to_string(),
+                    uri: target.uri,
+                    config: sqlx::types::Json(config),
+                    report: sqlx::types::Json(report),
+                };
+                show_run(&mut report_buf, &run);
+                status.set_label(&format!(
+                    "{} requests | {:.1} req/s | p99 {:.1} ms",
+                    run.report.requests, run.report.throughput, run.report.p99_ms
+                ));
+                s.send(GlobalAppMsg::SaveBenchRun(id, run));
+
+                running.store(false, Ordering::SeqCst);
+                btn.set_label("Run");
+                app::awake();
+                app::redraw();
+            });
+        });
+
+        win.handle(move |_, e| {
+            if e == Event::Hide {
+                tracing::debug!("window {id} (BenchWindow) closed");
+                s.send(GlobalAppMsg::CloseWindow(id));
+                return true;
+            }
+
+            false
+        });
+
+        Self {
+            id,
+            window: win,
+            runs,
+            list,
+            report_buf,
+            stop,
+        }
+    }
+}
+
+impl HasId for BenchWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for BenchWindow {
+    fn close(&mut self) {
+        if let Some(tx) = self.stop.borrow_mut().take() {
+            let _ = tx.send(());
+        }
+        self.window.hide();
+    }
+
+    fn bench_runs_loaded(&mut self, runs: &[BenchRun]) {
+        self.list.clear();
+        for run in runs {
+            let report = &run.report.0;
+            self.list.add(&format!(
+                "{}\t{}\t{}\t{:.1}\t{:.1}\t{:.1}\t{}",
+                run.ran_at.format("%Y-%m-%d %H:%M:%S"),
+                run.config.0.concurrency,
+                report.requests,
+                report.throughput,
+                report.p50_ms,
+                report.p99_ms,
+                report.errors
+            ));
+        }
+        *self.runs.borrow_mut() = runs.to_vec();
+        if self.report_buf.length() == 0
+            && let Some(latest) = runs.first()
+        {
+            show_run(&mut self.report_buf, latest);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::rc::Rc;
+
+use fltk::{
+    app, browser, button, dialog,
+    enums::Event,
+    frame, group,
+    prelude::*,
+    window::{self, DoubleWindow},
+};
+
+use crate::{
+    AppWindow, GlobalAppMsg, HasId, controls,
+    db::{Collection, CollectionRequest, OpenWindow},
+    http_file, next_window_id,
+};
+
+pub struct CollectionsWindow {
+    id: usize,
+    window: DoubleWindow,
+}
+
+/// Lists collections with their folders and requests. Browser lines are 1-based, each
+/// line knows its collection, `None` marks collection/folder headings.
+pub(crate) fn fill_collections(
+    list: &mut browser::HoldBrowser,
+    collections: &[(Collection, Vec<CollectionRequest>)],
+) -> Vec<(usize, Option<CollectionRequest>)> {
+    list.clear();
+    let mut lines: Vec<(usize, Option<CollectionRequest>)> = Vec::new();
+    for (idx, (c, requests)) in collections.iter().enumerate() {
+        list.add(&format!("@b{}", c.name));
+        lines.push((idx, None));
+
+        let mut folder = "";
+        for r in requests {
+            if r.folder != folder {
+                folder = r.folder.as_str();
+                list.add(&format!("@i    {folder}"));
+                lines.push((idx, None));
+            }
+            let indent = if folder.is_empty() {
+                "    "
+            } else {
+                "        "
+            };
+            list.add(&format!("{indent}{}  {}", r.method, r.name));
+            lines.push((idx, Some(r.clone())));
+        }
+    }
+    lines
+}
+
+/// Opens the request behind a line of [`fill_collections`].
+pub(crate) fn restore_request(request: &CollectionRequest) -> GlobalAppMsg {
+    GlobalAppMsg::Restore(OpenWindow::new_from(
+        &request.method,
+        &request.uri,
+        &request.headers,
+        &request.body,
+        Some(request.collection_id),
+    ))
+}
+
+impl CollectionsWindow {
+    pub fn new(collections: &[(Collection, Vec<CollectionRequest>)]) -> Self {
+        let id = next_window_id();
+
+        let mut win = window::DoubleWindow::default()
+            .with_size(600, 700)
+            .with_label("Collections");
+
+        let mut col = group::Flex::default_fill().column();
+        let mut list = browser::HoldBrowser::default();
+        let mut row = group::Flex::default().row();
+        let mut open_btn = button::Button::default().with_label("Open");
+        row.fixed(&open_btn, 128);
+        let mut save_http_btn = button::Button::default().with_label("Save as .http...");
+        row.fixed(&save_http_btn, 192);
+        frame::Frame::default();
+        row.end();
+        col.fixed(&row, 32);
+        col.end();
+
+        win.end();
+        win.make_resizable(true);
+        win.show();
+
+        let lines = Rc::new(fill_collections(&mut list, collections));
+        let collections = Rc::new(collections.to_vec());
+        let (s, _) = app::channel::<GlobalAppMsg>();
+
+        let open = {
+            let list = list.clone();
+            let lines = lines.clone();
+            move || {
+                let line = list.value();
+                if line < 1 {
+                    return;
+                }
+                if let Some((_, Some(r))) = lines.get(line as usize - 1) {
+                    s.send(restore_request(r));
+                }
+            }
+        };
+
+        let open_ptr = Rc::new(open);
+        let open_cl = open_ptr.clone();
+        list.set_callback(move |_| {
+            if app::event_clicks() {
+                open_cl();
+            }
+        });
+        open_btn.set_callback(move |_| open_ptr());
+
+        let p_list = list.clone();
+        save_http_btn.set_callback(move |_| {
+            let line = p_list.value();
+            let Some((idx, _)) = lines.get((line.max(1) - 1) as usize) else {
+                return;
+            };
+            let (collection, requests) = &collections[*idx];
+            let preset = format!("{}.http", collection.name.trim_end_matches(".http"));
+            let Some(path) = controls::pick_save_file("*.{http,rest}", &preset) else {
+                return;
+            };
+            if let Err(e) = std::fs::write(&path, http_file::write(collection, requests)) {
+                dialog::alert_default(&format!("Could not save {}:\n{e}", path.display()));
+            }
+        });
+
+        let p_sender = s;
+        win.handle(move |_, e| {
+            if e == Event::Hide {
+                p_sender.send(GlobalAppMsg::CloseWindow(id));
+                return true;
+            }
+
+            false
+        });
+
+        Self { id, window: win }
+    }
+}
+
+impl HasId for CollectionsWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for CollectionsWindow {
+    fn close(&mut self) {
+        self.window.hide();
+    }
+}
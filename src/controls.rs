@@ -1,69 +1,301 @@
+use std::path::PathBuf;
+
 use fltk::{
-    app::Sender,
-    button,
-    enums::Event,
-    group, image,
+    app::{self, Sender},
+    button, dialog,
+    enums::{Event, Key, Shortcut},
+    group::{self, Flex, Tabs},
+    image,
+    menu::{self, MenuFlag},
     prelude::*,
     window::{self, SingleWindow},
 };
 
-use crate::{AppWindow, GlobalAppMsg, HasId, next_window_id};
+use crate::{
+    AppWindow, GlobalAppMsg, HasId, next_window_id,
+    shortcuts::{self, Action, Binding, KeyCode},
+    sidebar::SidebarCtrl,
+};
 
+/// The toolbar window. With the tabbed layout it is the main window holding the sidebar
+/// and the request tabs.
 pub struct MainControls {
     global_msg_sender: Sender<GlobalAppMsg>,
     window_ptr: Option<SingleWindow>,
     id: usize,
+    tabs: Option<Tabs>,
+    sidebar: Option<SidebarCtrl>,
 }
 
 impl MainControls {
-    pub fn new(global_msg_sender: Sender<GlobalAppMsg>) -> Self {
+    pub fn new(global_msg_sender: Sender<GlobalAppMsg>, tabbed: bool) -> Self {
         let mut self_ = MainControls {
             global_msg_sender,
             window_ptr: None,
             id: next_window_id(),
+            tabs: None,
+            sidebar: None,
         };
-        self_.setup();
+        self_.setup(tabbed);
         self_
     }
 
-    fn setup(&mut self) {
+    /// Where requests open when the tabbed layout is on.
+    pub fn tabs(&self) -> Option<&Tabs> {
+        self.tabs.as_ref()
+    }
+
+    pub fn sidebar(&mut self) -> Option<&mut SidebarCtrl> {
+        self.sidebar.as_mut()
+    }
+
+    fn setup(&mut self, tabbed: bool) {
+        let (w, h) = if tabbed { (1600, 1000) } else { (1100, 64) };
         let mut ctrl_window = window::SingleWindow::default()
-            .with_size(600, 64)
+            .with_size(w, h)
             .with_label("Le Grillon");
 
+        let mut col = Flex::default_fill().column();
         let row = group::Flex::default_fill().row();
 
         let mut new_req_window_button = button::Button::default().with_label("New 🦗");
         new_req_window_button.set_label_size(32);
-        let p_sender = self.global_msg_sender.clone();
+        let p_sender = self.global_msg_sender;
         new_req_window_button.set_callback(move |_| {
             p_sender.send(GlobalAppMsg::OpenEmptyWindow);
         });
+
+        let mut ws_button = button::Button::default().with_label("WebSocket");
+        let p_sender = self.global_msg_sender;
+        ws_button.set_callback(move |_| {
+            p_sender.send(GlobalAppMsg::OpenWebSocketWindow);
+        });
+
+        let mut grpc_button = button::Button::default().with_label("gRPC");
+        let p_sender = self.global_msg_sender;
+        grpc_button.set_callback(move |_| {
+            p_sender.send(GlobalAppMsg::OpenGrpcWindow);
+        });
+
+        let mut collections_button = button::Button::default().with_label("Collections");
+        let p_sender = self.global_msg_sender;
+        collections_button.set_callback(move |_| {
+            p_sender.send(GlobalAppMsg::OpenCollections);
+        });
+
+        let mut import_menu = menu::MenuButton::default().with_label("Import");
+        let p_sender = self.global_msg_sender;
+        import_menu.add(
+            "Postman collection...",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                if let Some(path) = pick_file("*.json") {
+                    p_sender.send(GlobalAppMsg::ImportPostmanCollection(path));
+                }
+            },
+        );
+        let p_sender = self.global_msg_sender;
+        import_menu.add(
+            "Postman environment...",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                if let Some(path) = pick_file("*.json") {
+                    p_sender.send(GlobalAppMsg::ImportPostmanEnvironment(path));
+                }
+            },
+        );
+        let p_sender = self.global_msg_sender;
+        import_menu.add(
+            "HAR archive...",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                if let Some(path) = pick_file("*.har") {
+                    p_sender.send(GlobalAppMsg::ImportHar(path));
+                }
+            },
+        );
+
+        let p_sender = self.global_msg_sender;
+        import_menu.add(
+            "OpenAPI file...",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                if let Some(path) = pick_file("*.{json,yaml,yml}") {
+                    p_sender.send(GlobalAppMsg::ImportOpenApi(path.display().to_string()));
+                }
+            },
+        );
+        let p_sender = self.global_msg_sender;
+        import_menu.add(
+            "OpenAPI from URL...",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                if let Some(url) = dialog::input_default(
+                    "OpenAPI document URL:",
+                    "http://localhost:8080/openapi.json",
+                ) && !url.trim().is_empty()
+                {
+                    p_sender.send(GlobalAppMsg::ImportOpenApi(url.trim().to_string()));
+                }
+            },
+        );
+
+        let p_sender = self.global_msg_sender;
+        import_menu.add(
+            "HTTP file (.http, .rest)...",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                if let Some(path) = pick_file("*.{http,rest}") {
+                    p_sender.send(GlobalAppMsg::ImportHttpFile(path));
+                }
+            },
+        );
+
+        let mut history_button = button::Button::default().with_label("History");
+        let p_sender = self.global_msg_sender;
+        history_button.set_callback(move |_| {
+            p_sender.send(GlobalAppMsg::OpenHistory);
+        });
+
+        let mut mock_button = button::Button::default().with_label("Mock server");
+        let p_sender = self.global_msg_sender;
+        mock_button.set_callback(move |_| {
+            p_sender.send(GlobalAppMsg::OpenMockServer);
+        });
+
+        let mut proxy_button = button::Button::default().with_label("Proxy");
+        let p_sender = self.global_msg_sender;
+        proxy_button.set_callback(move |_| {
+            p_sender.send(GlobalAppMsg::OpenProxy);
+        });
+
+        let mut logs_button = button::Button::default().with_label("Logs");
+        let p_sender = self.global_msg_sender;
+        logs_button.set_callback(move |_| {
+            p_sender.send(GlobalAppMsg::OpenLogs);
+        });
         row.end();
 
+        if tabbed {
+            col.fixed(&row, 64);
+            let mut body = Flex::default_fill().row();
+            self.sidebar = Some(SidebarCtrl::new(&mut body, self.global_msg_sender));
+            let tabs = Tabs::default_fill();
+            tabs.end();
+            body.end();
+            self.tabs = Some(tabs);
+        }
+        col.end();
+
         ctrl_window.end();
+        ctrl_window.make_resizable(tabbed);
         ctrl_window.show();
 
-        let loadimg = image::JpegImage::load("./assets/legrillon.jpg");
-        if let Ok(image) = loadimg {
-            ctrl_window.set_icon(Some(image));
-        } else {
-            print!("IMGERR= {loadimg:?}");
+        match image::JpegImage::load("./assets/legrillon.jpg") {
+            Ok(image) => ctrl_window.set_icon(Some(image)),
+            Err(e) => tracing::warn!("could not load the window icon: {e}"),
         }
-        let p_sender = self.global_msg_sender.clone();
-        ctrl_window.handle(move |_, e| {
-            if e == Event::Hide {
+        let p_sender = self.global_msg_sender;
+        ctrl_window.handle(move |_, e| match e {
+            Event::Hide => {
                 p_sender.send(GlobalAppMsg::CloseApp);
                 true
-            } else {
-                false
             }
+            // keys the focused widget, e.g. a request tab, did not use
+            Event::KeyDown => match pressed_shortcut() {
+                Some(action) => {
+                    p_sender.send(GlobalAppMsg::RunAction(None, action));
+                    true
+                }
+                None => false,
+            },
+            _ => false,
         });
 
         self.window_ptr = Some(ctrl_window);
     }
 }
 
+pub(crate) fn pick_file(filter: &str) -> Option<PathBuf> {
+    let mut chooser = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
+    chooser.set_filter(filter);
+    chooser.show();
+
+    chosen_path(&chooser)
+}
+
+pub(crate) fn pick_files(filter: &str) -> Vec<PathBuf> {
+    let mut chooser =
+        dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseMultiFile);
+    chooser.set_filter(filter);
+    chooser.show();
+
+    chooser
+        .filenames()
+        .into_iter()
+        .filter(|p| !p.as_os_str().is_empty())
+        .collect()
+}
+
+pub(crate) fn pick_save_file(filter: &str, preset: &str) -> Option<PathBuf> {
+    let mut chooser = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseSaveFile);
+    chooser.set_filter(filter);
+    chooser.set_preset_file(preset);
+    chooser.set_option(dialog::NativeFileChooserOptions::SaveAsConfirm);
+    chooser.show();
+
+    chosen_path(&chooser)
+}
+
+/// The action bound to the key of the keyboard event being handled.
+pub(crate) fn pressed_shortcut() -> Option<Action> {
+    let key = app::event_key();
+    let code = if key == Key::Enter || key == Key::KPEnter {
+        KeyCode::Enter
+    } else if key == Key::Escape {
+        KeyCode::Escape
+    } else if key == Key::Tab {
+        KeyCode::Tab
+    } else if (Key::F1.bits()..=Key::F12.bits()).contains(&key.bits()) {
+        KeyCode::Function((key.bits() - Key::F1.bits() + 1) as u8)
+    } else {
+        KeyCode::Char(key.to_char()?.to_ascii_lowercase())
+    };
+    shortcuts::keymap().action(&Binding {
+        ctrl: app::is_event_ctrl(),
+        shift: app::is_event_shift(),
+        alt: app::is_event_alt(),
+        key: code,
+    })
+}
+
+/// Index of the shown tab, kept with the window layout.
+pub(crate) fn active_tab(tabs: &Tabs) -> i32 {
+    tabs.value().map_or(0, |g| tabs.find(&g))
+}
+
+/// Shows tab `index`, out of range indices keep the first tab.
+pub(crate) fn select_tab(tabs: &mut Tabs, index: i32) {
+    if let Some(group) = tabs.child(index).and_then(|w| w.as_group()) {
+        let _ = tabs.set_value(&group);
+    }
+}
+
+fn chosen_path(chooser: &dialog::NativeFileChooser) -> Option<PathBuf> {
+    let path = chooser.filename();
+    if path.as_os_str().is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
 impl HasId for MainControls {
     fn id(&self) -> usize {
         self.id
@@ -1,23 +1,50 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Display,
+    path::Path,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
 
 use fltk::app::{self, Sender};
+use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Sqlite, SqlitePool, migrate::MigrateDatabase};
+use tokio::task::JoinSet;
 
-use crate::{GlobalAppMsg, WINDOW_ID_COUNTER};
+use crate::{
+    GlobalAppMsg, WINDOW_ID_COUNTER, bench,
+    fs_store::FsStore,
+    graphql, har, http_file, openapi, postman,
+    secrets::{self, SecretFlags, Vault, VaultHeader},
+    settings::Settings,
+    vars::VariableScopes,
+};
 
-#[derive(Debug, sqlx::FromRow)]
-struct SentRequest {
-    id: i64,
-    sent_at: chrono::DateTime<chrono::Local>,
-    method: String,
-    uri: String,
-    path: String,
-    query: String,
-    headers: sqlx::types::Json<Vec<(String, String)>>,
-    body: String,
+/// Most recent history entries listed in the sidebar of the tabbed layout.
+pub const SIDEBAR_HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SentRequest {
+    pub id: i64,
+    pub sent_at: chrono::DateTime<chrono::Local>,
+    pub method: String,
+    pub uri: String,
+    pub path: String,
+    pub query: String,
+    pub headers: sqlx::types::Json<Vec<(String, String)>>,
+    pub body: String,
+    pub status: i64,
+    pub response_headers: sqlx::types::Json<Vec<(String, String)>>,
+    pub response_body: String,
+    pub wait_ms: f64,
+    pub receive_ms: f64,
+    /// Connection phases, zero when not measured.
+    pub dns_ms: f64,
+    pub connect_ms: f64,
+    pub tls_ms: f64,
 }
 
-#[derive(Debug, sqlx::FromRow)]
+#[derive(Debug, Clone, sqlx::FromRow)]
 pub struct OpenWindow {
     pub id: i32,
     pub method: String,
@@ -26,48 +53,775 @@ pub struct OpenWindow {
     pub query: String,
     pub headers: sqlx::types::Json<Vec<(String, String)>>,
     pub body: String,
+    pub environment_id: Option<i64>,
+    pub collection_id: Option<i64>,
+    /// Which window restores this state, one of the `WINDOW_KIND_*` constants.
+    pub kind: String,
+    /// Settings only some kinds of windows have, e.g. WebSocket subprotocols.
+    pub options: sqlx::types::Json<BTreeMap<String, String>>,
+    pub layout: sqlx::types::Json<WindowLayout>,
+    /// The response shown when the application was closed, `None` keeps the saved one.
+    pub response: Option<sqlx::types::Json<SentRequest>>,
+    /// Title given by the user, empty shows the URI instead.
+    pub name: String,
+}
+
+/// Geometry and view state of a window, a zero size means the default size.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowLayout {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    /// Share of the width taken by the request parameters, zero means half.
+    pub split: f64,
+    pub params_tab: i32,
+    pub result_tab: i32,
+}
+
+pub const WINDOW_KIND_HTTP: &str = "http";
+pub const WINDOW_KIND_WEBSOCKET: &str = "websocket";
+pub const WINDOW_KIND_GRPC: &str = "grpc";
+
+impl OpenWindow {
+    /// State for a window that is not open yet, e.g. one opened from a collection or the history.
+    pub fn new_from(
+        method: &str,
+        uri: &str,
+        headers: &[(String, String)],
+        body: &str,
+        collection_id: Option<i64>,
+    ) -> Self {
+        OpenWindow {
+            id: crate::next_window_id() as i32,
+            method: method.to_string(),
+            uri: uri.to_string(),
+            path: "".to_string(),
+            query: "".to_string(),
+            headers: sqlx::types::Json(headers.to_vec()),
+            body: body.to_string(),
+            environment_id: None,
+            collection_id,
+            kind: WINDOW_KIND_HTTP.to_string(),
+            options: sqlx::types::Json(BTreeMap::new()),
+            layout: sqlx::types::Json(WindowLayout::default()),
+            response: None,
+            name: String::new(),
+        }
+    }
+
+    /// Copy for a new window, placed a little below the original.
+    pub fn duplicate(&self) -> Self {
+        let mut layout = self.layout.0.clone();
+        if layout.w > 0 {
+            layout.x += 32;
+            layout.y += 32;
+        }
+        let name = match self.name.as_str() {
+            "" => String::new(),
+            name => format!("{name} (copy)"),
+        };
+        OpenWindow {
+            id: crate::next_window_id() as i32,
+            layout: sqlx::types::Json(layout),
+            name,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub variables: sqlx::types::Json<Vec<(String, String)>>,
+    /// Variables marked as secret or not, the others are told by their name.
+    pub secrets: sqlx::types::Json<SecretFlags>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CollectionRequest {
+    pub id: i64,
+    pub collection_id: i64,
+    pub folder: String,
+    pub name: String,
+    pub method: String,
+    pub uri: String,
+    pub headers: sqlx::types::Json<Vec<(String, String)>>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Environment {
+    pub id: i64,
+    pub name: String,
+    pub variables: sqlx::types::Json<Vec<(String, String)>>,
+    /// Variables marked as secret or not, the others are told by their name.
+    pub secrets: sqlx::types::Json<SecretFlags>,
+    /// Label colour as `#rrggbb`, e.g. red for production, empty for none.
+    pub color: String,
+    /// Only requests with safe methods, e.g. GET, are sent in this environment.
+    pub read_only: bool,
+}
+
+/// A finished load test, kept to compare runs against the same URI.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BenchRun {
+    pub ran_at: chrono::DateTime<chrono::Local>,
+    pub method: String,
+    pub uri: String,
+    pub config: sqlx::types::Json<bench::Config>,
+    pub report: sqlx::types::Json<bench::Report>,
+}
+
+/// A canned response of the mock server, see [`crate::mock`] for the path patterns.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MockRoute {
+    pub id: i64,
+    pub method: String,
+    pub path: String,
+    pub status: i64,
+    pub headers: sqlx::types::Json<Vec<(String, String)>>,
+    pub body: String,
+    pub delay_ms: i64,
+}
+
+impl MockRoute {
+    /// Replays a response from the history, headers that describe the original transfer
+    /// are dropped because the body is stored decoded.
+    pub fn from_sent(sent: &SentRequest) -> Self {
+        let path = reqwest::Url::parse(&sent.uri)
+            .map(|u| u.path().to_string())
+            .unwrap_or_else(|_| sent.uri.clone());
+        let skip = [
+            "content-length",
+            "transfer-encoding",
+            "content-encoding",
+            "connection",
+            "date",
+        ];
+
+        MockRoute {
+            id: 0,
+            method: sent.method.clone(),
+            path,
+            status: if sent.status == 0 { 200 } else { sent.status },
+            headers: sqlx::types::Json(
+                sent.response_headers
+                    .0
+                    .iter()
+                    .filter(|(n, _)| !skip.contains(&n.to_ascii_lowercase().as_str()))
+                    .cloned()
+                    .collect(),
+            ),
+            body: sent.response_body.clone(),
+            delay_ms: 0,
+        }
+    }
+}
+
+/// How a re-imported collection differs from the stored one.
+#[derive(Debug, Default)]
+pub struct CollectionDiff {
+    pub added: Vec<CollectionRequest>,
+    pub changed: Vec<CollectionRequest>,
+    pub removed: Vec<i64>,
+    pub unchanged: usize,
 }
 
 pub struct LeGrillonDb {
     pool: Pool<Sqlite>,
     global: Sender<GlobalAppMsg>,
+    /// When set, collections live on disk and the database only keeps local state.
+    fs: Option<FsStore>,
+    /// Messages still being handled, waited for on shutdown.
+    tasks: Mutex<JoinSet<()>>,
+    /// Encrypts secrets once the master passphrase was given, see [`LeGrillonDb::unlock_secrets`].
+    vault: RwLock<Option<Vault>>,
+    /// Secrets are encrypted, until the vault is unlocked new ones can't be stored.
+    encrypt_secrets: bool,
+    /// Calls whose failure was shown to the user, see [`LeGrillonDb::report`].
+    failing: Mutex<BTreeSet<String>>,
+    /// Collection files last reported as unreadable, see [`LeGrillonDb::report_skipped`].
+    skipped_files: Mutex<Vec<String>>,
 }
 
 const DB_URL: &str = "sqlite://sqlite.db";
+const META_SECRETS_SALT: &str = "secrets_salt";
+const META_SECRETS_CHECK: &str = "secrets_check";
+const META_HISTORY_REDACTED: &str = "history_redacted";
+/// Longest wait for pending writes when the application quits.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
 impl LeGrillonDb {
-    pub async fn new() -> Self {
+    pub async fn new(settings: &Settings) -> Self {
         if !Sqlite::database_exists(DB_URL).await.unwrap_or(false) {
-            println!("Creating database {}", DB_URL);
+            tracing::info!("creating database {DB_URL}");
             match Sqlite::create_database(DB_URL).await {
-                Ok(_) => println!("Create db success"),
+                Ok(_) => tracing::info!("database created"),
                 Err(error) => panic!("error: {}", error),
             };
         } else {
-            println!("Database already exists");
+            tracing::debug!("database {DB_URL} exists");
         }
 
         let pool = SqlitePool::connect(DB_URL).await.unwrap();
 
-        Self::setup(&pool).await;
-
         let (global, _) = app::channel();
 
-        Self { pool, global }
+        let fs = settings.collections_dir.clone().map(FsStore::new);
+        let db = Self {
+            pool,
+            global,
+            fs,
+            tasks: Mutex::new(JoinSet::new()),
+            vault: RwLock::new(None),
+            encrypt_secrets: settings.encrypt_secrets,
+            failing: Mutex::new(BTreeSet::new()),
+            skipped_files: Mutex::new(Vec::new()),
+        };
+        db.setup().await;
+        db.setup_fs_store().await;
+        db.redact_history().await;
+        db
+    }
+
+    /// Logs the outcome of a call and shows a failure to the user, only once until the same
+    /// call succeeds again, e.g. not on every autosave while the disk is full.
+    fn report<T, E: Display>(&self, what: &str, result: Result<T, E>) -> Option<T> {
+        log_result(what, &result);
+        let mut failing = self.failing.lock().unwrap();
+        match result {
+            Ok(value) => {
+                failing.remove(what);
+                Some(value)
+            }
+            Err(e) => {
+                if failing.insert(what.to_string()) {
+                    self.global
+                        .send(GlobalAppMsg::Notify(format!("Could not {what}:\n{e}")));
+                }
+                None
+            }
+        }
+    }
+
+    /// Tells the user about collection files that could not be read, e.g. after a `git pull`
+    /// left a merge conflict in one. Only when they differ from the last ones reported, as
+    /// collections are loaded often.
+    fn report_skipped(&self, skipped: Vec<String>) {
+        let mut reported = self.skipped_files.lock().unwrap();
+        if *reported == skipped {
+            return;
+        }
+        for e in &skipped {
+            tracing::warn!("skipped {e}");
+        }
+        if !skipped.is_empty() {
+            self.global.send(GlobalAppMsg::Notify(format!(
+                "Skipped {} collection file(s) that could not be read:\n{}",
+                skipped.len(),
+                skipped.join("\n")
+            )));
+        }
+        *reported = skipped;
+    }
+
+    /// Encrypts the secret values among `pairs` before they are stored. While the secrets
+    /// are locked a new secret is refused rather than stored in clear, encrypted ones are
+    /// kept as they are.
+    fn seal(&self, pairs: &mut [(String, String)]) -> Result<(), sqlx::Error> {
+        self.seal_variables(pairs, &SecretFlags::new())
+    }
+
+    /// [`Self::seal`] for variables, with their secret flags.
+    fn seal_variables(
+        &self,
+        pairs: &mut [(String, String)],
+        flags: &SecretFlags,
+    ) -> Result<(), sqlx::Error> {
+        if let Some(vault) = self.vault.read().unwrap().as_ref() {
+            vault.seal(pairs, flags);
+            return Ok(());
+        }
+        let plain = pairs.iter().find(|(name, value)| {
+            secrets::is_secret_variable(name, value, flags) && !secrets::is_encrypted(value)
+        });
+        match plain {
+            Some((name, _)) if self.encrypt_secrets => Err(sqlx::Error::InvalidArgument(format!(
+                "the secrets are locked, {name} can't be encrypted until the master passphrase \
+                 is entered on the next start"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Decrypts stored secret values, they stay encrypted while the secrets are locked.
+    fn unseal(&self, pairs: &mut [(String, String)]) {
+        if let Some(vault) = self.vault.read().unwrap().as_ref() {
+            vault.open(pairs);
+        }
+    }
+
+    async fn meta(&self, key: &str) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "
+            SELECT value FROM Meta WHERE key = ?
+        ",
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn set_meta(&self, key: &str, value: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "
+            INSERT INTO Meta (key, value) VALUES (?1, ?2)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value
+        ",
+        )
+        .bind(key)
+        .bind(value)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Whether a master passphrase was chosen before.
+    pub async fn has_master_passphrase(&self) -> bool {
+        matches!(self.meta(META_SECRETS_SALT).await, Ok(Some(_)))
+    }
+
+    /// Unlocks the secrets, the first call chooses the master passphrase. Secrets stored
+    /// in clear until then are encrypted.
+    pub async fn unlock_secrets(&self, passphrase: &str) -> Result<(), String> {
+        let salt = self
+            .meta(META_SECRETS_SALT)
+            .await
+            .map_err(|e| e.to_string())?;
+        let check = self
+            .meta(META_SECRETS_CHECK)
+            .await
+            .map_err(|e| e.to_string())?;
+        let vault = match (salt, check) {
+            (Some(salt), Some(check)) => Vault::unlock(passphrase, &VaultHeader { salt, check })?,
+            _ => {
+                let (vault, header) = Vault::create(passphrase)?;
+                self.set_meta(META_SECRETS_CHECK, &header.check)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                // written last, as it marks the passphrase as chosen
+                self.set_meta(META_SECRETS_SALT, &header.salt)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                vault
+            }
+        };
+        *self.vault.write().unwrap() = Some(vault);
+
+        let sealed = self.seal_stored_secrets().await;
+        log_result("encrypt the stored secrets", &sealed);
+        sealed.map_err(|e| e.to_string())
+    }
+
+    async fn seal_stored_secrets(&self) -> Result<(), sqlx::Error> {
+        for table in ["Environments", "Collections"] {
+            let rows = sqlx::query_as::<
+                _,
+                (
+                    i64,
+                    Option<sqlx::types::Json<Vec<(String, String)>>>,
+                    sqlx::types::Json<SecretFlags>,
+                ),
+            >(&format!("SELECT id, variables, secrets FROM {table}"))
+            .fetch_all(&self.pool)
+            .await?;
+            for (id, variables, flags) in rows {
+                let Some(mut variables) = variables else {
+                    continue;
+                };
+                let before = variables.0.clone();
+                let _ = self.seal_variables(&mut variables.0, &flags.0);
+                if variables.0 != before {
+                    sqlx::query(&format!("UPDATE {table} SET variables = ?1 WHERE id = ?2"))
+                        .bind(&variables)
+                        .bind(id)
+                        .execute(&self.pool)
+                        .await?;
+                }
+            }
+        }
+        for (table, column) in [
+            ("CollectionRequests", "headers"),
+            ("OpenWindows", "headers"),
+        ] {
+            self.rewrite_pairs(table, column, |pairs| {
+                let _ = self.seal(pairs);
+            })
+            .await?;
+        }
+
+        let local = sqlx::query_as::<_, (i64, String, String)>(
+            "
+            SELECT rowid, name, value FROM LocalSecrets
+        ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for (rowid, name, value) in local {
+            // only secrets are kept there, whatever their name
+            let flags = SecretFlags::from([(name.clone(), true)]);
+            let mut pair = [(name, value)];
+            self.seal_variables(&mut pair, &flags)?;
+            let [(_, sealed)] = pair;
+            sqlx::query(
+                "
+                UPDATE LocalSecrets SET value = ?1 WHERE rowid = ?2
+            ",
+            )
+            .bind(sealed)
+            .bind(rowid)
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Applies `change` to a column of name/value pairs in every row of `table`.
+    async fn rewrite_pairs(
+        &self,
+        table: &str,
+        column: &str,
+        change: impl Fn(&mut Vec<(String, String)>),
+    ) -> Result<(), sqlx::Error> {
+        let rows = sqlx::query_as::<_, (i64, Option<sqlx::types::Json<Vec<(String, String)>>>)>(
+            &format!("SELECT id, {column} FROM {table}"),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for (id, pairs) in rows {
+            let Some(mut pairs) = pairs else {
+                continue;
+            };
+            let before = pairs.0.clone();
+            change(&mut pairs.0);
+            if pairs.0 != before {
+                sqlx::query(&format!("UPDATE {table} SET {column} = ?1 WHERE id = ?2"))
+                    .bind(&pairs)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes credentials from history entries recorded before they were redacted, once.
+    async fn redact_history(&self) {
+        if matches!(self.meta(META_HISTORY_REDACTED).await, Ok(Some(_))) {
+            return;
+        }
+        let redact = |pairs: &mut Vec<(String, String)>| *pairs = secrets::redact(pairs);
+        let mut redacted = self.rewrite_pairs("SentRequest", "headers", redact).await;
+        if redacted.is_ok() {
+            redacted = self
+                .rewrite_pairs("SentRequest", "response_headers", redact)
+                .await;
+        }
+        if redacted.is_ok() {
+            redacted = self.redact_history_uris().await;
+        }
+        if redacted.is_ok() {
+            redacted = self.set_meta(META_HISTORY_REDACTED, "1").await;
+        }
+        self.report("redact the history", redacted);
+    }
+
+    async fn redact_history_uris(&self) -> Result<(), sqlx::Error> {
+        let rows = sqlx::query_as::<_, (i64, String)>(
+            "
+            SELECT id, uri FROM SentRequest
+        ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for (id, uri) in rows {
+            let redacted = secrets::redact_uri(&uri);
+            if redacted != uri {
+                sqlx::query(
+                    "
+                    UPDATE SentRequest SET uri = ?1 WHERE id = ?2
+                ",
+                )
+                .bind(redacted)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps the secrets of a collection on disk in the database, the files leave them out
+    /// so they can be shared.
+    async fn keep_local_secrets(&self, collection: &Collection, requests: &[CollectionRequest]) {
+        let no_flags = SecretFlags::new();
+        let mut local = Vec::new();
+        for (name, value) in &collection.variables.0 {
+            local.push((String::new(), name, value, &collection.secrets.0));
+        }
+        for req in requests {
+            for (name, value) in &req.headers.0 {
+                local.push((local_secret_key(req), name, value, &no_flags));
+            }
+        }
+
+        for (request, name, value, flags) in local {
+            if !secrets::is_secret_variable(name, value, flags) {
+                continue;
+            }
+            let what = format!("keep the secrets of collection '{}'", collection.name);
+            let mut pair = [(name.clone(), value.clone())];
+            if let Err(e) = self.seal_variables(&mut pair, flags) {
+                self.report(&what, Err::<(), _>(e));
+                continue;
+            }
+            let [(name, value)] = pair;
+            let kept = sqlx::query(
+                "
+                INSERT INTO LocalSecrets (collection, request, name, value)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT(collection, request, name) DO UPDATE SET value = excluded.value
+            ",
+            )
+            .bind(&collection.name)
+            .bind(&request)
+            .bind(&name)
+            .bind(value)
+            .execute(&self.pool)
+            .await;
+            self.report(&what, kept);
+        }
+    }
+
+    /// Fills in the secrets left out of the collection files.
+    async fn restore_local_secrets(
+        &self,
+        collections: &mut [(Collection, Vec<CollectionRequest>)],
+    ) -> Result<(), sqlx::Error> {
+        let local = sqlx::query_as::<_, (String, String, String, String)>(
+            "
+            SELECT collection, request, name, value FROM LocalSecrets
+        ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for (collection, request, name, value) in local {
+            let Some((c, requests)) = collections.iter_mut().find(|(c, _)| c.name == collection)
+            else {
+                continue;
+            };
+            let pairs = match request.as_str() {
+                "" => Some(&mut c.variables.0),
+                key => requests
+                    .iter_mut()
+                    .find(|r| local_secret_key(r) == key)
+                    .map(|r| &mut r.headers.0),
+            };
+            if let Some((_, v)) = pairs
+                .into_iter()
+                .flatten()
+                .find(|(n, v)| *n == name && v.is_empty())
+            {
+                *v = value;
+            }
+        }
+
+        for (c, requests) in collections.iter_mut() {
+            self.unseal(&mut c.variables.0);
+            for r in requests {
+                self.unseal(&mut r.headers.0);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a collection to disk, its secrets go to the database.
+    async fn save_fs_collection(
+        &self,
+        fs: &FsStore,
+        collection: &Collection,
+        requests: &[CollectionRequest],
+    ) -> std::io::Result<i64> {
+        self.keep_local_secrets(collection, requests).await;
+        fs.save(collection, requests)
+    }
+
+    /// Seeds an empty collections directory from the database and starts watching it.
+    async fn setup_fs_store(&self) {
+        let Some(fs) = &self.fs else {
+            return;
+        };
+
+        if fs.is_empty() {
+            let collections = self.load_db_collections().await;
+            let collections = self.report("load the collections to write to disk", collections);
+            for (c, requests) in collections.into_iter().flatten() {
+                let saved = self.save_fs_collection(fs, &c, &requests).await;
+                self.report(&format!("write collection '{}' to disk", c.name), saved);
+            }
+        }
+
+        fs.watch(self.global);
     }
 
     pub fn handle(s: Arc<Self>, msg: GlobalAppMsg) {
-        tokio::spawn(async move {
-            s.handle_msg(msg).await;
+        let db = s.clone();
+        let mut tasks = s.tasks.lock().unwrap();
+        // forget finished messages so the set only holds pending ones
+        while tasks.try_join_next().is_some() {}
+        tasks.spawn(async move {
+            db.handle_msg(msg).await;
         });
     }
 
     pub fn restore(s: Arc<Self>) {
         tokio::spawn(async move {
+            s.send_scopes().await;
             s.restore_open_windows().await;
         });
     }
 
+    /// Publishes environments and collection variables to the UI.
+    async fn send_scopes(&self) {
+        let scopes = self.load_scopes().await;
+        if let Some(scopes) = self.report("load the environments", scopes) {
+            self.global.send(GlobalAppMsg::ScopesLoaded(scopes));
+        }
+    }
+
+    async fn save_window_state(&self, mut open_window: OpenWindow) {
+        if let Err(e) = self.seal(&mut open_window.headers.0) {
+            self.report("save the window state", Err::<(), _>(e));
+            return;
+        }
+        let response = open_window
+            .response
+            .map(|r| sqlx::types::Json(secrets::redact_sent(&r, &[], &SecretFlags::new())));
+        let save = sqlx::query(
+            "
+            INSERT INTO OpenWindows (id, method, uri, path, query, headers, body,
+                environment_id, collection_id, kind, options, layout, response, name)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+            ON CONFLICT(id) DO UPDATE SET
+                method = excluded.method,
+                uri = excluded.uri,
+                path = excluded.path,
+                query = excluded.query,
+                headers = excluded.headers,
+                body = excluded.body,
+                environment_id = excluded.environment_id,
+                collection_id = excluded.collection_id,
+                kind = excluded.kind,
+                options = excluded.options,
+                layout = excluded.layout,
+                response = COALESCE(excluded.response, response),
+                name = excluded.name
+
+        ",
+        )
+        .bind(open_window.id)
+        .bind(open_window.method)
+        .bind(open_window.uri)
+        .bind(open_window.path)
+        .bind(open_window.query)
+        .bind(open_window.headers)
+        .bind(open_window.body)
+        .bind(open_window.environment_id)
+        .bind(open_window.collection_id)
+        .bind(open_window.kind)
+        .bind(open_window.options)
+        .bind(open_window.layout)
+        .bind(response)
+        .bind(open_window.name)
+        .execute(&self.pool)
+        .await;
+        self.report("save the window state", save);
+    }
+
+    /// Stores the response a window got, autosaves leave it out as it only changes on send.
+    async fn save_window_response(&self, id: i32, sent: &SentRequest) {
+        let save = sqlx::query(
+            "
+            UPDATE OpenWindows SET response = ? WHERE id = ?
+        ",
+        )
+        .bind(sqlx::types::Json(secrets::redact_sent(
+            sent,
+            &[],
+            &SecretFlags::new(),
+        )))
+        .bind(id)
+        .execute(&self.pool)
+        .await;
+        self.report("save the window response", save);
+    }
+
+    /// Finishes pending writes, e.g. autosaves, then saves the state of the windows still
+    /// open when the application quits. Blocks, as the runtime stops with the event loop
+    /// and spawned work would be lost.
+    pub fn shutdown(&self, windows: Vec<OpenWindow>) {
+        let mut tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let pending = async { while tasks.join_next().await.is_some() {} };
+                if tokio::time::timeout(SHUTDOWN_TIMEOUT, pending)
+                    .await
+                    .is_err()
+                {
+                    tracing::warn!("{} pending tasks dropped on shutdown", tasks.len());
+                }
+                for w in windows {
+                    self.save_window_state(w).await;
+                }
+            })
+        });
+    }
+
+    /// Answers with the cached introspection result of `uri`, if there is one.
+    async fn load_graphql_schema(&self, window_id: usize, uri: &str, show_docs: bool) {
+        let cached = sqlx::query_scalar::<_, String>(
+            "
+            SELECT schema FROM GraphQlSchemas WHERE uri = ?
+        ",
+        )
+        .bind(uri)
+        .fetch_optional(&self.pool)
+        .await;
+
+        match self.report("load the cached GraphQL schema", cached) {
+            Some(Some(json)) => {
+                if show_docs {
+                    match graphql::parse(&json) {
+                        Ok(schema) => self.global.send(GlobalAppMsg::ShowGraphQlDocs(schema)),
+                        Err(e) => self.global.send(GlobalAppMsg::Notify(format!(
+                            "Could not read the schema cached for {uri}:\n{e}"
+                        ))),
+                    }
+                }
+                self.global
+                    .send(GlobalAppMsg::GraphQlSchemaLoaded(window_id, json));
+            }
+            Some(None) if show_docs => self.global.send(GlobalAppMsg::Notify(format!(
+                "No schema cached for {uri}, use Introspect first."
+            ))),
+            Some(None) | None => (),
+        }
+    }
+
     async fn restore_open_windows(&self) {
         let wins = sqlx::query_as::<_, OpenWindow>(
             "
@@ -77,16 +831,15 @@ impl LeGrillonDb {
         .fetch_all(&self.pool)
         .await;
 
-        println!("{wins:?}");
-
         let mut max_id = 0;
 
-        if let Ok(wins) = wins {
-            for w in wins {
+        if let Some(wins) = self.report("restore the open windows", wins) {
+            for mut w in wins {
                 if w.id > max_id {
                     max_id = w.id;
                 }
 
+                self.unseal(&mut w.headers.0);
                 self.global.send(GlobalAppMsg::Restore(w));
             }
         }
@@ -97,6 +850,8 @@ impl LeGrillonDb {
     async fn handle_msg(&self, msg: GlobalAppMsg) {
         match msg {
             GlobalAppMsg::OpenEmptyWindow => (),
+            GlobalAppMsg::OpenWebSocketWindow => (),
+            GlobalAppMsg::OpenGrpcWindow => (),
             GlobalAppMsg::Restore(_) => (),
             GlobalAppMsg::CloseWindow(id) => {
                 let close_window = sqlx::query(
@@ -108,73 +863,1400 @@ impl LeGrillonDb {
                 .bind(id as i64)
                 .execute(&self.pool)
                 .await;
-                println!("DB::CLOSE_WINDOW:: {close_window:?}")
+                self.report("forget the closed window", close_window);
+            }
+            GlobalAppMsg::SaveWindowState(open_window) => self.save_window_state(open_window).await,
+            GlobalAppMsg::SaveWindowResponse(id, sent) => {
+                self.save_window_response(id, &sent).await
+            }
+            GlobalAppMsg::ImportPostmanCollection(path) => {
+                self.import_postman_collection(&path).await
+            }
+            GlobalAppMsg::ImportPostmanEnvironment(path) => {
+                self.import_postman_environment(&path).await
+            }
+            GlobalAppMsg::OpenCollections => {
+                let collections = self.load_collections().await;
+                if let Some(collections) = self.report("load the collections", collections) {
+                    self.global.send(GlobalAppMsg::ShowCollections(collections));
+                }
+            }
+            GlobalAppMsg::ShowCollections(_) => (),
+            GlobalAppMsg::ImportOpenApi(source) => self.import_openapi(&source).await,
+            GlobalAppMsg::ImportHttpFile(path) => self.import_http_file(&path).await,
+            GlobalAppMsg::ScopesLoaded(_) => (),
+            GlobalAppMsg::CollectionsChanged => self.send_scopes().await,
+            GlobalAppMsg::SetEnvironmentColor(id, color) => {
+                let set = sqlx::query(
+                    "
+                    UPDATE Environments SET color = ?1 WHERE id = ?2
+                ",
+                )
+                .bind(&color)
+                .bind(id)
+                .execute(&self.pool)
+                .await;
+                self.report("set the environment colour", set);
+                self.send_scopes().await;
+            }
+            GlobalAppMsg::SetEnvironmentReadOnly(id, read_only) => {
+                let set = sqlx::query(
+                    "
+                    UPDATE Environments SET read_only = ?1 WHERE id = ?2
+                ",
+                )
+                .bind(read_only)
+                .bind(id)
+                .execute(&self.pool)
+                .await;
+                self.report("make the environment read-only or writable", set);
+                self.send_scopes().await;
+            }
+            GlobalAppMsg::SetVariableSecret(id, name, secret) => {
+                let set = self.set_variable_secret(id, &name, secret).await;
+                self.report(&format!("mark {name} as secret or not"), set);
+                self.send_scopes().await;
+            }
+            GlobalAppMsg::SaveToCollection(name, request) => {
+                let saved = self.save_to_collection(&name, request).await;
+                log_result(&format!("save to collection '{name}'"), &saved);
+                let msg = match saved {
+                    Ok(summary) => summary,
+                    Err(e) => format!("Could not save to collection '{name}':\n{e}"),
+                };
+                self.global.send(GlobalAppMsg::Notify(msg));
+                self.send_scopes().await;
+            }
+            GlobalAppMsg::RecordSentRequest(sent) => {
+                let record = self.insert_sent_request(&sent).await;
+                self.report("add the request to the history", record);
+            }
+            GlobalAppMsg::OpenHistory => {
+                let history = self.load_history().await;
+                if let Some(history) = self.report("load the history", history) {
+                    self.global
+                        .send(GlobalAppMsg::ShowHistory("History".to_string(), history));
+                }
+            }
+            GlobalAppMsg::OpenMockServer => {
+                let routes = self.load_mock_routes().await;
+                if let Some(routes) = self.report("load the mock routes", routes) {
+                    self.global.send(GlobalAppMsg::ShowMockServer(routes));
+                }
+            }
+            GlobalAppMsg::ShowMockServer(_) => (),
+            GlobalAppMsg::SaveMockRoute(route) => {
+                let saved = self.save_mock_route(&route).await;
+                self.report("save the mock route", saved);
+                self.send_mock_routes().await;
+            }
+            GlobalAppMsg::DeleteMockRoute(id) => {
+                let deleted = sqlx::query(
+                    "
+                    DELETE FROM MockRoutes WHERE id = ?
+                ",
+                )
+                .bind(id)
+                .execute(&self.pool)
+                .await;
+                self.report("delete the mock route", deleted);
+                self.send_mock_routes().await;
+            }
+            GlobalAppMsg::PromoteToMock(sent) => {
+                let route = MockRoute::from_sent(&sent);
+                match self.save_mock_route(&route).await {
+                    Ok(_) => self.global.send(GlobalAppMsg::Notify(format!(
+                        "Added mock route {} {} ({}).",
+                        route.method, route.path, route.status
+                    ))),
+                    Err(e) => self.global.send(GlobalAppMsg::Notify(format!(
+                        "Could not add mock route:\n{e}"
+                    ))),
+                }
+                self.send_mock_routes().await;
+            }
+            GlobalAppMsg::MockRoutesLoaded(_) => (),
+            GlobalAppMsg::OpenProxy => (),
+            GlobalAppMsg::OpenLogs => (),
+            GlobalAppMsg::OpenBench(_) => (),
+            GlobalAppMsg::LoadBenchRuns(window_id, uri) => {
+                self.send_bench_runs(window_id, &uri).await
+            }
+            GlobalAppMsg::SaveBenchRun(window_id, run) => {
+                let saved = self.insert_bench_run(&run).await;
+                self.report("save the load test run", saved);
+                self.send_bench_runs(window_id, &run.uri).await;
+            }
+            GlobalAppMsg::BenchRunsLoaded(..) => (),
+            GlobalAppMsg::ShowDiff(_) => (),
+            GlobalAppMsg::DiffResponses(_) => (),
+            GlobalAppMsg::RefreshSidebar => {
+                let collections = self.load_collections().await;
+                let collections = self.report("load the collections", collections);
+                let history = self.load_history().await;
+                let history = self.report("load the history", history);
+                if let (Some(collections), Some(mut history)) = (collections, history) {
+                    history.truncate(SIDEBAR_HISTORY);
+                    self.global
+                        .send(GlobalAppMsg::SidebarLoaded(collections, history));
+                }
+            }
+            GlobalAppMsg::SidebarLoaded(..) => (),
+            GlobalAppMsg::ImportHar(path) => {
+                let entries = match tokio::fs::read_to_string(&path).await {
+                    Ok(json) => har::import(&json).map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                match entries {
+                    Ok(entries) => self.global.send(GlobalAppMsg::ShowHistory(
+                        path.display().to_string(),
+                        entries,
+                    )),
+                    Err(e) => self.global.send(GlobalAppMsg::Notify(format!(
+                        "Could not import {}:\n{e}",
+                        path.display()
+                    ))),
+                }
             }
-            GlobalAppMsg::SaveWindowState(open_window) => {
+            GlobalAppMsg::ShowHistory(..) => (),
+            GlobalAppMsg::SaveGraphQlSchema(uri, schema) => {
                 let save = sqlx::query(
                     "
-                    INSERT INTO OpenWindows (id, method, uri, path, query, headers, body)
-                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
-                    ON CONFLICT(id) DO UPDATE SET
-                        method = excluded.method,
-                        uri = excluded.uri,
-                        path = excluded.path,
-                        query = excluded.query,
-                        headers = excluded.headers,
-                        body = excluded.body
-
+                    INSERT INTO GraphQlSchemas (uri, schema, fetched_at)
+                    VALUES (?1, ?2, ?3)
+                    ON CONFLICT(uri) DO UPDATE SET
+                        schema = excluded.schema,
+                        fetched_at = excluded.fetched_at
                 ",
                 )
-                .bind(open_window.id)
-                .bind(open_window.method)
-                .bind(open_window.uri)
-                .bind(open_window.path)
-                .bind(open_window.query)
-                .bind(open_window.headers)
-                .bind(open_window.body)
+                .bind(uri)
+                .bind(schema)
+                .bind(chrono::Local::now())
                 .execute(&self.pool)
                 .await;
-                println!("{save:?}");
+                self.report("cache the GraphQL schema", save);
             }
+            GlobalAppMsg::LoadGraphQlSchema(window_id, uri, show_docs) => {
+                self.load_graphql_schema(window_id, &uri, show_docs).await
+            }
+            GlobalAppMsg::GraphQlSchemaLoaded(..) => (),
+            GlobalAppMsg::ShowGraphQlDocs(_) => (),
+            GlobalAppMsg::Notify(_) => (),
+            GlobalAppMsg::RunAction(..) => (),
             GlobalAppMsg::CloseApp => (),
         }
     }
 
-    async fn setup(pool: &Pool<Sqlite>) {
-        let r = sqlx::query(
-            "
-            CREATE TABLE IF NOT EXISTS OpenWindows (
-                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
-                method VARCHAR(32) NOT NULL,
-                uri VARCHAR(256) NOT NULL,
-                path VARCHAR(1024),
-                query TEXT,
-                headers TEXT,
-                body TEXT
-            );
-        ",
-        )
-        .execute(pool)
-        .await;
+    async fn import_postman_collection(&self, path: &Path) {
+        let import = match tokio::fs::read_to_string(path).await {
+            Ok(json) => postman::import_collection(&json).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
 
-        println!("{r:?}");
-        let r = sqlx::query(
-            "
-            CREATE TABLE IF NOT EXISTS SentRequest (
-                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
-                sent_at TEXT NOT NULL,
-                method VARCHAR(32) NOT NULL,
-                uri VARCHAR(256) NOT NULL,
-                path VARCHAR(1024),
-                query TEXT,
-                headers TEXT,
-                body TEXT
-            );
-        ",
-        )
-        .execute(pool)
-        .await;
+        let import = match import {
+            Ok(import) => import,
+            Err(e) => {
+                self.global.send(GlobalAppMsg::Notify(format!(
+                    "Could not import {}:\n{e}",
+                    path.display()
+                )));
+                return;
+            }
+        };
+
+        let saved = self
+            .insert_collection(&import.collection, &import.requests)
+            .await;
+        log_result(&format!("import {}", path.display()), &saved);
+
+        let msg = match saved {
+            Ok(_) => import_report(
+                &format!(
+                    "Imported collection '{}' with {} request(s).",
+                    import.collection.name,
+                    import.requests.len()
+                ),
+                &import.untranslated,
+            ),
+            Err(e) => format!(
+                "Could not save collection '{}':\n{e}",
+                import.collection.name
+            ),
+        };
+        self.global.send(GlobalAppMsg::Notify(msg));
+        self.send_scopes().await;
+    }
+
+    async fn import_postman_environment(&self, path: &Path) {
+        let import = match tokio::fs::read_to_string(path).await {
+            Ok(json) => postman::import_environment(&json).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        let import = match import {
+            Ok(import) => import,
+            Err(e) => {
+                self.global.send(GlobalAppMsg::Notify(format!(
+                    "Could not import {}:\n{e}",
+                    path.display()
+                )));
+                return;
+            }
+        };
+
+        let saved = self.insert_environment(&import.environment).await;
+        log_result(&format!("import {}", path.display()), &saved);
+
+        let msg = match saved {
+            Ok(_) => import_report(
+                &format!(
+                    "Imported environment '{}' with {} variable(s).",
+                    import.environment.name,
+                    import.environment.variables.0.len()
+                ),
+                &import.untranslated,
+            ),
+            Err(e) => format!(
+                "Could not save environment '{}':\n{e}",
+                import.environment.name
+            ),
+        };
+        self.global.send(GlobalAppMsg::Notify(msg));
+        self.send_scopes().await;
+    }
+
+    async fn import_openapi(&self, source: &str) {
+        let is_url = source.starts_with("http://") || source.starts_with("https://");
+        let text = if is_url {
+            match reqwest::get(source).await {
+                Ok(resp) => resp.text().await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        } else {
+            tokio::fs::read_to_string(source)
+                .await
+                .map_err(|e| e.to_string())
+        };
+
+        let import = text.and_then(|t| openapi::import(&t, is_url.then_some(source)));
+        let import = match import {
+            Ok(import) => import,
+            Err(e) => {
+                self.global.send(GlobalAppMsg::Notify(format!(
+                    "Could not import {source}:\n{e}"
+                )));
+                return;
+            }
+        };
+
+        let saved = match self.upsert_environment_variables(&import.environment).await {
+            Ok(_) => {
+                self.sync_collection(&import.collection, &import.requests)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        log_result(&format!("import {source}"), &saved);
+
+        let msg = match saved {
+            Ok(summary) => import_report(&summary, &import.untranslated),
+            Err(e) => format!(
+                "Could not save collection '{}':\n{e}",
+                import.collection.name
+            ),
+        };
+        self.global.send(GlobalAppMsg::Notify(msg));
+        self.send_scopes().await;
+    }
+
+    /// Stores an imported collection, merging it into an existing collection of the same name
+    /// instead of creating a duplicate.
+    async fn sync_collection(
+        &self,
+        collection: &Collection,
+        imported: &[CollectionRequest],
+    ) -> Result<String, sqlx::Error> {
+        if let Some(fs) = &self.fs {
+            self.keep_local_secrets(collection, imported).await;
+            return sync_fs_collection(fs, collection, imported).map_err(sqlx::Error::Io);
+        }
+
+        let existing = sqlx::query_as::<_, Collection>(
+            "
+            SELECT * FROM Collections WHERE name = ?
+        ",
+        )
+        .bind(&collection.name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(mut existing) = existing else {
+            self.insert_collection(collection, imported).await?;
+            return Ok(format!(
+                "Imported collection '{}' with {} request(s).",
+                collection.name,
+                imported.len()
+            ));
+        };
+
+        let mut requests = sqlx::query_as::<_, CollectionRequest>(
+            "
+            SELECT * FROM CollectionRequests WHERE collection_id = ?
+        ",
+        )
+        .bind(existing.id)
+        .fetch_all(&self.pool)
+        .await?;
+        for r in &mut requests {
+            self.unseal(&mut r.headers.0);
+        }
+
+        let mut diff = diff_requests(&requests, imported);
+        for r in diff.added.iter_mut().chain(diff.changed.iter_mut()) {
+            self.seal(&mut r.headers.0)?;
+        }
+
+        // keep values the user already filled in
+        for (name, value) in &collection.variables.0 {
+            if !existing.variables.0.iter().any(|(n, _)| n == name) {
+                existing.variables.0.push((name.clone(), value.clone()));
+            }
+        }
+        for (name, secret) in &collection.secrets.0 {
+            existing.secrets.0.entry(name.clone()).or_insert(*secret);
+        }
+        self.seal_variables(&mut existing.variables.0, &existing.secrets.0)?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "
+            UPDATE Collections SET variables = ?1, secrets = ?2 WHERE id = ?3
+        ",
+        )
+        .bind(&existing.variables)
+        .bind(&existing.secrets)
+        .bind(existing.id)
+        .execute(&mut *tx)
+        .await?;
+
+        for req in &diff.added {
+            sqlx::query(
+                "
+                INSERT INTO CollectionRequests (collection_id, folder, name, method, uri, headers, body)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ",
+            )
+            .bind(existing.id)
+            .bind(&req.folder)
+            .bind(&req.name)
+            .bind(&req.method)
+            .bind(&req.uri)
+            .bind(&req.headers)
+            .bind(&req.body)
+            .execute(&mut *tx)
+            .await?;
+        }
+        for req in &diff.changed {
+            sqlx::query(
+                "
+                UPDATE CollectionRequests
+                SET folder = ?1, name = ?2, method = ?3, uri = ?4, headers = ?5, body = ?6
+                WHERE id = ?7
+            ",
+            )
+            .bind(&req.folder)
+            .bind(&req.name)
+            .bind(&req.method)
+            .bind(&req.uri)
+            .bind(&req.headers)
+            .bind(&req.body)
+            .bind(req.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        for id in &diff.removed {
+            sqlx::query(
+                "
+                DELETE FROM CollectionRequests WHERE id = ?
+            ",
+            )
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(format!(
+            "Updated collection '{}': {} added, {} changed, {} removed, {} unchanged.",
+            existing.name,
+            diff.added.len(),
+            diff.changed.len(),
+            diff.removed.len(),
+            diff.unchanged
+        ))
+    }
+
+    /// Creates the environment or sets the given variables on the existing one with the same name.
+    async fn upsert_environment_variables(
+        &self,
+        environment: &Environment,
+    ) -> Result<i64, sqlx::Error> {
+        let existing = sqlx::query_as::<_, Environment>(
+            "
+            SELECT * FROM Environments WHERE name = ?
+        ",
+        )
+        .bind(&environment.name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(mut existing) = existing else {
+            return self.insert_environment(environment).await;
+        };
+
+        for (name, value) in &environment.variables.0 {
+            match existing.variables.0.iter_mut().find(|(n, _)| n == name) {
+                Some(var) => var.1 = value.clone(),
+                None => existing.variables.0.push((name.clone(), value.clone())),
+            }
+        }
+        existing.secrets.0.extend(environment.secrets.0.clone());
+        self.seal_variables(&mut existing.variables.0, &existing.secrets.0)?;
+
+        sqlx::query(
+            "
+            UPDATE Environments SET variables = ?1, secrets = ?2 WHERE id = ?3
+        ",
+        )
+        .bind(&existing.variables)
+        .bind(&existing.secrets)
+        .bind(existing.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(existing.id)
+    }
+
+    /// Marks a variable of an environment as secret or not, its value is encrypted or
+    /// decrypted accordingly.
+    async fn set_variable_secret(
+        &self,
+        id: i64,
+        name: &str,
+        secret: bool,
+    ) -> Result<(), sqlx::Error> {
+        let mut environment = sqlx::query_as::<_, Environment>(
+            "
+            SELECT * FROM Environments WHERE id = ?
+        ",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.unseal(&mut environment.variables.0);
+        environment.secrets.0.insert(name.to_string(), secret);
+        self.seal_variables(&mut environment.variables.0, &environment.secrets.0)?;
+
+        sqlx::query(
+            "
+            UPDATE Environments SET variables = ?1, secrets = ?2 WHERE id = ?3
+        ",
+        )
+        .bind(&environment.variables)
+        .bind(&environment.secrets)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn load_scopes(&self) -> Result<VariableScopes, sqlx::Error> {
+        let mut environments = sqlx::query_as::<_, Environment>(
+            "
+            SELECT * FROM Environments ORDER BY name
+        ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for env in &mut environments {
+            self.unseal(&mut env.variables.0);
+        }
+
+        let collections = self.load_collections().await?;
+
+        Ok(VariableScopes {
+            environments,
+            collections: collections.into_iter().map(|(c, _)| (c.id, c)).collect(),
+        })
+    }
+
+    async fn import_http_file(&self, path: &Path) {
+        let text = match tokio::fs::read_to_string(path).await {
+            Ok(text) => text,
+            Err(e) => {
+                self.global.send(GlobalAppMsg::Notify(format!(
+                    "Could not import {}:\n{e}",
+                    path.display()
+                )));
+                return;
+            }
+        };
+
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        let file = http_file::parse(&text, base_dir);
+        let collection = Collection {
+            id: 0,
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+            variables: sqlx::types::Json(file.variables),
+            secrets: sqlx::types::Json(SecretFlags::new()),
+        };
+
+        let saved = self.sync_collection(&collection, &file.requests).await;
+        log_result(&format!("import {}", path.display()), &saved);
+
+        let msg = match saved {
+            Ok(summary) => import_report(&summary, &file.untranslated),
+            Err(e) => format!("Could not save collection '{}':\n{e}", collection.name),
+        };
+        self.global.send(GlobalAppMsg::Notify(msg));
+        self.send_scopes().await;
+    }
+
+    /// Adds `request` to the collection called `name`, replacing the request with the same
+    /// folder and name. The collection is created when there is none.
+    async fn save_to_collection(
+        &self,
+        name: &str,
+        request: CollectionRequest,
+    ) -> Result<String, sqlx::Error> {
+        let collections = self.load_collections().await?;
+        let Some((collection, mut requests)) =
+            collections.into_iter().find(|(c, _)| c.name == name)
+        else {
+            let collection = Collection {
+                id: 0,
+                name: name.to_string(),
+                variables: sqlx::types::Json(Vec::new()),
+                secrets: sqlx::types::Json(SecretFlags::new()),
+            };
+            self.insert_collection(&collection, std::slice::from_ref(&request))
+                .await?;
+            return Ok(format!(
+                "Created collection '{name}' with '{}'.",
+                request.name
+            ));
+        };
+
+        let summary = format!("Saved '{}' to collection '{name}'.", request.name);
+        let replaced = requests
+            .iter()
+            .position(|r| r.folder == request.folder && r.name == request.name);
+        if let Some(fs) = &self.fs {
+            match replaced {
+                Some(i) => requests[i] = request,
+                None => requests.push(request),
+            }
+            self.save_fs_collection(fs, &collection, &requests)
+                .await
+                .map_err(sqlx::Error::Io)?;
+            return Ok(summary);
+        }
+
+        let mut headers = request.headers.clone();
+        self.seal(&mut headers.0)?;
+        if let Some(i) = replaced {
+            sqlx::query(
+                "
+                UPDATE CollectionRequests
+                SET method = ?1, uri = ?2, headers = ?3, body = ?4
+                WHERE id = ?5
+            ",
+            )
+            .bind(&request.method)
+            .bind(&request.uri)
+            .bind(&headers)
+            .bind(&request.body)
+            .bind(requests[i].id)
+            .execute(&self.pool)
+            .await?;
+        } else {
+            sqlx::query(
+                "
+                INSERT INTO CollectionRequests (collection_id, folder, name, method, uri, headers, body)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ",
+            )
+            .bind(collection.id)
+            .bind(&request.folder)
+            .bind(&request.name)
+            .bind(&request.method)
+            .bind(&request.uri)
+            .bind(&headers)
+            .bind(&request.body)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(summary)
+    }
+
+    pub async fn insert_collection(
+        &self,
+        collection: &Collection,
+        requests: &[CollectionRequest],
+    ) -> Result<i64, sqlx::Error> {
+        if let Some(fs) = &self.fs {
+            return self
+                .save_fs_collection(fs, collection, requests)
+                .await
+                .map_err(sqlx::Error::Io);
+        }
+
+        let mut variables = collection.variables.clone();
+        self.seal_variables(&mut variables.0, &collection.secrets.0)?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let collection_id = sqlx::query(
+            "
+            INSERT INTO Collections (name, variables, secrets)
+            VALUES (?1, ?2, ?3)
+        ",
+        )
+        .bind(&collection.name)
+        .bind(&variables)
+        .bind(&collection.secrets)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        for req in requests {
+            let mut headers = req.headers.clone();
+            self.seal(&mut headers.0)?;
+            sqlx::query(
+                "
+                INSERT INTO CollectionRequests (collection_id, folder, name, method, uri, headers, body)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ",
+            )
+            .bind(collection_id)
+            .bind(&req.folder)
+            .bind(&req.name)
+            .bind(&req.method)
+            .bind(&req.uri)
+            .bind(&headers)
+            .bind(&req.body)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(collection_id)
+    }
+
+    pub async fn insert_environment(&self, environment: &Environment) -> Result<i64, sqlx::Error> {
+        let mut variables = environment.variables.clone();
+        self.seal_variables(&mut variables.0, &environment.secrets.0)?;
+        let id = sqlx::query(
+            "
+            INSERT INTO Environments (name, variables, secrets, color, read_only)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+        )
+        .bind(&environment.name)
+        .bind(&variables)
+        .bind(&environment.secrets)
+        .bind(&environment.color)
+        .bind(environment.read_only)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    pub async fn insert_sent_request(&self, sent: &SentRequest) -> Result<i64, sqlx::Error> {
+        let sent = secrets::redact_sent(sent, &[], &SecretFlags::new());
+        let id = sqlx::query(
+            "
+            INSERT INTO SentRequest (sent_at, method, uri, path, query, headers, body,
+                status, response_headers, response_body, wait_ms, receive_ms, dns_ms, connect_ms,
+                tls_ms)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+        ",
+        )
+        .bind(sent.sent_at)
+        .bind(&sent.method)
+        .bind(&sent.uri)
+        .bind(&sent.path)
+        .bind(&sent.query)
+        .bind(&sent.headers)
+        .bind(&sent.body)
+        .bind(sent.status)
+        .bind(&sent.response_headers)
+        .bind(&sent.response_body)
+        .bind(sent.wait_ms)
+        .bind(sent.receive_ms)
+        .bind(sent.dns_ms)
+        .bind(sent.connect_ms)
+        .bind(sent.tls_ms)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    pub async fn load_history(&self) -> Result<Vec<SentRequest>, sqlx::Error> {
+        sqlx::query_as::<_, SentRequest>(
+            "
+            SELECT * FROM SentRequest ORDER BY sent_at DESC
+        ",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Newest first.
+    pub async fn load_bench_runs(&self, uri: &str) -> Result<Vec<BenchRun>, sqlx::Error> {
+        sqlx::query_as::<_, BenchRun>(
+            "
+            SELECT * FROM BenchRuns WHERE uri = ? ORDER BY ran_at DESC
+        ",
+        )
+        .bind(uri)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn send_bench_runs(&self, window_id: usize, uri: &str) {
+        let runs = self.load_bench_runs(uri).await;
+        if let Some(runs) = self.report("load the load test runs", runs) {
+            self.global
+                .send(GlobalAppMsg::BenchRunsLoaded(window_id, runs));
+        }
+    }
+
+    pub async fn insert_bench_run(&self, run: &BenchRun) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            "
+            INSERT INTO BenchRuns (ran_at, method, uri, config, report)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+        )
+        .bind(run.ran_at)
+        .bind(&run.method)
+        .bind(&run.uri)
+        .bind(&run.config)
+        .bind(&run.report)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    pub async fn load_mock_routes(&self) -> Result<Vec<MockRoute>, sqlx::Error> {
+        sqlx::query_as::<_, MockRoute>(
+            "
+            SELECT * FROM MockRoutes ORDER BY path, method
+        ",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn send_mock_routes(&self) {
+        if let Ok(routes) = self.load_mock_routes().await {
+            self.global.send(GlobalAppMsg::MockRoutesLoaded(routes));
+        }
+    }
+
+    /// Inserts routes with id 0, updates the others.
+    pub async fn save_mock_route(&self, route: &MockRoute) -> Result<i64, sqlx::Error> {
+        if route.id == 0 {
+            let id = sqlx::query(
+                "
+                INSERT INTO MockRoutes (method, path, status, headers, body, delay_ms)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ",
+            )
+            .bind(&route.method)
+            .bind(&route.path)
+            .bind(route.status)
+            .bind(&route.headers)
+            .bind(&route.body)
+            .bind(route.delay_ms)
+            .execute(&self.pool)
+            .await?
+            .last_insert_rowid();
+
+            return Ok(id);
+        }
+
+        sqlx::query(
+            "
+            UPDATE MockRoutes
+            SET method = ?2, path = ?3, status = ?4, headers = ?5, body = ?6, delay_ms = ?7
+            WHERE id = ?1
+        ",
+        )
+        .bind(route.id)
+        .bind(&route.method)
+        .bind(&route.path)
+        .bind(route.status)
+        .bind(&route.headers)
+        .bind(&route.body)
+        .bind(route.delay_ms)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(route.id)
+    }
+
+    pub async fn load_collections(
+        &self,
+    ) -> Result<Vec<(Collection, Vec<CollectionRequest>)>, sqlx::Error> {
+        let mut collections = match &self.fs {
+            Some(fs) => {
+                let loaded = fs.load_all().map_err(sqlx::Error::Io)?;
+                self.report_skipped(loaded.skipped);
+                loaded.collections
+            }
+            None => self.load_db_collections().await?,
+        };
+        if self.fs.is_some() {
+            self.restore_local_secrets(&mut collections).await?;
+        }
+        Ok(collections)
+    }
+
+    async fn load_db_collections(
+        &self,
+    ) -> Result<Vec<(Collection, Vec<CollectionRequest>)>, sqlx::Error> {
+        let collections = sqlx::query_as::<_, Collection>(
+            "
+            SELECT * FROM Collections ORDER BY name
+        ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut result = Vec::with_capacity(collections.len());
+        for mut c in collections {
+            self.unseal(&mut c.variables.0);
+            let mut requests = sqlx::query_as::<_, CollectionRequest>(
+                "
+                SELECT * FROM CollectionRequests
+                WHERE collection_id = ?
+                ORDER BY folder, id
+            ",
+            )
+            .bind(c.id)
+            .fetch_all(&self.pool)
+            .await?;
+            for r in &mut requests {
+                self.unseal(&mut r.headers.0);
+            }
+            result.push((c, requests));
+        }
+
+        Ok(result)
+    }
+
+    async fn setup(&self) {
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS OpenWindows (
+                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                method VARCHAR(32) NOT NULL,
+                uri VARCHAR(256) NOT NULL,
+                path VARCHAR(1024),
+                query TEXT,
+                headers TEXT,
+                body TEXT
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table OpenWindows", r);
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS SentRequest (
+                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                sent_at TEXT NOT NULL,
+                method VARCHAR(32) NOT NULL,
+                uri VARCHAR(256) NOT NULL,
+                path VARCHAR(1024),
+                query TEXT,
+                headers TEXT,
+                body TEXT
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table SentRequest", r);
+        self.add_column("OpenWindows", "environment_id INTEGER")
+            .await;
+        self.add_column("OpenWindows", "collection_id INTEGER")
+            .await;
+        self.add_column("OpenWindows", "kind VARCHAR(32) NOT NULL DEFAULT 'http'")
+            .await;
+        self.add_column("OpenWindows", "options TEXT NOT NULL DEFAULT '{}'")
+            .await;
+        self.add_column("OpenWindows", "layout TEXT NOT NULL DEFAULT '{}'")
+            .await;
+        self.add_column("OpenWindows", "response TEXT").await;
+        self.add_column("OpenWindows", "name TEXT NOT NULL DEFAULT ''")
+            .await;
+        self.add_column("SentRequest", "status INTEGER NOT NULL DEFAULT 0")
+            .await;
+        self.add_column("SentRequest", "response_headers TEXT NOT NULL DEFAULT '[]'")
+            .await;
+        self.add_column("SentRequest", "response_body TEXT NOT NULL DEFAULT ''")
+            .await;
+        self.add_column("SentRequest", "wait_ms REAL NOT NULL DEFAULT 0")
+            .await;
+        self.add_column("SentRequest", "receive_ms REAL NOT NULL DEFAULT 0")
+            .await;
+        self.add_column("SentRequest", "dns_ms REAL NOT NULL DEFAULT 0")
+            .await;
+        self.add_column("SentRequest", "connect_ms REAL NOT NULL DEFAULT 0")
+            .await;
+        self.add_column("SentRequest", "tls_ms REAL NOT NULL DEFAULT 0")
+            .await;
+
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS Collections (
+                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                name VARCHAR(256) NOT NULL,
+                variables TEXT
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table Collections", r);
+        self.add_column("Collections", "secrets TEXT NOT NULL DEFAULT '{}'")
+            .await;
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS CollectionRequests (
+                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                collection_id INTEGER NOT NULL REFERENCES Collections(id) ON DELETE CASCADE,
+                folder VARCHAR(1024) NOT NULL,
+                name VARCHAR(256) NOT NULL,
+                method VARCHAR(32) NOT NULL,
+                uri VARCHAR(256) NOT NULL,
+                headers TEXT,
+                body TEXT
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table CollectionRequests", r);
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS Environments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                name VARCHAR(256) NOT NULL,
+                variables TEXT
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table Environments", r);
+        self.add_column("Environments", "color TEXT NOT NULL DEFAULT ''")
+            .await;
+        self.add_column("Environments", "read_only BOOLEAN NOT NULL DEFAULT 0")
+            .await;
+        self.add_column("Environments", "secrets TEXT NOT NULL DEFAULT '{}'")
+            .await;
+
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS Meta (
+                key VARCHAR(64) PRIMARY KEY NOT NULL,
+                value TEXT NOT NULL
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table Meta", r);
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS LocalSecrets (
+                collection VARCHAR(256) NOT NULL,
+                request VARCHAR(1024) NOT NULL,
+                name VARCHAR(256) NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (collection, request, name)
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table LocalSecrets", r);
+
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS GraphQlSchemas (
+                uri VARCHAR(1024) PRIMARY KEY NOT NULL,
+                schema TEXT NOT NULL,
+                fetched_at TEXT NOT NULL
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table GraphQlSchemas", r);
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS MockRoutes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                method VARCHAR(32) NOT NULL,
+                path VARCHAR(1024) NOT NULL,
+                status INTEGER NOT NULL DEFAULT 200,
+                headers TEXT NOT NULL DEFAULT '[]',
+                body TEXT NOT NULL DEFAULT '',
+                delay_ms INTEGER NOT NULL DEFAULT 0
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table MockRoutes", r);
+        let r = sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS BenchRuns (
+                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                ran_at TEXT NOT NULL,
+                method VARCHAR(32) NOT NULL,
+                uri VARCHAR(256) NOT NULL,
+                config TEXT NOT NULL,
+                report TEXT NOT NULL
+            );
+        ",
+        )
+        .execute(&self.pool)
+        .await;
+
+        self.report("create table BenchRuns", r);
+    }
+
+    /// `ALTER TABLE .. ADD COLUMN` for databases created before the column existed.
+    /// Fails harmlessly with "duplicate column name" once it has been added.
+    async fn add_column(&self, table: &str, column_def: &str) {
+        let r = sqlx::query(&format!("ALTER TABLE {table} ADD COLUMN {column_def}"))
+            .execute(&self.pool)
+            .await;
+
+        match r {
+            Err(e) if e.to_string().contains("duplicate column name") => (),
+            r => {
+                self.report(&format!("add column {column_def} to table {table}"), r);
+            }
+        }
+    }
+}
+
+/// Logs the outcome of a call whose failure the caller shows to the user itself.
+fn log_result<T, E: Display>(what: &str, result: &Result<T, E>) {
+    match result {
+        Ok(_) => tracing::debug!("{what}"),
+        Err(e) => tracing::error!("could not {what}: {e}"),
+    }
+}
+
+/// Identifies a request of a collection on disk in `LocalSecrets`.
+fn local_secret_key(request: &CollectionRequest) -> String {
+    format!("{}/{}", request.folder, request.name)
+}
+
+/// Matches requests by folder and name first, then the remaining ones by method and path
+/// template, ignoring the query string. Requests sharing a key, e.g. two `POST /login`
+/// with different bodies, are matched in order.
+pub fn diff_requests(
+    existing: &[CollectionRequest],
+    imported: &[CollectionRequest],
+) -> CollectionDiff {
+    let by_name = |r: &CollectionRequest| (r.folder.clone(), r.name.clone());
+    let by_path = |r: &CollectionRequest| {
+        let path = r.uri.split('?').next().unwrap_or_default().to_string();
+        (r.method.clone(), path)
+    };
+    let index = |key: &dyn Fn(&CollectionRequest) -> (String, String)| {
+        let mut index: BTreeMap<(String, String), VecDeque<usize>> = BTreeMap::new();
+        for (i, r) in existing.iter().enumerate() {
+            index.entry(key(r)).or_default().push_back(i);
+        }
+        index
+    };
+    let (mut names, mut paths) = (index(&by_name), index(&by_path));
+    let mut taken = vec![false; existing.len()];
+    let mut take = |index: &mut BTreeMap<(String, String), VecDeque<usize>>,
+                    key: (String, String)| {
+        let found = index.get_mut(&key)?;
+        while let Some(i) = found.pop_front() {
+            if !taken[i] {
+                taken[i] = true;
+                return Some(i);
+            }
+        }
+        None
+    };
+
+    let mut matched: Vec<Option<usize>> = imported
+        .iter()
+        .map(|r| take(&mut names, by_name(r)))
+        .collect();
+    for (r, m) in imported.iter().zip(matched.iter_mut()) {
+        if m.is_none() {
+            *m = take(&mut paths, by_path(r));
+        }
+    }
+
+    let mut diff = CollectionDiff::default();
+    for (r, m) in imported.iter().zip(matched) {
+        match m.map(|i| &existing[i]) {
+            Some(old) => {
+                let same = old.name == r.name
+                    && old.folder == r.folder
+                    && old.method == r.method
+                    && old.uri == r.uri
+                    && old.headers.0 == r.headers.0
+                    && old.body == r.body;
+                if same {
+                    diff.unchanged += 1;
+                } else {
+                    diff.changed.push(CollectionRequest {
+                        id: old.id,
+                        collection_id: old.collection_id,
+                        ..r.clone()
+                    });
+                }
+            }
+            None => diff.added.push(r.clone()),
+        }
+    }
+
+    diff.removed = existing
+        .iter()
+        .zip(taken)
+        .filter(|(_, taken)| !taken)
+        .map(|(r, _)| r.id)
+        .collect();
+    diff
+}
+
+/// [`LeGrillonDb::sync_collection`] for collections stored on disk.
+fn sync_fs_collection(
+    fs: &FsStore,
+    collection: &Collection,
+    imported: &[CollectionRequest],
+) -> std::io::Result<String> {
+    let Some((mut existing, requests)) = fs.find(&collection.name)? else {
+        fs.save(collection, imported)?;
+        return Ok(format!(
+            "Imported collection '{}' with {} request(s).",
+            collection.name,
+            imported.len()
+        ));
+    };
+
+    let diff = diff_requests(&requests, imported);
+
+    // keep values the user already filled in
+    for (name, value) in &collection.variables.0 {
+        if !existing.variables.0.iter().any(|(n, _)| n == name) {
+            existing.variables.0.push((name.clone(), value.clone()));
+        }
+    }
+    for (name, secret) in &collection.secrets.0 {
+        existing.secrets.0.entry(name.clone()).or_insert(*secret);
+    }
+    fs.save(&existing, imported)?;
+
+    Ok(format!(
+        "Updated collection '{}': {} added, {} changed, {} removed, {} unchanged.",
+        existing.name,
+        diff.added.len(),
+        diff.changed.len(),
+        diff.removed.len(),
+        diff.unchanged
+    ))
+}
+
+fn import_report(summary: &str, untranslated: &[String]) -> String {
+    if untranslated.is_empty() {
+        return summary.to_string();
+    }
+
+    let mut report = format!("{summary}\n\nNot translated:");
+    for item in untranslated.iter().take(20) {
+        report.push_str(&format!("\n - {item}"));
+    }
+    if untranslated.len() > 20 {
+        report.push_str(&format!("\n ... and {} more", untranslated.len() - 20));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::vars;
+
+    async fn memory_db() -> LeGrillonDb {
+        // one connection, each one would open its own empty database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let db = LeGrillonDb {
+            pool,
+            global: app::channel().0,
+            fs: None,
+            tasks: Mutex::new(JoinSet::new()),
+            vault: RwLock::new(None),
+            encrypt_secrets: false,
+            failing: Mutex::new(BTreeSet::new()),
+            skipped_files: Mutex::new(Vec::new()),
+        };
+        db.setup().await;
+        db
+    }
+
+    fn request(id: i64, name: &str, method: &str, uri: &str, body: &str) -> CollectionRequest {
+        CollectionRequest {
+            id,
+            collection_id: 1,
+            folder: String::new(),
+            name: name.to_string(),
+            method: method.to_string(),
+            uri: uri.to_string(),
+            headers: sqlx::types::Json(Vec::new()),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn diff_keeps_requests_sharing_method_and_path() {
+        let imported = [
+            request(0, "login as admin", "POST", "http://h/login", "admin"),
+            request(0, "login as guest", "POST", "http://h/login", "guest"),
+            request(0, "logout", "POST", "http://h/logout", ""),
+        ];
+        let existing = [
+            request(1, "login as admin", "POST", "http://h/login", "admin"),
+            request(2, "login as guest", "POST", "http://h/login", "guest"),
+            request(3, "logout", "GET", "http://h/logout", ""),
+        ];
+
+        let diff = diff_requests(&existing, &imported);
+        assert_eq!(diff.unchanged, 2);
+        let changed: Vec<(i64, &str)> = diff
+            .changed
+            .iter()
+            .map(|r| (r.id, r.method.as_str()))
+            .collect();
+        assert_eq!(changed, [(3, "POST")]);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+    }
+
+    #[test]
+    fn diff_matches_by_name_then_in_order_by_path() {
+        let existing = [
+            request(1, "a", "POST", "http://h/login", "1"),
+            request(2, "b", "POST", "http://h/login", "2"),
+            request(3, "gone", "GET", "http://h/old", ""),
+            request(4, "me", "GET", "http://h/me", ""),
+        ];
+        let imported = [
+            request(0, "b", "POST", "http://h/login", "two"),
+            request(0, "me", "PUT", "http://h/me", ""),
+            request(0, "renamed", "POST", "http://h/login?x=1", "1"),
+            request(0, "new", "POST", "http://h/login", "3"),
+        ];
+
+        let diff = diff_requests(&existing, &imported);
+        let changed: Vec<(i64, &str)> = diff
+            .changed
+            .iter()
+            .map(|r| (r.id, r.name.as_str()))
+            .collect();
+        assert_eq!(changed, [(2, "b"), (4, "me"), (1, "renamed")]);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name, "new");
+        assert_eq!(diff.removed, [3]);
+    }
+
+    #[tokio::test]
+    async fn history_leaves_out_substituted_secrets() {
+        let db = memory_db().await;
+        let vars = vec![
+            ("user".to_string(), "alice".to_string()),
+            ("password".to_string(), "hunter2".to_string()),
+            ("pin".to_string(), "8461".to_string()),
+        ];
+        let flags = SecretFlags::from([("pin".to_string(), true)]);
+        let sent = SentRequest {
+            id: 0,
+            sent_at: chrono::Local::now(),
+            method: "POST".to_string(),
+            uri: vars::substitute("http://localhost/login/{{pin}}", &vars),
+            path: String::new(),
+            query: String::new(),
+            headers: sqlx::types::Json(vec![(
+                "X-Login".to_string(),
+                vars::substitute("{{user}}:{{password}}", &vars),
+            )]),
+            body: vars::substitute(r#"{"user": "{{user}}", "password": "{{password}}"}"#, &vars),
+            status: 200,
+            response_headers: sqlx::types::Json(Vec::new()),
+            response_body: String::new(),
+            wait_ms: 0.0,
+            receive_ms: 0.0,
+            dns_ms: 0.0,
+            connect_ms: 0.0,
+            tls_ms: 0.0,
+        };
 
-        println!("{r:?}");
+        let id = db
+            .insert_sent_request(&secrets::redact_sent(&sent, &vars, &flags))
+            .await
+            .unwrap();
+        let row: (String, String, String) =
+            sqlx::query_as("SELECT uri, headers, body FROM SentRequest WHERE id = ?")
+                .bind(id)
+                .fetch_one(&db.pool)
+                .await
+                .unwrap();
+        let row = format!("{row:?}");
+        assert!(!row.contains("hunter2"), "{row}");
+        assert!(!row.contains("8461"), "{row}");
+        assert!(row.contains("alice"), "{row}");
     }
 }
@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+
+use crate::db::SentRequest;
+
+const CONTEXT_LINES: usize = 3;
+/// Above this many line pairs the changed middle is shown as replaced instead of diffed.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Longest common subsequence over the lines between the common prefix and suffix.
+fn diff_lines<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (ma, mb) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut lines: Vec<Line> = a[..prefix].iter().map(|l| Line::Same(l)).collect();
+    if ma.len() * mb.len() > MAX_DIFF_CELLS {
+        lines.extend(ma.iter().map(|l| Line::Removed(l)));
+        lines.extend(mb.iter().map(|l| Line::Added(l)));
+    } else {
+        let mut lcs = vec![vec![0usize; mb.len() + 1]; ma.len() + 1];
+        for i in (0..ma.len()).rev() {
+            for j in (0..mb.len()).rev() {
+                lcs[i][j] = if ma[i] == mb[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < ma.len() || j < mb.len() {
+            if i < ma.len() && j < mb.len() && ma[i] == mb[j] {
+                lines.push(Line::Same(ma[i]));
+                i += 1;
+                j += 1;
+            } else if i < ma.len() && (j == mb.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                lines.push(Line::Removed(ma[i]));
+                i += 1;
+            } else {
+                lines.push(Line::Added(mb[j]));
+                j += 1;
+            }
+        }
+    }
+    lines.extend(a[a.len() - suffix..].iter().map(|l| Line::Same(l)));
+    lines
+}
+
+/// Unified style text diff, unchanged lines far from a change are left out.
+pub fn text_diff(a: &str, b: &str) -> Vec<String> {
+    let (a, b): (Vec<&str>, Vec<&str>) = (a.lines().collect(), b.lines().collect());
+    let lines = diff_lines(&a, &b);
+
+    // lines within CONTEXT_LINES of a change, one sweep after and one before each change
+    let mut near = vec![false; lines.len()];
+    let mut sweep = |i: usize, left: &mut usize| {
+        if !matches!(lines[i], Line::Same(_)) {
+            *left = CONTEXT_LINES + 1;
+        }
+        if *left > 0 {
+            near[i] = true;
+            *left -= 1;
+        }
+    };
+    let mut left = 0;
+    (0..lines.len()).for_each(|i| sweep(i, &mut left));
+    left = 0;
+    (0..lines.len()).rev().for_each(|i| sweep(i, &mut left));
+
+    let mut out = Vec::new();
+    let mut last_shown = None;
+    for (i, line) in lines.iter().enumerate() {
+        if !near[i] {
+            continue;
+        }
+        if last_shown.is_some_and(|l| l + 1 != i) {
+            out.push("...".to_string());
+        }
+        last_shown = Some(i);
+        out.push(match line {
+            Line::Same(l) => format!("  {l}"),
+            Line::Removed(l) => format!("- {l}"),
+            Line::Added(l) => format!("+ {l}"),
+        });
+    }
+    out
+}
+
+/// Differences by path, object key order does not matter, arrays compare by index.
+pub fn json_diff(path: &str, a: &Value, b: &Value, out: &mut Vec<String>) {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let child = format!("{path}.{key}");
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => json_diff(&child, x, y, out),
+                    (Some(x), None) => out.push(format!("- {child}: {x}")),
+                    (None, Some(y)) => out.push(format!("+ {child}: {y}")),
+                    (None, None) => (),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let child = format!("{path}[{i}]");
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => json_diff(&child, x, y, out),
+                    (Some(x), None) => out.push(format!("- {child}: {x}")),
+                    (None, Some(y)) => out.push(format!("+ {child}: {y}")),
+                    (None, None) => (),
+                }
+            }
+        }
+        _ if a != b => out.push(format!("~ {path}: {a} -> {b}")),
+        _ => (),
+    }
+}
+
+/// Header values by lowercase name, repeated headers joined.
+fn header_map(headers: &[(String, String)]) -> BTreeMap<String, String> {
+    let mut map: BTreeMap<String, String> = BTreeMap::new();
+    for (n, v) in headers {
+        map.entry(n.trim().to_ascii_lowercase())
+            .and_modify(|e| {
+                e.push_str(", ");
+                e.push_str(v.trim());
+            })
+            .or_insert_with(|| v.trim().to_string());
+    }
+    map
+}
+
+fn header_diff(a: &[(String, String)], b: &[(String, String)]) -> Vec<String> {
+    let (a, b) = (header_map(a), header_map(b));
+    let names: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|n| match (a.get(n), b.get(n)) {
+            (Some(x), Some(y)) if x != y => Some(format!("~ {n}: {x} -> {y}")),
+            (Some(x), None) => Some(format!("- {n}: {x}")),
+            (None, Some(y)) => Some(format!("+ {n}: {y}")),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn describe(sent: &SentRequest) -> String {
+    format!(
+        "{} {} {} {}",
+        sent.sent_at.format("%Y-%m-%d %H:%M:%S"),
+        sent.method,
+        sent.status,
+        sent.uri
+    )
+}
+
+/// Status, header and body differences of two responses as text.
+pub fn compare(left: &SentRequest, right: &SentRequest) -> String {
+    let mut text = format!("- {}\n+ {}\n\n", describe(left), describe(right));
+
+    if left.status == right.status {
+        text.push_str(&format!("Status: {} (same)\n\n", left.status));
+    } else {
+        text.push_str(&format!("Status: {} -> {}\n\n", left.status, right.status));
+    }
+
+    let headers = header_diff(&left.response_headers.0, &right.response_headers.0);
+    text.push_str("Headers\n");
+    if headers.is_empty() {
+        text.push_str("  (same)\n");
+    }
+    for line in headers {
+        text.push_str(&format!("  {line}\n"));
+    }
+
+    let json = serde_json::from_str::<Value>(&left.response_body)
+        .and_then(|a| serde_json::from_str::<Value>(&right.response_body).map(|b| (a, b)));
+    let body = match json {
+        Ok((a, b)) => {
+            text.push_str("\nBody (JSON, key order ignored)\n");
+            let mut out = Vec::new();
+            json_diff("$", &a, &b, &mut out);
+            out
+        }
+        Err(_) => {
+            text.push_str("\nBody\n");
+            text_diff(&left.response_body, &right.response_body)
+        }
+    };
+    if body.is_empty() {
+        text.push_str("  (same)\n");
+    }
+    for line in body {
+        text.push_str(&format!("  {line}\n"));
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_diff_keeps_context_around_changes() {
+        let a: Vec<String> = (1..=20).map(|i| i.to_string()).collect();
+        let mut b = a.clone();
+        b[1] = "two".to_string();
+        b[15] = "sixteen".to_string();
+
+        let diff = text_diff(&a.join("\n"), &b.join("\n"));
+        assert_eq!(
+            diff,
+            [
+                "  1",
+                "- 2",
+                "+ two",
+                "  3",
+                "  4",
+                "  5",
+                "...",
+                "  13",
+                "  14",
+                "  15",
+                "- 16",
+                "+ sixteen",
+                "  17",
+                "  18",
+                "  19",
+            ]
+        );
+        assert!(text_diff("same\n", "same\n").is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use std::rc::Rc;
+
+use fltk::{
+    app,
+    enums::{self, Event},
+    frame, group,
+    menu::Choice,
+    prelude::*,
+    text::{self, TextBuffer},
+    window::{self, DoubleWindow},
+};
+
+use crate::{AppWindow, GlobalAppMsg, HasId, db::SentRequest, diff, next_window_id};
+
+/// Status, header and body differences between two of the given responses.
+pub struct DiffWindow {
+    id: usize,
+    window: DoubleWindow,
+}
+
+fn fill(choice: &mut Choice, entries: &[SentRequest], selected: usize) {
+    for e in entries {
+        choice.add_choice(&diff::describe(e).replace('/', "\\/").replace('|', "\\|"));
+    }
+    choice.set_value(selected as i32);
+}
+
+impl DiffWindow {
+    pub fn new(entries: &[SentRequest], left: usize, right: usize) -> Self {
+        let id = next_window_id();
+
+        let mut win = window::DoubleWindow::default()
+            .with_size(1200, 800)
+            .with_label("Le Grillon - Diff");
+
+        let mut col = group::Flex::default_fill().column();
+        let mut row = group::Flex::default().row();
+        let left_label = frame::Frame::default().with_label("-");
+        row.fixed(&left_label, 24);
+        let mut left_choice = Choice::default();
+        fill(&mut left_choice, entries, left);
+        let right_label = frame::Frame::default().with_label("+");
+        row.fixed(&right_label, 24);
+        let mut right_choice = Choice::default();
+        fill(&mut right_choice, entries, right);
+        row.end();
+        col.fixed(&row, 32);
+
+        let mut diff_buf = TextBuffer::default();
+        let mut display = text::TextDisplay::default();
+        display.set_buffer(diff_buf.clone());
+        display.set_text_font(enums::Font::Courier);
+        col.end();
+
+        win.end();
+        win.make_resizable(true);
+        win.show();
+
+        let entries = Rc::new(entries.to_vec());
+        diff_buf.set_text(&diff::compare(&entries[left], &entries[right]));
+
+        let update = {
+            let (left_choice, right_choice) = (left_choice.clone(), right_choice.clone());
+            move || {
+                let pick =
+                    |c: &Choice| usize::try_from(c.value()).ok().and_then(|i| entries.get(i));
+                if let (Some(a), Some(b)) = (pick(&left_choice), pick(&right_choice)) {
+                    diff_buf.clone().set_text(&diff::compare(a, b));
+                }
+            }
+        };
+        let update = Rc::new(update);
+        let p_update = update.clone();
+        left_choice.set_callback(move |_| p_update());
+        right_choice.set_callback(move |_| update());
+
+        let (s, _) = app::channel::<GlobalAppMsg>();
+        win.handle(move |_, e| {
+            if e == Event::Hide {
+                tracing::debug!("window {id} (DiffWindow) closed");
+                s.send(GlobalAppMsg::CloseWindow(id));
+                return true;
+            }
+
+            false
+        });
+
+        Self { id, window: win }
+    }
+}
+
+impl HasId for DiffWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for DiffWindow {
+    fn close(&mut self) {
+        self.window.hide();
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use fltk::{
+    button,
+    enums::{self, CallbackTrigger, Color},
+    frame,
+    group::Flex,
+    input,
+    prelude::*,
+    text::{StyleTableEntry, TextBuffer, TextDisplay},
+};
+
+use crate::{db::SentRequest, json_query};
+
+const STYLE_PLAIN: char = 'A';
+const STYLE_MATCH: char = 'B';
+
+#[derive(Default)]
+struct FindState {
+    needle: String,
+    /// Byte offsets of the matches in the displayed text.
+    matches: Vec<usize>,
+    current: usize,
+}
+
+/// Incremental search with highlighted matches and a JSONPath filter below a response display.
+#[derive(Clone)]
+pub struct FindBarCtrl {
+    style_buf: TextBuffer,
+}
+
+/// Case insensitive for ASCII so offsets stay valid in the original text.
+fn find_all(text: &str, needle: &str) -> Vec<usize> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let (text, needle) = (text.to_ascii_lowercase(), needle.to_ascii_lowercase());
+    text.match_indices(&needle).map(|(i, _)| i).collect()
+}
+
+fn highlight(style_buf: &mut TextBuffer, text: &str, state: &FindState) {
+    if state.matches.is_empty() {
+        style_buf.set_text("");
+        return;
+    }
+    let mut styles = vec![STYLE_PLAIN as u8; text.len()];
+    for &start in &state.matches {
+        styles[start..start + state.needle.len()].fill(STYLE_MATCH as u8);
+    }
+    style_buf.set_text(&String::from_utf8(styles).unwrap_or_default());
+}
+
+fn show_current(display: &mut TextDisplay, state: &FindState, count: &mut frame::Frame) {
+    let Some(mut buf) = display.buffer() else {
+        return;
+    };
+    match state.matches.get(state.current) {
+        Some(&start) => {
+            let end = start + state.needle.len();
+            buf.select(start as i32, end as i32);
+            display.set_insert_position(end as i32);
+            display.show_insert_position();
+            count.set_label(&format!("{}/{}", state.current + 1, state.matches.len()));
+        }
+        None => {
+            buf.unselect();
+            let label = if state.needle.is_empty() {
+                ""
+            } else {
+                "No match"
+            };
+            count.set_label(label);
+        }
+    }
+}
+
+impl FindBarCtrl {
+    /// Adds the bar to `parent`, `display` has to show a buffer already. The filter applies
+    /// to the body of `response`.
+    pub fn new(
+        parent: &mut Flex,
+        display: &TextDisplay,
+        response: Arc<Mutex<Option<SentRequest>>>,
+    ) -> Self {
+        let mut row = Flex::default().row();
+        let find_label = frame::Frame::default().with_label("Find:");
+        row.fixed(&find_label, 56);
+        let mut find_input = input::Input::default();
+        find_input.set_tooltip("Enter jumps to the next match");
+        let mut prev_btn = button::Button::default().with_label("@<");
+        prev_btn.set_tooltip("Previous match");
+        row.fixed(&prev_btn, 32);
+        let mut next_btn = button::Button::default().with_label("@>");
+        next_btn.set_tooltip("Next match");
+        row.fixed(&next_btn, 32);
+        let count = frame::Frame::default();
+        row.fixed(&count, 112);
+        let filter_label = frame::Frame::default().with_label("Filter:");
+        row.fixed(&filter_label, 64);
+        let mut filter_input = input::Input::default();
+        filter_input.set_tooltip(
+            "JSONPath ($.items[?(@.price < 10)].name) or jq path (.items[].name), \
+             Enter applies, empty shows the whole response",
+        );
+        row.end();
+        parent.fixed(&row, 32);
+
+        let mut display = display.clone();
+        let style_buf = TextBuffer::default();
+        let size = display.text_size();
+        display.set_highlight_data(
+            style_buf.clone(),
+            vec![
+                StyleTableEntry {
+                    color: display.text_color(),
+                    font: enums::Font::Courier,
+                    size,
+                },
+                StyleTableEntry {
+                    color: Color::Yellow,
+                    font: enums::Font::CourierBold,
+                    size,
+                },
+            ],
+        );
+
+        let state = Rc::new(RefCell::new(FindState::default()));
+
+        let search = {
+            let (state, display, style_buf, count) = (
+                state.clone(),
+                display.clone(),
+                style_buf.clone(),
+                count.clone(),
+            );
+            move |needle: String, step: isize| {
+                let (mut display, mut style_buf, mut count) =
+                    (display.clone(), style_buf.clone(), count.clone());
+                let text = display.buffer().map(|b| b.text()).unwrap_or_default();
+                let matches = find_all(&text, &needle);
+                let mut state = state.borrow_mut();
+                // the text changes under the search when a new response or a filter arrives
+                if needle != state.needle || matches != state.matches || step == 0 {
+                    state.matches = matches;
+                    state.needle = needle;
+                    state.current = 0;
+                    highlight(&mut style_buf, &text, &state);
+                } else if !state.matches.is_empty() {
+                    let len = state.matches.len() as isize;
+                    state.current = (state.current as isize + step).rem_euclid(len) as usize;
+                }
+                show_current(&mut display, &state, &mut count);
+            }
+        };
+        let search = Rc::new(search);
+
+        find_input.set_trigger(CallbackTrigger::Changed | CallbackTrigger::EnterKeyAlways);
+        let p_search = search.clone();
+        find_input.set_callback(move |i| p_search(i.value(), 1));
+        let (p_search, p_input) = (search.clone(), find_input.clone());
+        next_btn.set_callback(move |_| p_search(p_input.value(), 1));
+        let (p_search, p_input) = (search.clone(), find_input.clone());
+        prev_btn.set_callback(move |_| p_search(p_input.value(), -1));
+
+        filter_input.set_trigger(CallbackTrigger::EnterKeyAlways);
+        let mut p_count = count.clone();
+        filter_input.set_callback(move |i| {
+            let Some(body) = response
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|r| r.response_body.clone())
+            else {
+                p_count.set_label("No response");
+                return;
+            };
+            let expr = i.value();
+            p_count.set_label("");
+            let text = if expr.trim().is_empty() {
+                Ok(body)
+            } else {
+                json_query::filter(&expr, &body)
+            };
+            match text {
+                Ok(text) => {
+                    p_count.set_tooltip("");
+                    if let Some(mut buf) = display.buffer() {
+                        buf.set_text(&text);
+                    }
+                    search(find_input.value(), 0);
+                }
+                Err(e) => {
+                    p_count.set_label("Invalid filter");
+                    p_count.set_tooltip(&e);
+                }
+            }
+        });
+
+        Self { style_buf }
+    }
+
+    /// Drops the highlights, to be called when the display gets a new response.
+    pub fn clear(&self) {
+        self.style_buf.clone().set_text("");
+    }
+}
@@ -0,0 +1,625 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Component, Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use fltk::app::Sender;
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GlobalAppMsg,
+    db::{Collection, CollectionRequest},
+    secrets,
+};
+
+const COLLECTION_FILE: &str = "collection.toml";
+/// Lists the request files the store wrote last in a collection directory, only those
+/// are removed when they are no longer part of the collection.
+const MANIFEST_FILE: &str = ".legrillon-files";
+
+/// Collections mirrored as a directory tree so they can be shared through git:
+///
+/// ```text
+/// <root>/<collection>/collection.toml
+/// <root>/<collection>/<folder>/<request>.toml
+/// ```
+///
+/// Ids are derived from the paths, so they stay stable across restarts. Secret values,
+/// e.g. an `Authorization` header, are written empty, the database keeps them.
+pub struct FsStore {
+    root: PathBuf,
+    watcher: Mutex<Option<notify::RecommendedWatcher>>,
+}
+
+/// What [`FsStore::load_all`] could read.
+#[derive(Debug, Default)]
+pub struct Loaded {
+    pub collections: Vec<(Collection, Vec<CollectionRequest>)>,
+    /// Files that could not be read, e.g. with a merge conflict, as `path: error`.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CollectionFile {
+    name: String,
+    #[serde(default)]
+    variables: Vec<Variable>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Variable {
+    name: String,
+    value: String,
+    /// Marked as secret or not, otherwise told by the name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secret: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RequestFile {
+    name: String,
+    /// The folder as named in the app, the directories only hold its slug.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    folder: String,
+    method: String,
+    uri: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    headers: Vec<Header>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    name: String,
+    value: String,
+}
+
+impl FsStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            watcher: Mutex::new(None),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        std::fs::read_dir(&self.root).map_or(true, |mut d| d.next().is_none())
+    }
+
+    /// Reports external changes (e.g. a `git pull`) as [`GlobalAppMsg::CollectionsChanged`],
+    /// bursts of file events are coalesced into one message.
+    pub fn watch(&self, global: Sender<GlobalAppMsg>) {
+        if let Err(e) = std::fs::create_dir_all(&self.root) {
+            tracing::error!("could not create {}: {e}", self.root.display());
+            return;
+        }
+
+        let pending = Arc::new(AtomicBool::new(false));
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res
+                && (event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove())
+                && !pending.swap(true, Ordering::SeqCst)
+            {
+                let pending = pending.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_millis(300));
+                    pending.store(false, Ordering::SeqCst);
+                    global.send(GlobalAppMsg::CollectionsChanged);
+                });
+            }
+        });
+
+        match watcher {
+            Ok(mut watcher) => {
+                if let Err(e) = watcher.watch(&self.root, RecursiveMode::Recursive) {
+                    tracing::error!("could not watch {}: {e}", self.root.display());
+                }
+                *self.watcher.lock().unwrap() = Some(watcher);
+            }
+            Err(e) => tracing::error!("could not watch the collections: {e}"),
+        }
+    }
+
+    /// Loads every collection, files that cannot be parsed are skipped so one bad file
+    /// does not hide the others.
+    pub fn load_all(&self) -> io::Result<Loaded> {
+        let mut loaded = Loaded::default();
+        if !self.root.exists() {
+            return Ok(loaded);
+        }
+
+        for entry in std::fs::read_dir(&self.root)? {
+            let dir = entry?.path();
+            let collection_file = dir.join(COLLECTION_FILE);
+            if !collection_file.is_file() {
+                continue;
+            }
+
+            let file: CollectionFile = match read_toml(&collection_file) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    loaded.skipped.push(e.to_string());
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let collection_id = path_id(dir.strip_prefix(&self.root).unwrap_or(&dir));
+            let collection = Collection {
+                id: collection_id,
+                name: file.name,
+                secrets: sqlx::types::Json(
+                    file.variables
+                        .iter()
+                        .filter_map(|v| Some((v.name.clone(), v.secret?)))
+                        .collect(),
+                ),
+                variables: sqlx::types::Json(
+                    file.variables
+                        .into_iter()
+                        .map(|v| (v.name, v.value))
+                        .collect(),
+                ),
+            };
+
+            let mut requests = Vec::new();
+            load_requests(
+                &dir,
+                &dir,
+                collection_id,
+                &mut requests,
+                &mut loaded.skipped,
+            )?;
+            requests.sort_by(|a, b| a.folder.cmp(&b.folder).then(a.name.cmp(&b.name)));
+            loaded.collections.push((collection, requests));
+        }
+
+        loaded.collections.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+        Ok(loaded)
+    }
+
+    pub fn find(&self, name: &str) -> io::Result<Option<(Collection, Vec<CollectionRequest>)>> {
+        Ok(self
+            .load_all()?
+            .collections
+            .into_iter()
+            .find(|(c, _)| c.name == name))
+    }
+
+    /// Writes the whole collection, request files that are no longer part of it are removed.
+    pub fn save(&self, collection: &Collection, requests: &[CollectionRequest]) -> io::Result<i64> {
+        let dir = self.collection_dir(&collection.name);
+        std::fs::create_dir_all(&dir)?;
+
+        write_toml(
+            &dir.join(COLLECTION_FILE),
+            &CollectionFile {
+                name: collection.name.clone(),
+                variables: collection
+                    .variables
+                    .0
+                    .iter()
+                    .map(|(name, value)| Variable {
+                        name: name.clone(),
+                        value: shareable(
+                            value,
+                            secrets::is_secret_variable(name, value, &collection.secrets.0),
+                        ),
+                        secret: collection.secrets.0.get(name).copied(),
+                    })
+                    .collect(),
+            },
+        )?;
+
+        let mut written = BTreeSet::new();
+        for req in requests {
+            let mut folder_dir = dir.clone();
+            folder_dir.extend(folder_dirs(&req.folder));
+            std::fs::create_dir_all(&folder_dir)?;
+
+            let base = slug(&req.name);
+            let mut path = folder_dir.join(format!("{base}.toml"));
+            let mut n = 2;
+            while written.contains(&path) {
+                path = folder_dir.join(format!("{base}-{n}.toml"));
+                n += 1;
+            }
+
+            write_toml(
+                &path,
+                &RequestFile {
+                    name: req.name.clone(),
+                    folder: req.folder.clone(),
+                    method: req.method.clone(),
+                    uri: req.uri.clone(),
+                    body: req.body.clone(),
+                    headers: req
+                        .headers
+                        .0
+                        .iter()
+                        .map(|(name, value)| Header {
+                            name: name.clone(),
+                            value: shareable(value, secrets::is_secret(name, value)),
+                        })
+                        .collect(),
+                },
+            )?;
+            written.insert(path);
+        }
+
+        remove_stale(&dir, &written)?;
+
+        Ok(path_id(dir.strip_prefix(&self.root).unwrap_or(&dir)))
+    }
+
+    /// The directory of the collection called `name`. Names with the same slug, e.g. `A/B`
+    /// and `A?B`, get a suffix the way request files do.
+    fn collection_dir(&self, name: &str) -> PathBuf {
+        let base = slug(name);
+        let candidates = (1..).map(|n| match n {
+            1 => self.root.join(&base),
+            n => self.root.join(format!("{base}-{n}")),
+        });
+        let name_in = |dir: &Path| {
+            read_toml::<CollectionFile>(&dir.join(COLLECTION_FILE))
+                .ok()
+                .map(|file| file.name)
+        };
+
+        let mut free = None;
+        for dir in candidates {
+            if !dir.exists() {
+                return free.unwrap_or(dir);
+            }
+            match name_in(&dir) {
+                Some(other) if other == name => return dir,
+                // another collection, or one that can't be read and may be this one
+                Some(_) => (),
+                None => {
+                    free.get_or_insert(dir);
+                }
+            }
+        }
+        unreachable!("endless candidates")
+    }
+}
+
+fn load_requests(
+    collection_dir: &Path,
+    dir: &Path,
+    collection_id: i64,
+    requests: &mut Vec<CollectionRequest>,
+    skipped: &mut Vec<String>,
+) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            load_requests(collection_dir, &path, collection_id, requests, skipped)?;
+            continue;
+        }
+        if path.extension().is_none_or(|e| e != "toml")
+            || path.file_name().is_some_and(|n| n == COLLECTION_FILE)
+        {
+            continue;
+        }
+
+        let file: RequestFile = match read_toml(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                skipped.push(e.to_string());
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let relative = path.strip_prefix(collection_dir).unwrap_or(&path);
+        let dirs: Vec<String> = relative
+            .parent()
+            .map(|p| {
+                p.components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        // the directories win when the file was moved to another folder by hand
+        let folder = if folder_dirs(&file.folder) == dirs {
+            file.folder
+        } else {
+            dirs.join("/")
+        };
+
+        requests.push(CollectionRequest {
+            id: path_id(relative),
+            collection_id,
+            folder,
+            name: file.name,
+            method: file.method,
+            uri: file.uri,
+            headers: sqlx::types::Json(
+                file.headers
+                    .into_iter()
+                    .map(|h| (h.name, h.value))
+                    .collect(),
+            ),
+            body: file.body,
+        });
+    }
+
+    Ok(())
+}
+
+/// `value` as written to a file others may read.
+fn shareable(value: &str, secret: bool) -> String {
+    if secret {
+        String::new()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Removes the request files of the previous save that are not in `keep`, and the
+/// folders they leave empty, then lists `keep` in the manifest. Files other tools put in
+/// the directory are never in the manifest and stay.
+fn remove_stale(dir: &Path, keep: &BTreeSet<PathBuf>) -> io::Result<()> {
+    let manifest = dir.join(MANIFEST_FILE);
+    let previous = std::fs::read_to_string(&manifest).unwrap_or_default();
+    for relative in previous.lines().map(Path::new) {
+        // only plain relative paths, nothing outside the collection
+        if relative.as_os_str().is_empty()
+            || !relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            continue;
+        }
+        let path = dir.join(relative);
+        if keep.contains(&path) {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => (),
+        }
+        let mut folder = path.parent();
+        while let Some(f) = folder.filter(|f| *f != dir) {
+            // fails, and stops, on the first folder that is not empty
+            if std::fs::remove_dir(f).is_err() {
+                break;
+            }
+            folder = f.parent();
+        }
+    }
+
+    let listed: Vec<String> = keep
+        .iter()
+        .filter_map(|p| p.strip_prefix(dir).ok())
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .collect();
+    write_if_changed(&manifest, &(listed.join("\n") + "\n"))
+}
+
+fn read_toml<T: serde::de::DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let text = std::fs::read_to_string(path)?;
+    toml::from_str(&text).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })
+}
+
+/// Only writes when the content changed, to not trigger the watcher needlessly.
+fn write_toml<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let text = toml::to_string_pretty(value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    write_if_changed(path, &text)
+}
+
+fn write_if_changed(path: &Path, text: &str) -> io::Result<()> {
+    if std::fs::read_to_string(path).is_ok_and(|old| old == text) {
+        return Ok(());
+    }
+    std::fs::write(path, text)
+}
+
+/// Directories of a request folder, e.g. `Auth & Users/admin` is in `Auth _ Users/admin`.
+fn folder_dirs(folder: &str) -> Vec<String> {
+    folder
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(slug)
+        .collect()
+}
+
+/// File system friendly name, kept readable for code review.
+fn slug(name: &str) -> String {
+    let s: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let s = s.trim_matches('.').to_string();
+    if s.is_empty() { "_".to_string() } else { s }
+}
+
+/// FNV-1a of the relative path, positive so it never collides with "no id" checks.
+fn path_id(relative: &Path) -> i64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in relative.to_string_lossy().replace('\\', "/").bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (hash >> 1) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("legrillon-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        root
+    }
+
+    fn collection(name: &str) -> Collection {
+        Collection {
+            id: 0,
+            name: name.to_string(),
+            variables: sqlx::types::Json(Vec::new()),
+            secrets: sqlx::types::Json(Default::default()),
+        }
+    }
+
+    fn request(name: &str) -> CollectionRequest {
+        CollectionRequest {
+            id: 0,
+            collection_id: 0,
+            folder: "users".to_string(),
+            name: name.to_string(),
+            method: "GET".to_string(),
+            uri: "http://localhost/users".to_string(),
+            headers: sqlx::types::Json(Vec::new()),
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn load_all_skips_unreadable_files() {
+        let root = temp_root("skip");
+        let store = FsStore::new(root.clone());
+        store
+            .save(&collection("api"), &[request("list"), request("get")])
+            .unwrap();
+        store
+            .save(&collection("other"), &[request("list")])
+            .unwrap();
+        std::fs::write(
+            root.join("api/users/get.toml"),
+            "<<<<<<< HEAD\nname = \"get\"\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("other/collection.toml"), "name = ").unwrap();
+
+        let loaded = store.load_all().unwrap();
+        let names: Vec<(&str, usize)> = loaded
+            .collections
+            .iter()
+            .map(|(c, r)| (c.name.as_str(), r.len()))
+            .collect();
+        assert_eq!(names, [("api", 1)]);
+        assert_eq!(loaded.skipped.len(), 2);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn save_only_removes_files_it_wrote() {
+        let root = temp_root("stale");
+        let store = FsStore::new(root.clone());
+        let api = collection("api");
+        store
+            .save(&api, &[request("list"), request("get")])
+            .unwrap();
+        let foreign = root.join("api/users/notes.toml");
+        std::fs::write(&foreign, "written = \"by hand\"\n").unwrap();
+
+        store.save(&api, &[request("list")]).unwrap();
+        assert!(root.join("api/users/list.toml").is_file());
+        assert!(!root.join("api/users/get.toml").exists());
+        assert!(foreign.is_file());
+
+        std::fs::remove_file(&foreign).unwrap();
+        store.save(&api, &[]).unwrap();
+        assert!(!root.join("api/users").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn keeps_folder_and_name_that_are_not_file_names() {
+        let root = temp_root("names");
+        let store = FsStore::new(root.clone());
+        let mut req = request("Get user #1?");
+        req.folder = "Auth & Users/v2: beta".to_string();
+        let mut moved = request("moved");
+        moved.folder = "Auth & Users".to_string();
+        store.save(&collection("api"), &[req, moved]).unwrap();
+        std::fs::rename(
+            root.join("api/Auth _ Users/moved.toml"),
+            root.join("api/moved.toml"),
+        )
+        .unwrap();
+
+        let (_, requests) = store.find("api").unwrap().unwrap();
+        let loaded: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|r| (r.folder.as_str(), r.name.as_str()))
+            .collect();
+        assert_eq!(
+            loaded,
+            [("", "moved"), ("Auth & Users/v2: beta", "Get user #1?")]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn collections_with_the_same_slug_keep_apart() {
+        let root = temp_root("slugs");
+        let store = FsStore::new(root.clone());
+        let a = store.save(&collection("A/B"), &[request("a")]).unwrap();
+        let b = store.save(&collection("A?B"), &[request("b")]).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(store.save(&collection("A?B"), &[request("b")]).unwrap(), b);
+
+        let loaded = store.load_all().unwrap();
+        let names: Vec<(&str, &str)> = loaded
+            .collections
+            .iter()
+            .map(|(c, r)| (c.name.as_str(), r[0].name.as_str()))
+            .collect();
+        assert_eq!(names, [("A/B", "a"), ("A?B", "b")]);
+        assert!(root.join("A_B-2/collection.toml").is_file());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn secret_flags_override_the_name() {
+        let root = temp_root("secrets");
+        let store = FsStore::new(root.clone());
+        let mut api = collection("api");
+        api.variables.0 = vec![
+            ("host".to_string(), "localhost".to_string()),
+            ("signingKey".to_string(), "s3cr3t".to_string()),
+            ("tokenUrl".to_string(), "https://auth".to_string()),
+        ];
+        api.secrets.0 = [
+            ("signingKey".to_string(), true),
+            ("tokenUrl".to_string(), false),
+        ]
+        .into();
+        store.save(&api, &[]).unwrap();
+
+        let (loaded, _) = store.find("api").unwrap().unwrap();
+        assert_eq!(
+            loaded.variables.0,
+            [
+                ("host".to_string(), "localhost".to_string()),
+                ("signingKey".to_string(), String::new()),
+                ("tokenUrl".to_string(), "https://auth".to_string()),
+            ]
+        );
+        assert_eq!(loaded.secrets.0, api.secrets.0);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
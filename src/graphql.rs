@@ -0,0 +1,410 @@
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue};
+use serde::Deserialize;
+
+pub const INTROSPECTION_QUERY: &str = "
+query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      kind
+      name
+      description
+      fields(includeDeprecated: true) {
+        name
+        description
+        args { name description type { ...TypeRef } defaultValue }
+        type { ...TypeRef }
+      }
+      inputFields { name description type { ...TypeRef } defaultValue }
+      enumValues(includeDeprecated: true) { name description }
+      possibleTypes { name }
+    }
+  }
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType {
+    kind
+    name
+    ofType {
+      kind
+      name
+      ofType {
+        kind
+        name
+        ofType {
+          kind
+          name
+          ofType {
+            kind
+            name
+            ofType { kind name ofType { kind name } }
+          }
+        }
+      }
+    }
+  }
+}
+";
+
+/// The parts of an introspection result used for completion and the docs browser.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schema {
+    pub query_type: Option<NamedRef>,
+    pub mutation_type: Option<NamedRef>,
+    pub subscription_type: Option<NamedRef>,
+    pub types: Vec<FullType>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NamedRef {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FullType {
+    pub kind: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub fields: Option<Vec<Field>>,
+    pub input_fields: Option<Vec<InputValue>>,
+    pub enum_values: Option<Vec<EnumValue>>,
+    pub possible_types: Option<Vec<NamedRef>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Field {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub args: Vec<InputValue>,
+    #[serde(rename = "type")]
+    pub type_ref: TypeRef,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputValue {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub type_ref: TypeRef,
+    pub default_value: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnumValue {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeRef {
+    pub kind: String,
+    pub name: Option<String>,
+    pub of_type: Option<Box<TypeRef>>,
+}
+
+impl TypeRef {
+    /// The named type below all `NON_NULL` and `LIST` wrappers.
+    pub fn named(&self) -> &str {
+        match (&self.name, &self.of_type) {
+            (Some(name), _) => name,
+            (None, Some(inner)) => inner.named(),
+            (None, None) => "",
+        }
+    }
+
+    /// SDL notation, e.g. `[User!]!`.
+    pub fn display(&self) -> String {
+        let inner = || self.of_type.as_ref().map_or(String::new(), |t| t.display());
+        match self.kind.as_str() {
+            "NON_NULL" => format!("{}!", inner()),
+            "LIST" => format!("[{}]", inner()),
+            _ => self.name.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl Schema {
+    pub fn get_type(&self, name: &str) -> Option<&FullType> {
+        self.types.iter().find(|t| t.name == name)
+    }
+
+    fn field(&self, type_name: &str, field: &str) -> Option<&Field> {
+        self.get_type(type_name)?
+            .fields
+            .as_ref()?
+            .iter()
+            .find(|f| f.name == field)
+    }
+
+    /// SDL like description of a type for the docs browser.
+    pub fn describe(&self, name: &str) -> String {
+        let Some(t) = self.get_type(name) else {
+            return String::new();
+        };
+
+        let mut out = String::new();
+        if let Some(d) = &t.description {
+            out.push_str(&format!("\"\"\"\n{d}\n\"\"\"\n"));
+        }
+        let keyword = match t.kind.as_str() {
+            "OBJECT" => "type",
+            "INPUT_OBJECT" => "input",
+            "ENUM" => "enum",
+            "INTERFACE" => "interface",
+            "UNION" => "union",
+            _ => "scalar",
+        };
+        out.push_str(&format!("{keyword} {}", t.name));
+
+        if let Some(possible) = &t.possible_types {
+            let names: Vec<_> = possible.iter().map(|p| p.name.as_str()).collect();
+            out.push_str(&format!(" = {}\n", names.join(" | ")));
+            return out;
+        }
+        if t.fields.is_none() && t.input_fields.is_none() && t.enum_values.is_none() {
+            out.push('\n');
+            return out;
+        }
+
+        out.push_str(" {\n");
+        for f in t.fields.iter().flatten() {
+            push_description(&mut out, &f.description);
+            let args: Vec<_> = f.args.iter().map(input_value).collect();
+            if args.is_empty() {
+                out.push_str(&format!("  {}: {}\n", f.name, f.type_ref.display()));
+            } else {
+                out.push_str(&format!(
+                    "  {}({}): {}\n",
+                    f.name,
+                    args.join(", "),
+                    f.type_ref.display()
+                ));
+            }
+        }
+        for v in t.input_fields.iter().flatten() {
+            push_description(&mut out, &v.description);
+            out.push_str(&format!("  {}\n", input_value(v)));
+        }
+        for v in t.enum_values.iter().flatten() {
+            push_description(&mut out, &v.description);
+            out.push_str(&format!("  {}\n", v.name));
+        }
+        out.push_str("}\n");
+
+        out
+    }
+}
+
+fn push_description(out: &mut String, description: &Option<String>) {
+    if let Some(d) = description.as_ref().filter(|d| !d.is_empty()) {
+        for line in d.lines() {
+            out.push_str(&format!("  # {line}\n"));
+        }
+    }
+}
+
+fn input_value(v: &InputValue) -> String {
+    match &v.default_value {
+        Some(default) => format!("{}: {} = {default}", v.name, v.type_ref.display()),
+        None => format!("{}: {}", v.name, v.type_ref.display()),
+    }
+}
+
+/// Accepts a full introspection response (`{"data": {"__schema": ...}}`) or just the `__schema`.
+pub fn parse(json: &str) -> Result<Schema, String> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    if let Some(errors) = value
+        .get("errors")
+        .filter(|e| value.get("data").is_none_or(|d| d.is_null()) && !e.is_null())
+    {
+        return Err(format!("introspection failed: {errors}"));
+    }
+
+    let schema = value
+        .pointer("/data/__schema")
+        .or_else(|| value.get("__schema"))
+        .unwrap_or(&value);
+    serde_json::from_value(schema.clone()).map_err(|e| e.to_string())
+}
+
+/// The `{"query", "variables"}` envelope, built as text so `{{variables}}` survive until send time.
+pub fn envelope(query: &str, variables: &str) -> String {
+    let query = serde_json::to_string(query).unwrap_or_default();
+    let variables = variables.trim();
+    let variables = if variables.is_empty() {
+        "null"
+    } else {
+        variables
+    };
+    format!("{{\"query\": {query}, \"variables\": {variables}}}")
+}
+
+pub async fn introspect(uri: &str, mut headers: HeaderMap) -> Result<(String, Schema), String> {
+    if !headers.contains_key(CONTENT_TYPE) {
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+
+    let resp = reqwest::Client::new()
+        .post(uri)
+        .headers(headers)
+        .body(envelope(INTROSPECTION_QUERY, ""))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = resp.status();
+    let text = resp.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("{status}: {text}"));
+    }
+
+    let schema = parse(&text)?;
+    Ok((text, schema))
+}
+
+enum Token<'a> {
+    Name(&'a str),
+    Punct(char),
+}
+
+fn tokens(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '#' => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+            '"' => {
+                let mut escaped = false;
+                for (_, c) in chars.by_ref() {
+                    if c == '"' && !escaped {
+                        break;
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = i + c.len_utf8();
+                while let Some((j, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+                    end = j + c.len_utf8();
+                }
+                tokens.push(Token::Name(&text[i..end]));
+            }
+            '{' | '}' | '(' | ')' | ':' | '$' | '@' => tokens.push(Token::Punct(c)),
+            _ => (),
+        }
+    }
+
+    tokens
+}
+
+/// Completion candidates at `cursor` (a byte offset into `query`), returns the length of the
+/// partially typed word that the chosen candidate replaces.
+pub fn complete(schema: &Schema, query: &str, cursor: usize) -> (usize, Vec<String>) {
+    let before = query.get(..cursor).unwrap_or(query);
+    let partial = before
+        .chars()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect::<String>()
+        .chars()
+        .rev()
+        .collect::<String>();
+    let scanned = &before[..before.len() - partial.len()];
+
+    // selection set types from the root down to the cursor
+    let mut stack: Vec<Option<String>> = Vec::new();
+    let mut operation = "query";
+    let mut last_name: Option<&str> = None;
+    let mut prev_name: Option<&str> = None;
+    let mut args_of: Option<(Option<String>, &str)> = None;
+    let mut paren_depth = 0;
+    for token in tokens(scanned) {
+        match token {
+            Token::Name(_) if paren_depth > 0 => (),
+            Token::Name(name) => {
+                prev_name = last_name;
+                last_name = Some(name);
+                if stack.is_empty() && matches!(name, "query" | "mutation" | "subscription") {
+                    operation = name;
+                }
+            }
+            Token::Punct('(') => {
+                paren_depth += 1;
+                if paren_depth == 1
+                    && let Some(field) = last_name
+                {
+                    args_of = Some((stack.last().cloned().flatten(), field));
+                }
+            }
+            Token::Punct(')') => {
+                paren_depth -= 1;
+                if paren_depth == 0 {
+                    args_of = None;
+                }
+                paren_depth = paren_depth.max(0);
+            }
+            Token::Punct('{') if paren_depth == 0 => {
+                let next = if stack.is_empty() {
+                    match operation {
+                        "mutation" => schema.mutation_type.as_ref(),
+                        "subscription" => schema.subscription_type.as_ref(),
+                        _ => schema.query_type.as_ref(),
+                    }
+                    .map(|t| t.name.clone())
+                } else if prev_name == Some("on") {
+                    last_name.map(str::to_string)
+                } else {
+                    let parent = stack.last().cloned().flatten();
+                    parent
+                        .zip(last_name)
+                        .and_then(|(p, f)| schema.field(&p, f))
+                        .map(|f| f.type_ref.named().to_string())
+                };
+                stack.push(next);
+                last_name = None;
+                prev_name = None;
+            }
+            Token::Punct('}') if paren_depth == 0 => {
+                stack.pop();
+                last_name = None;
+                prev_name = None;
+            }
+            Token::Punct(_) => (),
+        }
+    }
+
+    let candidates: Vec<String> = match args_of {
+        Some((Some(parent), field)) => schema
+            .field(&parent, field)
+            .map(|f| f.args.iter().map(|a| a.name.clone()).collect())
+            .unwrap_or_default(),
+        Some((None, _)) => Vec::new(),
+        None => match stack.last() {
+            Some(Some(type_name)) => schema
+                .get_type(type_name)
+                .and_then(|t| t.fields.as_ref())
+                .map(|fields| fields.iter().map(|f| f.name.clone()).collect())
+                .unwrap_or_default(),
+            Some(None) => Vec::new(),
+            None => ["query", "mutation", "subscription", "fragment"]
+                .iter()
+                .map(|k| k.to_string())
+                .collect(),
+        },
+    };
+
+    let candidates = candidates
+        .into_iter()
+        .filter(|c| c.starts_with(&partial) && *c != partial)
+        .collect();
+    (partial.len(), candidates)
+}
@@ -0,0 +1,432 @@
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor};
+use tonic::{
+    Code, Status,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    metadata::{MetadataKey, MetadataMap, MetadataValue},
+    transport::{Channel, ClientTlsConfig, Endpoint},
+};
+use tonic_reflection::pb::{
+    v1::{
+        ServerReflectionRequest, ServerReflectionResponse,
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+    },
+    v1alpha,
+};
+
+/// Encodes and decodes messages only known at runtime.
+#[derive(Clone)]
+struct DynamicCodec(MessageDescriptor);
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicCodec;
+    type Decoder = DynamicCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        self.clone()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        self.clone()
+    }
+}
+
+impl Encoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(dst)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+impl Decoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let mut msg = DynamicMessage::new(self.0.clone());
+        msg.merge(src)
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Some(msg))
+    }
+}
+
+/// Outcome of a call, `status` is `None` when the call succeeded.
+#[derive(Debug)]
+pub struct CallResult {
+    pub status: Option<Status>,
+    pub headers: Vec<(String, String)>,
+    pub trailers: Vec<(String, String)>,
+    pub messages: usize,
+}
+
+/// `localhost:50051` means plaintext, `https://` uses TLS with the system roots.
+async fn connect(endpoint: &str) -> Result<Channel, String> {
+    let uri = if endpoint.contains("://") {
+        endpoint.to_string()
+    } else {
+        format!("http://{endpoint}")
+    };
+
+    let mut builder = Endpoint::from_shared(uri.clone())
+        .map_err(|e| e.to_string())?
+        .connect_timeout(Duration::from_secs(10));
+    if uri.starts_with("https://") {
+        builder = builder
+            .tls_config(ClientTlsConfig::new().with_native_roots())
+            .map_err(|e| e.to_string())?;
+    }
+
+    builder.connect().await.map_err(|e| format!("{e}: {e:?}"))
+}
+
+/// Compiles `.proto` files, imports are looked up next to each file.
+pub fn load_protos(files: &[PathBuf]) -> Result<DescriptorPool, String> {
+    let includes: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|f| f.parent().map(|p| p.to_path_buf()))
+        .collect();
+    let set = protox::compile(files, includes).map_err(|e| e.to_string())?;
+    DescriptorPool::from_file_descriptor_set(set).map_err(|e| e.to_string())
+}
+
+/// Builds the descriptors of every service the server lists via gRPC server reflection,
+/// `grpc.reflection.v1` or, for servers that do not offer it, `v1alpha`.
+pub async fn reflect(endpoint: &str) -> Result<DescriptorPool, String> {
+    let channel = connect(endpoint).await?;
+    let mut client = Reflection::V1(ServerReflectionClient::new(channel.clone()));
+
+    let list = MessageRequest::ListServices(String::new());
+    let listed = match client.request(list.clone()).await {
+        Err(status) if status.code() == Code::Unimplemented => {
+            client = Reflection::V1Alpha(
+                v1alpha::server_reflection_client::ServerReflectionClient::new(channel),
+            );
+            client.request(list).await
+        }
+        listed => listed,
+    };
+    let services = match listed.map_err(reflection_error)? {
+        MessageResponse::ListServicesResponse(list) => list.service,
+        other => return Err(format!("unexpected reflection response {other:?}")),
+    };
+
+    let mut files: Vec<prost_types::FileDescriptorProto> = Vec::new();
+    let mut pending: Vec<MessageRequest> = services
+        .into_iter()
+        .filter(|s| !s.name.starts_with("grpc.reflection."))
+        .map(|s| MessageRequest::FileContainingSymbol(s.name))
+        .collect();
+    while let Some(request) = pending.pop() {
+        let MessageResponse::FileDescriptorResponse(response) =
+            client.request(request).await.map_err(reflection_error)?
+        else {
+            continue;
+        };
+
+        for bytes in response.file_descriptor_proto {
+            let file = prost_types::FileDescriptorProto::decode(bytes.as_slice())
+                .map_err(|e| e.to_string())?;
+            if files.iter().any(|f| f.name == file.name) {
+                continue;
+            }
+            for dependency in &file.dependency {
+                if !files.iter().any(|f| f.name.as_ref() == Some(dependency)) {
+                    pending.push(MessageRequest::FileByFilename(dependency.clone()));
+                }
+            }
+            files.push(file);
+        }
+    }
+
+    DescriptorPool::from_file_descriptor_set(prost_types::FileDescriptorSet { file: files })
+        .map_err(|e| e.to_string())
+}
+
+fn reflection_error(status: Status) -> String {
+    format!("server reflection: {}", status.message())
+}
+
+/// The reflection service of a server, both versions have the same messages on the wire.
+enum Reflection {
+    V1(ServerReflectionClient<Channel>),
+    V1Alpha(v1alpha::server_reflection_client::ServerReflectionClient<Channel>),
+}
+
+impl Reflection {
+    async fn request(&mut self, request: MessageRequest) -> Result<MessageResponse, Status> {
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(request),
+        };
+        let response = match self {
+            Reflection::V1(client) => {
+                client
+                    .server_reflection_info(futures_util::stream::iter([request]))
+                    .await?
+                    .into_inner()
+                    .message()
+                    .await?
+            }
+            Reflection::V1Alpha(client) => {
+                let request =
+                    v1alpha::ServerReflectionRequest::decode(request.encode_to_vec().as_slice())
+                        .map_err(|e| Status::internal(e.to_string()))?;
+                let response = client
+                    .server_reflection_info(futures_util::stream::iter([request]))
+                    .await?
+                    .into_inner()
+                    .message()
+                    .await?;
+                response
+                    .map(|r| ServerReflectionResponse::decode(r.encode_to_vec().as_slice()))
+                    .transpose()
+                    .map_err(|e| Status::internal(e.to_string()))?
+            }
+        };
+
+        match response.map(|r| r.message_response) {
+            Some(Some(MessageResponse::ErrorResponse(e))) => {
+                Err(Status::new(Code::from(e.error_code), e.error_message))
+            }
+            Some(Some(r)) => Ok(r),
+            Some(None) => Err(Status::unknown("empty reflection response")),
+            None => Err(Status::unknown("no reflection response")),
+        }
+    }
+}
+
+/// `package.Service/Method` for every method in the pool.
+pub fn method_names(pool: &DescriptorPool) -> Vec<String> {
+    pool.services()
+        .flat_map(|s| {
+            s.methods()
+                .map(|m| format!("{}/{}", s.full_name(), m.name()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+pub fn find_method(pool: &DescriptorPool, name: &str) -> Option<MethodDescriptor> {
+    let (service, method) = name.split_once('/')?;
+    pool.get_service_by_name(service)?
+        .methods()
+        .find(|m| m.name() == method)
+}
+
+/// The request message with every field at its default value, as a starting point.
+pub fn request_template(method: &MethodDescriptor) -> String {
+    let msg = DynamicMessage::new(method.input());
+    let options = prost_reflect::SerializeOptions::new().skip_default_fields(false);
+    let mut out = Vec::new();
+    let mut serializer = serde_json::Serializer::pretty(&mut out);
+    match msg.serialize_with_options(&mut serializer, &options) {
+        Ok(()) => String::from_utf8_lossy(&out).to_string(),
+        Err(_) => "{}".to_string(),
+    }
+}
+
+fn metadata_pairs(metadata: &MetadataMap) -> Vec<(String, String)> {
+    metadata
+        .clone()
+        .into_headers()
+        .iter()
+        .map(|(n, v)| {
+            (
+                n.to_string(),
+                String::from_utf8_lossy(v.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Invokes a unary or server-streaming method, each response message is passed to
+/// `on_message` as pretty JSON as soon as it arrives.
+pub async fn call(
+    endpoint: &str,
+    method: &MethodDescriptor,
+    json: &str,
+    metadata: &[(String, String)],
+    mut on_message: impl FnMut(String),
+) -> Result<CallResult, String> {
+    if method.is_client_streaming() {
+        return Err(format!(
+            "{} is client streaming, only unary and server-streaming calls are supported",
+            method.full_name()
+        ));
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let message = DynamicMessage::deserialize(method.input(), &mut deserializer)
+        .map_err(|e| format!("request message: {e}"))?;
+
+    let mut request = tonic::Request::new(message);
+    for (name, value) in metadata {
+        let key = MetadataKey::from_str(name.trim()).map_err(|e| format!("{name}: {e}"))?;
+        let value = MetadataValue::from_str(value.trim()).map_err(|e| format!("{name}: {e}"))?;
+        request.metadata_mut().insert(key, value);
+    }
+
+    let path = tonic::codegen::http::uri::PathAndQuery::from_str(&format!(
+        "/{}/{}",
+        method.parent_service().full_name(),
+        method.name()
+    ))
+    .map_err(|e| e.to_string())?;
+
+    let mut grpc = tonic::client::Grpc::new(connect(endpoint).await?);
+    grpc.ready().await.map_err(|e| e.to_string())?;
+    let codec = DynamicCodec(method.output());
+
+    let to_json = |msg: &DynamicMessage| serde_json::to_string_pretty(msg).unwrap_or_default();
+    let mut result = CallResult {
+        status: None,
+        headers: Vec::new(),
+        trailers: Vec::new(),
+        messages: 0,
+    };
+
+    if method.is_server_streaming() {
+        let response = match grpc.server_streaming(request, path, codec).await {
+            Ok(response) => response,
+            Err(status) => return Ok(failed(result, status)),
+        };
+        result.headers = metadata_pairs(response.metadata());
+        let mut stream = response.into_inner();
+        loop {
+            match stream.message().await {
+                Ok(Some(msg)) => {
+                    result.messages += 1;
+                    on_message(to_json(&msg));
+                }
+                Ok(None) => break,
+                Err(status) => return Ok(failed(result, status)),
+            }
+        }
+        if let Ok(Some(trailers)) = stream.trailers().await {
+            result.trailers = metadata_pairs(&trailers);
+        }
+    } else {
+        match grpc.unary(request, path, codec).await {
+            Ok(response) => {
+                result.headers = metadata_pairs(response.metadata());
+                result.messages = 1;
+                on_message(to_json(response.get_ref()));
+            }
+            Err(status) => return Ok(failed(result, status)),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Unary trailers arrive merged into the response metadata, error trailers in the status.
+fn failed(mut result: CallResult, status: Status) -> CallResult {
+    result.trailers = metadata_pairs(status.metadata());
+    result.status = Some(status);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `test.Greeter` with `rpc SayHello (HelloRequest) returns (HelloReply)`.
+    fn greeter() -> prost_types::FileDescriptorProto {
+        use prost_types::{
+            DescriptorProto, FieldDescriptorProto, MethodDescriptorProto, ServiceDescriptorProto,
+            field_descriptor_proto::{Label, Type},
+        };
+
+        let message = |name: &str, field: &str| DescriptorProto {
+            name: Some(name.to_string()),
+            field: vec![FieldDescriptorProto {
+                name: Some(field.to_string()),
+                json_name: Some(field.to_string()),
+                number: Some(1),
+                label: Some(Label::Optional as i32),
+                r#type: Some(Type::String as i32),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        prost_types::FileDescriptorProto {
+            name: Some("greeter.proto".to_string()),
+            package: Some("test".to_string()),
+            syntax: Some("proto3".to_string()),
+            message_type: vec![
+                message("HelloRequest", "name"),
+                message("HelloReply", "message"),
+            ],
+            service: vec![ServiceDescriptorProto {
+                name: Some("Greeter".to_string()),
+                method: vec![MethodDescriptorProto {
+                    name: Some("SayHello".to_string()),
+                    input_type: Some(".test.HelloRequest".to_string()),
+                    output_type: Some(".test.HelloReply".to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Serves only reflection of the greeter on 127.0.0.1, answers the endpoint.
+    async fn serve(v1alpha: bool) -> String {
+        let set = prost_types::FileDescriptorSet {
+            file: vec![greeter()],
+        };
+        let builder =
+            tonic_reflection::server::Builder::configure().register_file_descriptor_set(set);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let incoming = tonic::transport::server::TcpIncoming::from(listener);
+        let mut server = tonic::transport::Server::builder();
+        let router = if v1alpha {
+            server.add_service(builder.build_v1alpha().unwrap())
+        } else {
+            server.add_service(builder.build_v1().unwrap())
+        };
+        tokio::spawn(router.serve_with_incoming(incoming));
+        endpoint
+    }
+
+    #[tokio::test]
+    async fn reflect_v1() {
+        let endpoint = serve(false).await;
+        let pool = reflect(&endpoint).await.unwrap();
+        assert_eq!(method_names(&pool), ["test.Greeter/SayHello"]);
+    }
+
+    #[tokio::test]
+    async fn reflect_falls_back_to_v1alpha() {
+        let endpoint = serve(true).await;
+        let pool = reflect(&endpoint).await.unwrap();
+        assert_eq!(method_names(&pool), ["test.Greeter/SayHello"]);
+    }
+
+    #[tokio::test]
+    async fn call_reports_the_status() {
+        let endpoint = serve(false).await;
+        let pool = reflect(&endpoint).await.unwrap();
+        let method = find_method(&pool, "test.Greeter/SayHello").unwrap();
+        assert_eq!(request_template(&method), "{\n  \"name\": \"\"\n}");
+
+        let mut messages = Vec::new();
+        let result = call(&endpoint, &method, r#"{"name": "grillon"}"#, &[], |m| {
+            messages.push(m)
+        })
+        .await
+        .unwrap();
+        assert_eq!(result.status.map(|s| s.code()), Some(Code::Unimplemented));
+        assert!(messages.is_empty());
+    }
+}
@@ -0,0 +1,435 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    path::PathBuf,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use fltk::{
+    app, button, enums, frame,
+    group::{self, Flex, Tabs},
+    input,
+    menu::Choice,
+    prelude::*,
+    text::{self, TextBuffer},
+};
+use prost_reflect::DescriptorPool;
+
+use crate::{
+    AppWindow, GlobalAppMsg, HasId,
+    autosave::Autosave,
+    controls,
+    db::{OpenWindow, WINDOW_KIND_GRPC, WindowLayout},
+    grpc,
+    host::Host,
+    next_window_id, secrets,
+    shortcuts::Action,
+};
+
+/// Empty when the methods come from server reflection.
+const PROTO_FILES_OPTION: &str = "proto_files";
+const METHOD_OPTION: &str = "grpc_method";
+
+/// gRPC client, methods come from server reflection or from `.proto` files and the
+/// request message is written as JSON.
+pub struct GrpcWindow {
+    id: usize,
+    host: Host,
+    state: Rc<dyn Fn() -> OpenWindow>,
+    autosave: Autosave,
+    actions: Rc<dyn Fn(Action)>,
+}
+
+fn fill_methods(choice: &mut Choice, pool: &DescriptorPool, selected: &str) {
+    choice.clear();
+    for (i, name) in grpc::method_names(pool).iter().enumerate() {
+        choice.add_choice(&name.replace('/', "\\/"));
+        if name == selected {
+            choice.set_value(i as i32);
+        }
+    }
+    if choice.value() < 0 && choice.size() > 0 {
+        choice.set_value(0);
+    }
+}
+
+fn selected_method(choice: &Choice, pool: &Mutex<Option<DescriptorPool>>) -> Option<String> {
+    let pool = pool.lock().unwrap();
+    let idx = usize::try_from(choice.value()).ok()?;
+    grpc::method_names(pool.as_ref()?).get(idx).cloned()
+}
+
+fn metadata_pairs(buf: &TextBuffer) -> Vec<(String, String)> {
+    buf.text()
+        .lines()
+        .filter_map(|l| l.split_once(':'))
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect()
+}
+
+impl GrpcWindow {
+    pub fn new(wnd: Option<&OpenWindow>, tabs: Option<&Tabs>) -> Self {
+        let id = wnd.map_or(next_window_id(), |f| f.id as usize);
+
+        let mut host = Host::new(tabs, "Le Grillon - gRPC", wnd.map(|w| &w.layout.0));
+
+        let mut col = group::Flex::default_fill().column();
+        let mut row = group::Flex::default().row();
+        let endpoint_label = frame::Frame::default().with_label("gRPC:");
+        row.fixed(&endpoint_label, 64);
+        let mut endpoint_input = input::Input::default();
+        endpoint_input.set_tooltip("host:port for plaintext, https://host:port for TLS");
+        let mut reflect_btn = button::Button::default().with_label("Reflect");
+        row.fixed(&reflect_btn, 128);
+        let mut proto_btn = button::Button::default().with_label("Load .proto...");
+        row.fixed(&proto_btn, 160);
+        row.end();
+        col.fixed(&row, 32);
+
+        let mut row = group::Flex::default().row();
+        let method_label = frame::Frame::default().with_label("Method:");
+        row.fixed(&method_label, 64);
+        let mut method_choice = Choice::default();
+        let mut invoke_btn = button::Button::default().with_label("Invoke");
+        row.fixed(&invoke_btn, 128);
+        row.end();
+        col.fixed(&row, 32);
+
+        let row = group::Flex::default_fill().row();
+        let mut tab = Tabs::default_fill();
+        let grp1 = Flex::default_fill().with_label("Message\t\t").row();
+        let mut message_buf = text::TextBuffer::default();
+        let mut message = text::TextEditor::default();
+        message.set_buffer(message_buf.clone());
+        message.set_text_font(enums::Font::Courier);
+        grp1.end();
+        let grp2 = Flex::default_fill().with_label("Metadata\t\t").row();
+        let mut metadata_buf = text::TextBuffer::default();
+        let mut metadata = text::TextEditor::default();
+        metadata.set_buffer(metadata_buf.clone());
+        metadata.set_text_font(enums::Font::Courier);
+        grp2.end();
+        tab.end();
+        tab.auto_layout();
+
+        let result_buf = text::TextBuffer::default();
+        let mut result = text::TextDisplay::default();
+        result.set_buffer(result_buf.clone());
+        result.set_text_font(enums::Font::Courier);
+        row.end();
+
+        let mut status = frame::Frame::default();
+        status.set_frame(enums::FrameType::FlatBox);
+        col.fixed(&status, 32);
+        col.end();
+
+        host.show();
+
+        let pool: Arc<Mutex<Option<DescriptorPool>>> = Arc::new(Mutex::new(None));
+        let proto_files: Rc<RefCell<Vec<PathBuf>>> = Rc::new(RefCell::new(Vec::new()));
+        let saved_method = wnd
+            .and_then(|w| w.options.0.get(METHOD_OPTION).cloned())
+            .unwrap_or_default();
+
+        if let Some(wnd) = wnd {
+            endpoint_input.set_value(&wnd.uri);
+            host.set_label(&wnd.uri);
+            message_buf.set_text(&wnd.body);
+            for (n, v) in wnd.headers.0.iter() {
+                metadata_buf.append(&format!("{n}:{v}\n"));
+            }
+            if let Some(files) = wnd.options.0.get(PROTO_FILES_OPTION) {
+                *proto_files.borrow_mut() = files.lines().map(PathBuf::from).collect();
+            }
+            controls::select_tab(&mut tab, wnd.layout.params_tab);
+        }
+
+        let (s, _) = app::channel();
+        host.on_events(id, "GrpcWindow");
+
+        let state = {
+            let endpoint_input = endpoint_input.clone();
+            let tab = tab.clone();
+            let host = host.clone();
+            let message_buf = message_buf.clone();
+            let metadata_buf = metadata_buf.clone();
+            let method_choice = method_choice.clone();
+            let pool = pool.clone();
+            let proto_files = proto_files.clone();
+            move || {
+                let files = proto_files.borrow();
+                let mut options = BTreeMap::new();
+                options.insert(
+                    PROTO_FILES_OPTION.to_string(),
+                    files
+                        .iter()
+                        .map(|f| f.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+                if let Some(method) = selected_method(&method_choice, &pool) {
+                    options.insert(METHOD_OPTION.to_string(), method);
+                }
+
+                OpenWindow {
+                    id: id as i32,
+                    method: "POST".to_string(),
+                    uri: endpoint_input.value(),
+                    path: "".to_string(),
+                    query: "".to_string(),
+                    headers: sqlx::types::Json(metadata_pairs(&metadata_buf)),
+                    body: message_buf.text(),
+                    environment_id: None,
+                    collection_id: None,
+                    kind: WINDOW_KIND_GRPC.to_string(),
+                    options: sqlx::types::Json(options),
+                    layout: sqlx::types::Json(WindowLayout {
+                        params_tab: controls::active_tab(&tab),
+                        ..host.layout()
+                    }),
+                    response: None,
+                    name: String::new(),
+                }
+            }
+        };
+        let state: Rc<dyn Fn() -> OpenWindow> = Rc::new(state);
+        let save_state: Rc<dyn Fn()> = {
+            let state = state.clone();
+            Rc::new(move || s.send(GlobalAppMsg::SaveWindowState(state())))
+        };
+
+        let autosave = Autosave::new(save_state.clone());
+        for buf in [&message_buf, &metadata_buf] {
+            let p_autosave = autosave.clone();
+            buf.clone().add_modify_callback(move |_, i, d, _, _| {
+                if i > 0 || d > 0 {
+                    p_autosave.touch();
+                }
+            });
+        }
+        let p_autosave = autosave.clone();
+        endpoint_input.set_trigger(enums::CallbackTrigger::Changed);
+        endpoint_input.set_callback(move |_| p_autosave.touch());
+
+        let actions: Rc<dyn Fn(Action)> = {
+            let (invoke_btn, endpoint_input, tab) =
+                (invoke_btn.clone(), endpoint_input.clone(), tab.clone());
+            let state = state.clone();
+            Rc::new(move |action: Action| match action {
+                Action::Send if invoke_btn.active() => invoke_btn.clone().do_callback(),
+                Action::FocusUri => {
+                    let _ = endpoint_input.clone().take_focus();
+                }
+                Action::BodyTab => controls::select_tab(&mut tab.clone(), 0),
+                Action::HeadersTab => controls::select_tab(&mut tab.clone(), 1),
+                Action::Duplicate => s.send(GlobalAppMsg::Restore(state().duplicate())),
+                _ => (),
+            })
+        };
+
+        let reflect = {
+            let endpoint_input = endpoint_input.clone();
+            let method_choice = method_choice.clone();
+            let pool = pool.clone();
+            let proto_files = proto_files.clone();
+            let status = status.clone();
+            let saved_method = saved_method.clone();
+            move || {
+                proto_files.borrow_mut().clear();
+                let endpoint = endpoint_input.value();
+                let mut method_choice = method_choice.clone();
+                let pool = pool.clone();
+                let mut status = status.clone();
+                let saved_method = saved_method.clone();
+                status.set_label(&format!("Reflecting {endpoint}..."));
+
+                tokio::spawn(async move {
+                    match grpc::reflect(&endpoint).await {
+                        Ok(reflected) => {
+                            status.set_label(&format!(
+                                "REFLECTION | SERVICES={}",
+                                reflected.services().count()
+                            ));
+                            fill_methods(&mut method_choice, &reflected, &saved_method);
+                            *pool.lock().unwrap() = Some(reflected);
+                        }
+                        Err(e) => status.set_label(&format!("REFLECTION | {e}")),
+                    }
+                    app::awake();
+                    app::redraw();
+                });
+            }
+        };
+        let reflect = Rc::new(reflect);
+
+        let load_protos = {
+            let method_choice = method_choice.clone();
+            let pool = pool.clone();
+            let proto_files = proto_files.clone();
+            let status = status.clone();
+            move |selected: &str| {
+                let (mut method_choice, mut status) = (method_choice.clone(), status.clone());
+                let files = proto_files.borrow().clone();
+                match grpc::load_protos(&files) {
+                    Ok(loaded) => {
+                        status
+                            .set_label(&format!("PROTO | SERVICES={}", loaded.services().count()));
+                        fill_methods(&mut method_choice, &loaded, selected);
+                        *pool.lock().unwrap() = Some(loaded);
+                    }
+                    Err(e) => status.set_label(&format!("PROTO | {e}")),
+                }
+            }
+        };
+        let load_protos = Rc::new(load_protos);
+
+        if wnd.is_some() {
+            if proto_files.borrow().is_empty() {
+                reflect();
+            } else {
+                load_protos(&saved_method);
+            }
+        }
+
+        let p_reflect = reflect.clone();
+        reflect_btn.set_callback(move |_| p_reflect());
+
+        let p_load_protos = load_protos.clone();
+        let p_proto_files = proto_files.clone();
+        proto_btn.set_callback(move |_| {
+            let files = controls::pick_files("*.proto");
+            if files.is_empty() {
+                return;
+            }
+            *p_proto_files.borrow_mut() = files;
+            p_load_protos("");
+        });
+
+        let p_pool = pool.clone();
+        let mut p_message_buf = message_buf.clone();
+        let p_autosave = autosave.clone();
+        method_choice.set_callback(move |c| {
+            p_autosave.touch();
+            let text = p_message_buf.text();
+            if !text.trim().is_empty() && text.trim() != "{}" {
+                return;
+            }
+            if let Some(name) = selected_method(c, &p_pool)
+                && let Some(pool) = p_pool.lock().unwrap().as_ref()
+                && let Some(method) = grpc::find_method(pool, &name)
+            {
+                p_message_buf.set_text(&grpc::request_template(&method));
+            }
+        });
+
+        let mut p_host = host.clone();
+        invoke_btn.set_callback(move |btn| {
+            let endpoint = endpoint_input.value();
+            let Some(method_name) = selected_method(&method_choice, &pool) else {
+                status.set_label("Reflect or load a .proto file and pick a method first");
+                return;
+            };
+            let Some(method) = pool
+                .lock()
+                .unwrap()
+                .as_ref()
+                .and_then(|p| grpc::find_method(p, &method_name))
+            else {
+                return;
+            };
+
+            p_host.set_label(&endpoint);
+            save_state();
+
+            let json = message_buf.text();
+            let metadata = metadata_pairs(&metadata_buf);
+            let values = metadata.iter().map(|(_, v)| v.as_str());
+            if let Err(e) = secrets::check_sendable(
+                [endpoint.as_str(), json.as_str()].into_iter().chain(values),
+            ) {
+                status.set_label(&e);
+                return;
+            }
+            let mut result = result_buf.clone();
+            let mut status = status.clone();
+            let mut btn = btn.clone();
+            result.set_text("");
+            status.set_label(&format!("Calling {method_name}..."));
+            btn.deactivate();
+
+            tokio::spawn(async move {
+                let start = std::time::Instant::now();
+                let mut stream_buf = result.clone();
+                let called = grpc::call(&endpoint, &method, &json, &metadata, |msg| {
+                    stream_buf.append(&msg);
+                    stream_buf.append("\n");
+                    app::awake();
+                })
+                .await;
+                let elapsed = start.elapsed();
+
+                match called {
+                    Ok(call) => {
+                        let code = call
+                            .status
+                            .as_ref()
+                            .map_or("OK".to_string(), |s| format!("{:?}", s.code()));
+                        if let Some(s) = &call.status {
+                            result.append(&format!("\n{:?}: {}\n", s.code(), s.message()));
+                        }
+                        result.append("\n--- headers\n");
+                        for (n, v) in &call.headers {
+                            result.append(&format!("{n}: {v}\n"));
+                        }
+                        result.append("--- trailers\n");
+                        for (n, v) in &call.trailers {
+                            result.append(&format!("{n}: {v}\n"));
+                        }
+                        status.set_label(&format!(
+                            "STATUS={code} | MESSAGES={} | RTT={elapsed:?}",
+                            call.messages
+                        ));
+                    }
+                    Err(e) => {
+                        status.set_label("");
+                        result.append(&e);
+                    }
+                }
+
+                btn.activate();
+                app::awake();
+                app::redraw();
+            });
+        });
+
+        Self {
+            id,
+            host,
+            state,
+            autosave,
+            actions,
+        }
+    }
+}
+
+impl HasId for GrpcWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for GrpcWindow {
+    fn close(&mut self) {
+        self.autosave.cancel();
+        self.host.close();
+    }
+
+    fn window_state(&self) -> Option<OpenWindow> {
+        Some((self.state)())
+    }
+
+    fn run_action(&mut self, action: Action) {
+        (self.actions)(action)
+    }
+}
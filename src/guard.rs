@@ -0,0 +1,101 @@
+use std::sync::OnceLock;
+
+use reqwest::{Method, Url};
+
+/// Methods that only read. Any other method, custom ones included, may change data on
+/// the server and needs a confirmation before it is sent to a protected host.
+pub fn is_safe(method: &Method) -> bool {
+    matches!(method.as_str(), "GET" | "HEAD" | "OPTIONS" | "TRACE")
+}
+
+/// Matches a host against a pattern where `*` stands for any run of characters, e.g.
+/// `*.prod.example.com` or `db-*`. Case and the trailing dot of a fully qualified name do
+/// not matter.
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern
+        .trim()
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+        .into_bytes();
+    let host = host.trim_end_matches('.').to_ascii_lowercase().into_bytes();
+    let (mut p, mut h) = (0, 0);
+    // where to resume after the last star, when what followed it stops matching
+    let mut star = None;
+    while h < host.len() {
+        if pattern.get(p) == Some(&b'*') {
+            star = Some((p + 1, h));
+            p += 1;
+        } else if pattern.get(p) == Some(&host[h]) {
+            p += 1;
+            h += 1;
+        } else if let Some((after_star, from)) = star {
+            p = after_star;
+            h = from + 1;
+            star = Some((after_star, from + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+static PROTECTED_HOSTS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Sets up the protected host patterns from the settings, before the first window opens.
+pub fn init(patterns: &[String]) {
+    let _ = PROTECTED_HOSTS.set(patterns.to_vec());
+}
+
+/// The host of `uri` when it matches one of the protected host patterns.
+pub fn protected_host(uri: &str) -> Option<String> {
+    let url = Url::parse(uri).ok()?;
+    let host = url
+        .host_str()?
+        .trim_matches(['[', ']'])
+        .trim_end_matches('.');
+    PROTECTED_HOSTS
+        .get()?
+        .iter()
+        .any(|p| host_matches(p, host))
+        .then(|| host.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_host_patterns() {
+        assert!(host_matches("*.prod.example.com", "api.prod.example.com"));
+        assert!(host_matches("*.prod.example.com", "a.b.prod.example.com"));
+        assert!(!host_matches("*.prod.example.com", "prod.example.com"));
+        assert!(!host_matches(
+            "*.prod.example.com",
+            "api.prod.example.com.evil.test"
+        ));
+        assert!(host_matches("db-*", "db-1"));
+        assert!(host_matches("db-*", "db-"));
+        assert!(!host_matches("db-*", "mydb-1"));
+        assert!(host_matches("API.Prod.*", "api.prod.example.com"));
+        assert!(host_matches("*.prod.example.com", "API.PROD.EXAMPLE.COM"));
+        assert!(host_matches("*.prod.example.com.", "api.prod.example.com"));
+        assert!(host_matches("*.prod.example.com", "api.prod.example.com."));
+        assert!(host_matches("*", "anything"));
+        assert!(!host_matches("", "anything"));
+    }
+
+    #[test]
+    fn protects_matching_hosts() {
+        init(&["*.prod.example.com".to_string(), "::1".to_string()]);
+        assert_eq!(
+            protected_host("https://API.prod.example.com./users"),
+            Some("api.prod.example.com".to_string())
+        );
+        assert_eq!(
+            protected_host("http://[::1]:8080/"),
+            Some("::1".to_string())
+        );
+        assert_eq!(protected_host("https://prod.example.com/"), None);
+        assert_eq!(protected_host("not a url"), None);
+    }
+}
@@ -0,0 +1,359 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::SentRequest,
+    secrets::{self, SecretFlags},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Har {
+    pub log: Log,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Log {
+    pub version: String,
+    pub creator: Creator,
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub started_date_time: String,
+    pub time: f64,
+    pub request: Request,
+    pub response: Response,
+    #[serde(default)]
+    pub cache: serde_json::Value,
+    pub timings: Timings,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    #[serde(default)]
+    pub query_string: Vec<NameValue>,
+    pub post_data: Option<PostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    pub status: i64,
+    #[serde(default)]
+    pub status_text: String,
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    pub content: Content,
+    #[serde(default, rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Content {
+    pub size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+/// Phase durations in milliseconds, `-1` when a phase does not apply.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Timings {
+    #[serde(default = "not_applicable", skip_serializing_if = "is_not_applicable")]
+    pub blocked: f64,
+    #[serde(default = "not_applicable", skip_serializing_if = "is_not_applicable")]
+    pub dns: f64,
+    #[serde(default = "not_applicable", skip_serializing_if = "is_not_applicable")]
+    pub connect: f64,
+    #[serde(default = "not_applicable", skip_serializing_if = "is_not_applicable")]
+    pub ssl: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+fn not_applicable() -> f64 {
+    -1.0
+}
+
+/// Phases stored as zero were not measured.
+fn measured(ms: f64) -> f64 {
+    if ms > 0.0 { ms } else { -1.0 }
+}
+
+fn is_not_applicable(v: &f64) -> bool {
+    *v < 0.0
+}
+
+pub fn import(json: &str) -> Result<Vec<SentRequest>, serde_json::Error> {
+    let har: Har = serde_json::from_str(json)?;
+
+    let entries = har
+        .log
+        .entries
+        .into_iter()
+        .map(|e| {
+            let sent_at = chrono::DateTime::parse_from_rfc3339(&e.started_date_time)
+                .map(|d| d.with_timezone(&chrono::Local))
+                .unwrap_or_else(|_| chrono::Local::now());
+
+            let response_body = match (e.response.content.text, e.response.content.encoding) {
+                (Some(text), Some(enc)) if enc == "base64" => STANDARD
+                    .decode(&text)
+                    .ok()
+                    .and_then(|b| String::from_utf8(b).ok())
+                    .unwrap_or(text),
+                (Some(text), _) => text,
+                (None, _) => String::new(),
+            };
+
+            SentRequest {
+                id: 0,
+                sent_at,
+                method: e.request.method,
+                uri: e.request.url,
+                path: "".to_string(),
+                query: "".to_string(),
+                headers: sqlx::types::Json(
+                    e.request
+                        .headers
+                        .into_iter()
+                        .filter(|h| !h.name.starts_with(':'))
+                        .map(|h| (h.name, h.value))
+                        .collect(),
+                ),
+                body: e.request.post_data.map(|p| p.text).unwrap_or_default(),
+                status: e.response.status,
+                response_headers: sqlx::types::Json(
+                    e.response
+                        .headers
+                        .into_iter()
+                        .map(|h| (h.name, h.value))
+                        .collect(),
+                ),
+                response_body,
+                wait_ms: e.timings.wait.max(0.0),
+                receive_ms: e.timings.receive.max(0.0),
+                dns_ms: e.timings.dns.max(0.0),
+                // HAR counts the TLS handshake as part of connect
+                connect_ms: (e.timings.connect - e.timings.ssl.max(0.0)).max(0.0),
+                tls_ms: e.timings.ssl.max(0.0),
+            }
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// Secrets in headers and query strings are redacted, the values of secret variables were
+/// when the requests were recorded.
+pub fn export(requests: &[SentRequest]) -> Result<String, serde_json::Error> {
+    let entries = requests
+        .iter()
+        .map(|r| secrets::redact_sent(r, &[], &SecretFlags::new()))
+        .map(|r| {
+            let header_value = |headers: &[(String, String)], name: &str| {
+                headers
+                    .iter()
+                    .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                    .map(|(_, v)| v.clone())
+                    .unwrap_or_default()
+            };
+            let to_name_values = |headers: &[(String, String)]| {
+                headers
+                    .iter()
+                    .map(|(n, v)| NameValue {
+                        name: n.clone(),
+                        value: v.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            let query_string = reqwest::Url::parse(&r.uri)
+                .map(|u| {
+                    u.query_pairs()
+                        .map(|(n, v)| NameValue {
+                            name: n.into_owned(),
+                            value: v.into_owned(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let post_data = (!r.body.is_empty()).then(|| PostData {
+                mime_type: header_value(&r.headers.0, "content-type"),
+                text: r.body.clone(),
+            });
+
+            Entry {
+                started_date_time: r.sent_at.to_rfc3339(),
+                time: r.dns_ms + r.connect_ms + r.tls_ms + r.wait_ms + r.receive_ms,
+                request: Request {
+                    method: r.method.clone(),
+                    url: r.uri.clone(),
+                    http_version: "HTTP/1.1".to_string(),
+                    cookies: Vec::new(),
+                    headers: to_name_values(&r.headers.0),
+                    query_string,
+                    post_data,
+                    headers_size: -1,
+                    body_size: r.body.len() as i64,
+                },
+                response: Response {
+                    status: r.status,
+                    status_text: reqwest::StatusCode::from_u16(r.status as u16)
+                        .ok()
+                        .and_then(|s| s.canonical_reason())
+                        .unwrap_or_default()
+                        .to_string(),
+                    http_version: "HTTP/1.1".to_string(),
+                    cookies: Vec::new(),
+                    headers: to_name_values(&r.response_headers.0),
+                    content: Content {
+                        size: r.response_body.len() as i64,
+                        mime_type: header_value(&r.response_headers.0, "content-type"),
+                        text: Some(r.response_body.clone()),
+                        encoding: None,
+                    },
+                    redirect_url: header_value(&r.response_headers.0, "location"),
+                    headers_size: -1,
+                    body_size: r.response_body.len() as i64,
+                },
+                cache: serde_json::json!({}),
+                timings: Timings {
+                    blocked: -1.0,
+                    dns: measured(r.dns_ms),
+                    connect: measured(r.connect_ms + r.tls_ms),
+                    ssl: measured(r.tls_ms),
+                    send: 0.0,
+                    wait: r.wait_ms,
+                    receive: r.receive_ms,
+                },
+            }
+        })
+        .collect();
+
+    let har = Har {
+        log: Log {
+            version: "1.2".to_string(),
+            creator: Creator {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            entries,
+        },
+    };
+
+    serde_json::to_string_pretty(&har)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAR: &str = r#"{
+        "log": {
+            "version": "1.2",
+            "creator": {"name": "browser", "version": "1"},
+            "entries": [{
+                "startedDateTime": "2024-05-01T10:00:00.000Z",
+                "time": 42.5,
+                "request": {
+                    "method": "POST",
+                    "url": "https://api.example.com/users?page=2",
+                    "httpVersion": "HTTP/2",
+                    "headers": [
+                        {"name": ":authority", "value": "api.example.com"},
+                        {"name": "content-type", "value": "application/json"}
+                    ],
+                    "postData": {"mimeType": "application/json", "text": "{\"name\":\"a\"}"},
+                    "headersSize": -1,
+                    "bodySize": 12
+                },
+                "response": {
+                    "status": 201,
+                    "httpVersion": "HTTP/2",
+                    "headers": [{"name": "content-type", "value": "application/json"}],
+                    "content": {"size": 8, "mimeType": "application/json", "text": "eyJpZCI6MX0=", "encoding": "base64"},
+                    "headersSize": -1,
+                    "bodySize": 8
+                },
+                "timings": {"dns": -1, "connect": 12, "ssl": 8, "send": 0, "wait": 20.5, "receive": 2}
+            }]
+        }
+    }"#;
+
+    #[test]
+    fn imports_entries_as_sent_requests() {
+        let sent = import(HAR).unwrap();
+        assert_eq!(sent.len(), 1);
+        let r = &sent[0];
+        assert_eq!(
+            (r.method.as_str(), r.uri.as_str(), r.status),
+            ("POST", "https://api.example.com/users?page=2", 201)
+        );
+        // HTTP/2 pseudo headers are not sent as headers
+        assert_eq!(
+            r.headers.0,
+            [("content-type".to_string(), "application/json".to_string())]
+        );
+        assert_eq!(r.body, r#"{"name":"a"}"#);
+        assert_eq!(r.response_body, r#"{"id":1}"#);
+        assert_eq!((r.dns_ms, r.wait_ms, r.receive_ms), (0.0, 20.5, 2.0));
+    }
+
+    #[test]
+    fn export_imports_again() {
+        let sent = import(HAR).unwrap();
+        let again = import(&export(&sent).unwrap()).unwrap();
+        assert_eq!(again.len(), 1);
+        let (a, b) = (&sent[0], &again[0]);
+        assert_eq!((&a.method, &a.uri, &a.body), (&b.method, &b.uri, &b.body));
+        assert_eq!(a.headers.0, b.headers.0);
+        assert_eq!((a.status, &a.response_body), (b.status, &b.response_body));
+        assert_eq!(a.sent_at, b.sent_at);
+    }
+}
@@ -0,0 +1,175 @@
+use std::rc::Rc;
+
+use fltk::{
+    app, browser, button, dialog,
+    enums::{self, Event},
+    frame, group,
+    prelude::*,
+    text,
+    window::{self, DoubleWindow},
+};
+
+use crate::{
+    AppWindow, GlobalAppMsg, HasId, controls,
+    db::{OpenWindow, SentRequest},
+    har, next_window_id,
+    timing::Timings,
+};
+
+/// Lists sent requests, either the local history or the entries of an imported HAR archive.
+pub struct HistoryWindow {
+    id: usize,
+    window: DoubleWindow,
+}
+
+impl HistoryWindow {
+    pub fn new(title: &str, entries: &[SentRequest]) -> Self {
+        let id = next_window_id();
+
+        let mut win = window::DoubleWindow::default()
+            .with_size(1000, 700)
+            .with_label(title);
+
+        let mut col = group::Flex::default_fill().column();
+        let mut list = browser::MultiBrowser::default();
+        list.set_column_char('\t');
+        list.set_column_widths(&[200, 96, 64, 96, 96]);
+        list.set_tooltip("Sent, method, status, total ms, TTFB ms, URI");
+        let mut timing_buf = text::TextBuffer::default();
+        let mut timing = text::TextDisplay::default();
+        timing.set_buffer(timing_buf.clone());
+        timing.set_text_font(enums::Font::Courier);
+        col.fixed(&timing, 120);
+        let mut row = group::Flex::default().row();
+        let mut open_btn = button::Button::default().with_label("Open");
+        row.fixed(&open_btn, 128);
+        let mut export_btn = button::Button::default().with_label("Export HAR...");
+        row.fixed(&export_btn, 160);
+        let mut mock_btn = button::Button::default().with_label("Mock response");
+        mock_btn.set_tooltip("Serve this response from the mock server");
+        row.fixed(&mock_btn, 160);
+        let mut diff_btn = button::Button::default().with_label("Diff");
+        diff_btn.set_tooltip("Compare two entries, select the second with Ctrl+click");
+        row.fixed(&diff_btn, 96);
+        frame::Frame::default();
+        row.end();
+        col.fixed(&row, 32);
+        col.end();
+
+        win.end();
+        win.make_resizable(true);
+        win.show();
+
+        for e in entries {
+            let timings = Timings::from(e);
+            list.add(&format!(
+                "{}\t{}\t{}\t{:.1}\t{:.1}\t{}",
+                e.sent_at.format("%Y-%m-%d %H:%M:%S"),
+                e.method,
+                e.status,
+                timings.total().as_secs_f64() * 1000.0,
+                e.wait_ms,
+                e.uri
+            ));
+        }
+
+        let entries = Rc::new(entries.to_vec());
+        let (s, _) = app::channel::<GlobalAppMsg>();
+
+        let open = {
+            let list = list.clone();
+            let entries = entries.clone();
+            move || {
+                let line = list.value();
+                if line < 1 {
+                    return;
+                }
+                if let Some(e) = entries.get(line as usize - 1) {
+                    s.send(GlobalAppMsg::Restore(OpenWindow::new_from(
+                        &e.method, &e.uri, &e.headers, &e.body, None,
+                    )));
+                }
+            }
+        };
+
+        let open_ptr = Rc::new(open);
+        let open_cl = open_ptr.clone();
+        let p_entries = entries.clone();
+        list.set_callback(move |l| {
+            if app::event_clicks() {
+                open_cl();
+            }
+            let waterfall = usize::try_from(l.value() - 1)
+                .ok()
+                .and_then(|i| p_entries.get(i))
+                .map(|e| Timings::from(e).waterfall());
+            timing_buf.set_text(&waterfall.unwrap_or_default());
+        });
+        open_btn.set_callback(move |_| open_ptr());
+
+        let p_sender = s;
+        let p_list = list.clone();
+        let p_entries = entries.clone();
+        mock_btn.set_callback(move |_| {
+            let line = p_list.value();
+            if line < 1 {
+                return;
+            }
+            if let Some(e) = p_entries.get(line as usize - 1) {
+                p_sender.send(GlobalAppMsg::PromoteToMock(e.clone()));
+            }
+        });
+
+        let p_sender = s;
+        let p_list = list.clone();
+        let p_entries = entries.clone();
+        diff_btn.set_callback(move |_| {
+            let mut selected: Vec<SentRequest> = (1..=p_list.size())
+                .filter(|line| p_list.selected(*line))
+                .filter_map(|line| p_entries.get(line as usize - 1).cloned())
+                .collect();
+            if selected.len() != 2 {
+                dialog::message_default("Select two entries to compare.");
+                return;
+            }
+            selected.sort_by_key(|e| e.sent_at);
+            p_sender.send(GlobalAppMsg::ShowDiff(selected));
+        });
+
+        export_btn.set_callback(move |_| {
+            let Some(path) = controls::pick_save_file("*.har", "legrillon.har") else {
+                return;
+            };
+            let written = har::export(&entries)
+                .map_err(|e| e.to_string())
+                .and_then(|json| std::fs::write(&path, json).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                dialog::alert_default(&format!("Could not export {}:\n{e}", path.display()));
+            }
+        });
+
+        let p_sender = s;
+        win.handle(move |_, e| {
+            if e == Event::Hide {
+                p_sender.send(GlobalAppMsg::CloseWindow(id));
+                return true;
+            }
+
+            false
+        });
+
+        Self { id, window: win }
+    }
+}
+
+impl HasId for HistoryWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for HistoryWindow {
+    fn close(&mut self) {
+        self.window.hide();
+    }
+}
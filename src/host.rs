@@ -0,0 +1,170 @@
+use fltk::{
+    app,
+    enums::{CallbackTrigger, Color, Event},
+    group::{Group, Tabs},
+    prelude::*,
+    window::{self, DoubleWindow},
+};
+
+use crate::{GlobalAppMsg, controls, db::WindowLayout};
+
+/// Part of a restored window that has to be on a screen, so it can still be dragged.
+const MIN_VISIBLE: i32 = 64;
+
+/// Where a request is shown, its own window or a tab of the main window.
+#[derive(Clone)]
+pub enum Host {
+    Window(DoubleWindow),
+    Tab { group: Group, tabs: Tabs },
+}
+
+/// Tab labels draw `@` as symbol markup.
+fn tab_label(label: &str) -> String {
+    label.replace('@', "@@")
+}
+
+/// Gives the window its saved size, shrunk to fit the screen. The saved position is only
+/// used when the title bar would be on a screen, e.g. not after a monitor was unplugged.
+fn place(win: &mut DoubleWindow, layout: &WindowLayout) {
+    let screens: Vec<(i32, i32, i32, i32)> = (0..app::screen_count())
+        .map(app::screen_work_area)
+        .collect();
+    let on_screen = screens.iter().copied().find(|&(sx, sy, sw, sh)| {
+        let overlap = (layout.x + layout.w).min(sx + sw) - layout.x.max(sx);
+        overlap >= MIN_VISIBLE && layout.y >= sy && layout.y <= sy + sh - MIN_VISIBLE
+    });
+    let (_, _, sw, sh) = on_screen
+        .or(screens.first().copied())
+        .unwrap_or((0, 0, layout.w, layout.h));
+    let (w, h) = (layout.w.min(sw), layout.h.min(sh));
+
+    match on_screen {
+        Some(_) => win.resize(layout.x, layout.y, w, h),
+        None => win.set_size(w, h),
+    }
+}
+
+impl Host {
+    /// Begins the host, widgets created until [`Host::show`] are placed in it. A window
+    /// takes the geometry of `layout` when it has a size.
+    pub fn new(tabs: Option<&Tabs>, label: &str, layout: Option<&WindowLayout>) -> Self {
+        match tabs {
+            Some(tabs) => {
+                tabs.begin();
+                let group = Group::default_fill().with_label(&tab_label(label));
+                Host::Tab {
+                    group,
+                    tabs: tabs.clone(),
+                }
+            }
+            None => {
+                let mut win = window::DoubleWindow::default()
+                    .with_size(1200, 800)
+                    .with_label(label);
+                if let Some(layout) = layout.filter(|l| l.w > 0 && l.h > 0) {
+                    place(&mut win, layout);
+                }
+                Host::Window(win)
+            }
+        }
+    }
+
+    /// Position and size of a window, tabs follow the main window and have none.
+    pub fn layout(&self) -> WindowLayout {
+        match self {
+            Host::Window(win) => WindowLayout {
+                x: win.x(),
+                y: win.y(),
+                w: win.w(),
+                h: win.h(),
+                ..WindowLayout::default()
+            },
+            Host::Tab { .. } => WindowLayout::default(),
+        }
+    }
+
+    pub fn show(&mut self) {
+        match self {
+            Host::Window(win) => {
+                win.end();
+                win.make_resizable(true);
+                win.show();
+            }
+            Host::Tab { group, tabs } => {
+                group.end();
+                tabs.end();
+                tabs.auto_layout();
+                let _ = tabs.set_value(group);
+                tabs.redraw();
+                // windows created later must not end up inside the main window
+                Group::set_current(None::<&Group>);
+            }
+        }
+    }
+
+    pub fn set_label(&mut self, label: &str) {
+        match self {
+            Host::Window(win) => win.set_label(label),
+            Host::Tab { group, tabs } => {
+                group.set_label(&tab_label(label));
+                tabs.redraw();
+            }
+        }
+    }
+
+    /// Colours a tab's label, window titles are drawn by the window manager and keep theirs.
+    pub fn set_label_color(&mut self, color: Option<Color>) {
+        if let Host::Tab { group, tabs } = self {
+            group.set_label_color(color.unwrap_or(Color::Foreground));
+            tabs.redraw();
+        }
+    }
+
+    /// Sends [`GlobalAppMsg::CloseWindow`] when the user closes the window or the tab, and
+    /// [`GlobalAppMsg::RunAction`] for shortcuts the focused widget did not use.
+    pub fn on_events(&mut self, id: usize, name: &str) {
+        let (s, _) = app::channel::<GlobalAppMsg>();
+        let shortcut = move |e: Event| {
+            if e == Event::KeyDown
+                && let Some(action) = controls::pressed_shortcut()
+            {
+                s.send(GlobalAppMsg::RunAction(Some(id), action));
+                return true;
+            }
+            false
+        };
+        match self {
+            Host::Window(win) => {
+                let name = name.to_string();
+                win.handle(move |_, e| {
+                    if e == Event::Hide {
+                        tracing::debug!("window {id} ({name}) closed");
+                        s.send(GlobalAppMsg::CloseWindow(id));
+                        return true;
+                    }
+
+                    shortcut(e)
+                });
+            }
+            Host::Tab { group, .. } => {
+                group.set_trigger(CallbackTrigger::Closed);
+                group.set_callback(move |_| s.send(GlobalAppMsg::CloseWindow(id)));
+                group.handle(move |_, e| shortcut(e));
+            }
+        }
+    }
+
+    pub fn close(&mut self) {
+        match self {
+            Host::Window(win) => win.hide(),
+            Host::Tab { group, tabs } => {
+                tabs.remove(group);
+                app::delete_widget(group.clone());
+                if let Some(first) = tabs.child(0).and_then(|w| w.as_group()) {
+                    let _ = tabs.set_value(&first);
+                }
+                tabs.redraw();
+            }
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use std::path::Path;
+
+use crate::{
+    db::{Collection, CollectionRequest},
+    secrets,
+};
+
+/// Requests parsed from a `.http` / `.rest` file as used by the VS Code REST Client
+/// and the JetBrains HTTP Client.
+#[derive(Debug)]
+pub struct HttpFile {
+    pub variables: Vec<(String, String)>,
+    pub requests: Vec<CollectionRequest>,
+    pub untranslated: Vec<String>,
+}
+
+const METHODS: [&str; 9] = [
+    "GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "TRACE", "CONNECT",
+];
+
+enum Section {
+    Preamble,
+    Headers,
+    Body,
+    ResponseHandler,
+}
+
+/// `base_dir` resolves `< ./file` body references to check them. They stay in the body as
+/// written, so saving the collection writes them back instead of the file content.
+pub fn parse(text: &str, base_dir: &Path) -> HttpFile {
+    let mut file = HttpFile {
+        variables: Vec::new(),
+        requests: Vec::new(),
+        untranslated: Vec::new(),
+    };
+
+    let mut title = String::new();
+    let mut block = Vec::new();
+    for line in text.lines() {
+        if let Some(next_title) = line.strip_prefix("###") {
+            parse_block(&title, &block, base_dir, &mut file);
+            title = next_title.trim().to_string();
+            block.clear();
+        } else {
+            block.push(line);
+        }
+    }
+    parse_block(&title, &block, base_dir, &mut file);
+
+    file
+}
+
+fn parse_block(title: &str, lines: &[&str], base_dir: &Path, file: &mut HttpFile) {
+    let mut section = Section::Preamble;
+    let mut name = title.to_string();
+    let mut req: Option<CollectionRequest> = None;
+    let mut body: Vec<String> = Vec::new();
+
+    for line in lines {
+        let trimmed = line.trim();
+        match section {
+            Section::Preamble => {
+                if trimmed.is_empty() {
+                    continue;
+                }
+                if let Some(comment) = comment_text(trimmed) {
+                    if let Some(n) = comment.strip_prefix("@name") {
+                        name = n.trim().to_string();
+                    }
+                    continue;
+                }
+                if let Some((var, value)) =
+                    trimmed.strip_prefix('@').and_then(|v| v.split_once('='))
+                {
+                    file.variables
+                        .push((var.trim().to_string(), value.trim().to_string()));
+                    continue;
+                }
+
+                let (method, uri) = parse_request_line(trimmed);
+                req = Some(CollectionRequest {
+                    id: 0,
+                    collection_id: 0,
+                    folder: String::new(),
+                    name: String::new(),
+                    method,
+                    uri,
+                    headers: sqlx::types::Json(Vec::new()),
+                    body: String::new(),
+                });
+                section = Section::Headers;
+            }
+            Section::Headers => {
+                let Some(req) = req.as_mut() else {
+                    break;
+                };
+                if trimmed.is_empty() {
+                    section = Section::Body;
+                } else if (trimmed.starts_with('?') || trimmed.starts_with('&'))
+                    && req.headers.0.is_empty()
+                {
+                    // multi line query string
+                    req.uri.push_str(trimmed);
+                } else if comment_text(trimmed).is_some() {
+                    continue;
+                } else if let Some((n, v)) = trimmed.split_once(':') {
+                    req.headers
+                        .0
+                        .push((n.trim().to_string(), v.trim().to_string()));
+                }
+            }
+            Section::Body => {
+                if trimmed.starts_with("> {%") {
+                    file.untranslated.push(format!(
+                        "response handler script on '{}'",
+                        display_name(&name, &req)
+                    ));
+                    if !trimmed.ends_with("%}") {
+                        section = Section::ResponseHandler;
+                    }
+                } else if trimmed.starts_with('>') || trimmed.starts_with("<>") {
+                    file.untranslated
+                        .push(format!("'{trimmed}' on '{}'", display_name(&name, &req)));
+                } else if let Some(path) = trimmed
+                    .strip_prefix("<@ ")
+                    .or_else(|| trimmed.strip_prefix("< "))
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                {
+                    let problem = match base_dir.join(path).metadata() {
+                        Ok(_) => "is sent as the reference, not the file".to_string(),
+                        Err(e) => e.to_string(),
+                    };
+                    file.untranslated.push(format!(
+                        "body file {path} of '{}': {problem}",
+                        display_name(&name, &req)
+                    ));
+                    body.push(line.to_string());
+                } else {
+                    body.push(line.to_string());
+                }
+            }
+            Section::ResponseHandler => {
+                if trimmed.ends_with("%}") {
+                    section = Section::Body;
+                }
+            }
+        }
+    }
+
+    if let Some(mut req) = req {
+        while body.last().is_some_and(|l| l.trim().is_empty()) {
+            body.pop();
+        }
+        req.body = body.join("\n");
+        // `write` puts the folder into the `###` title in front of the `@name`
+        if let Some(folder) = title
+            .strip_suffix(name.as_str())
+            .and_then(|f| f.strip_suffix('/'))
+            .filter(|f| !f.is_empty() && name != title)
+        {
+            req.folder = folder.to_string();
+        }
+        req.name = if name.is_empty() {
+            format!("{} {}", req.method, req.uri)
+        } else {
+            name
+        };
+        file.requests.push(req);
+    }
+}
+
+fn display_name(name: &str, req: &Option<CollectionRequest>) -> String {
+    match req {
+        Some(r) if name.is_empty() => format!("{} {}", r.method, r.uri),
+        _ => name.to_string(),
+    }
+}
+
+fn comment_text(line: &str) -> Option<&str> {
+    line.strip_prefix('#')
+        .or_else(|| line.strip_prefix("//"))
+        .map(str::trim)
+}
+
+/// `POST https://host/path HTTP/1.1`, `GET /path` or just an URL (implies GET).
+fn parse_request_line(line: &str) -> (String, String) {
+    let mut parts = line.split_whitespace();
+    let first = parts.next().unwrap_or_default();
+    let (method, uri) = if METHODS.contains(&first) || is_custom_method(first, line) {
+        (
+            first.to_string(),
+            parts.next().unwrap_or_default().to_string(),
+        )
+    } else {
+        ("GET".to_string(), first.to_string())
+    };
+
+    (method, uri)
+}
+
+/// Custom verbs such as `PROPFIND` are upper case words followed by an URL.
+fn is_custom_method(word: &str, line: &str) -> bool {
+    !word.is_empty()
+        && word.chars().all(|c| c.is_ascii_uppercase())
+        && line.split_whitespace().count() > 1
+}
+
+/// Writes the collection as an `.http` file, secrets are redacted so it can be shared.
+pub fn write(collection: &Collection, requests: &[CollectionRequest]) -> String {
+    let mut out = String::new();
+
+    for (name, value) in &secrets::redact_variables(&collection.variables.0, &collection.secrets.0)
+    {
+        out.push_str(&format!("@{name} = {value}\n"));
+    }
+    if !collection.variables.0.is_empty() {
+        out.push('\n');
+    }
+
+    for req in requests {
+        let title = if req.folder.is_empty() {
+            req.name.clone()
+        } else {
+            format!("{}/{}", req.folder, req.name)
+        };
+        // the `@name` tells where the folder ends in the title, e.g. `### users/Get user`
+        out.push_str(&format!("### {title}\n"));
+        out.push_str(&format!("# @name {}\n", req.name));
+        out.push_str(&format!("{} {}\n", req.method, req.uri));
+        for (n, v) in &secrets::redact(&req.headers.0) {
+            out.push_str(&format!("{n}: {v}\n"));
+        }
+        if !req.body.is_empty() {
+            out.push('\n');
+            out.push_str(&req.body);
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+@host = http://localhost
+@token = s3cr3t
+
+### users/Get user #1
+# @name Get user #1
+GET {{host}}/users/1
+    ?fields=name
+    &sort=asc
+Accept: application/json
+
+> {%
+    client.global.set(\"id\", response.body.id);
+%}
+
+### upload
+POST {{host}}/upload HTTP/1.1
+Content-Type: application/json
+
+< ./upload.json
+
+### PROPFIND {{host}}/dav
+PROPFIND {{host}}/dav
+Depth: 1
+";
+
+    fn summary(requests: &[CollectionRequest]) -> Vec<(&str, &str, &str, &str, &str)> {
+        requests
+            .iter()
+            .map(|r| {
+                (
+                    r.folder.as_str(),
+                    r.name.as_str(),
+                    r.method.as_str(),
+                    r.uri.as_str(),
+                    r.body.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_requests() {
+        let file = parse(FILE, Path::new("/nonexistent"));
+        assert_eq!(
+            summary(&file.requests),
+            [
+                (
+                    "users",
+                    "Get user #1",
+                    "GET",
+                    "{{host}}/users/1?fields=name&sort=asc",
+                    ""
+                ),
+                ("", "upload", "POST", "{{host}}/upload", "< ./upload.json"),
+                ("", "PROPFIND {{host}}/dav", "PROPFIND", "{{host}}/dav", ""),
+            ]
+        );
+        assert_eq!(
+            file.requests[0].headers.0,
+            [("Accept".to_string(), "application/json".to_string())]
+        );
+        assert_eq!(file.variables.len(), 2);
+        assert_eq!(file.untranslated.len(), 2, "{:?}", file.untranslated);
+        assert!(file.untranslated[0].starts_with("response handler script"));
+        assert!(file.untranslated[1].starts_with("body file ./upload.json"));
+    }
+
+    #[test]
+    fn write_round_trips() {
+        let file = parse(FILE, Path::new("/nonexistent"));
+        let collection = Collection {
+            id: 0,
+            name: "api".to_string(),
+            variables: sqlx::types::Json(file.variables.clone()),
+            secrets: sqlx::types::Json(Default::default()),
+        };
+
+        let written = write(&collection, &file.requests);
+        assert!(written.contains("\n< ./upload.json\n"), "{written}");
+        assert!(written.contains(&format!("@token = {}\n", secrets::REDACTED)));
+
+        let again = parse(&written, Path::new("/nonexistent"));
+        assert_eq!(summary(&again.requests), summary(&file.requests));
+        let headers = |requests: &[CollectionRequest]| {
+            requests
+                .iter()
+                .map(|r| r.headers.0.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(headers(&again.requests), headers(&file.requests));
+        assert_eq!(write(&collection, &again.requests), written);
+    }
+}
@@ -0,0 +1,440 @@
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+/// One step of a JSONPath expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Wildcard,
+    /// The node itself and everything below it, `..` in JSONPath.
+    Descendants,
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    path: Vec<Segment>,
+    /// Without a comparison the filter only checks that the path exists.
+    test: Option<(Op, Value)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.src[self.pos..]
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        self.pos = self.src.len() - self.rest().trim_start().len();
+    }
+
+    fn error<T>(&self, what: &str) -> Result<T, String> {
+        Err(format!("{what} at position {} of {}", self.pos, self.src))
+    }
+
+    fn name(&mut self) -> String {
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '$'))
+            .unwrap_or(self.rest().len());
+        let name = self.rest()[..len].to_string();
+        self.pos += len;
+        name
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        let quote = if self.eat("'") {
+            '\''
+        } else if self.eat("\"") {
+            '"'
+        } else {
+            return self.error("expected a quoted name");
+        };
+        let mut text = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some((_, escaped)) = chars.next() {
+                        text.push(escaped);
+                    }
+                }
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Ok(text);
+                }
+                c => text.push(c),
+            }
+        }
+        self.error("unterminated string")
+    }
+
+    fn int(&mut self) -> Option<i64> {
+        let len = self
+            .rest()
+            .char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+            .map_or(self.rest().len(), |(i, _)| i);
+        let n = self.rest()[..len].parse().ok()?;
+        self.pos += len;
+        Some(n)
+    }
+
+    fn segments(&mut self, inside_filter: bool) -> Result<Vec<Segment>, String> {
+        let mut segments = Vec::new();
+        loop {
+            if self.eat("..") {
+                segments.push(Segment::Descendants);
+                if self.rest().starts_with('[') {
+                    continue;
+                }
+                segments.push(self.dot_member()?);
+            } else if self.eat(".") {
+                // a lone `.` is the jq identity
+                if self.rest().is_empty() || self.rest().starts_with('[') {
+                    continue;
+                }
+                segments.push(self.dot_member()?);
+            } else if self.eat("[") {
+                segments.push(self.bracket()?);
+            } else if inside_filter || self.rest().is_empty() {
+                return Ok(segments);
+            } else {
+                return self.error("unexpected input");
+            }
+        }
+    }
+
+    fn dot_member(&mut self) -> Result<Segment, String> {
+        if self.eat("*") {
+            return Ok(Segment::Wildcard);
+        }
+        if self.rest().starts_with(['\'', '"']) {
+            return self.quoted().map(Segment::Child);
+        }
+        let name = self.name();
+        if name.is_empty() {
+            return self.error("expected a member name");
+        }
+        Ok(Segment::Child(name))
+    }
+
+    fn bracket(&mut self) -> Result<Segment, String> {
+        self.skip_ws();
+        let segment = if self.eat("]") {
+            // jq `.[]`
+            return Ok(Segment::Wildcard);
+        } else if self.eat("*") {
+            Segment::Wildcard
+        } else if self.rest().starts_with(['\'', '"']) {
+            Segment::Child(self.quoted()?)
+        } else if self.eat("?") {
+            self.skip_ws();
+            let parens = self.eat("(");
+            let filter = self.filter()?;
+            self.skip_ws();
+            if parens && !self.eat(")") {
+                return self.error("expected )");
+            }
+            Segment::Filter(filter)
+        } else {
+            let start = self.int();
+            if self.eat(":") {
+                Segment::Slice(start, self.int())
+            } else if let Some(i) = start {
+                Segment::Index(i)
+            } else {
+                return self.error("expected an index, a name, * or a filter");
+            }
+        };
+        self.skip_ws();
+        if !self.eat("]") {
+            return self.error("expected ]");
+        }
+        Ok(segment)
+    }
+
+    fn filter(&mut self) -> Result<Filter, String> {
+        if !self.eat("@") {
+            return self.error("a filter starts with @");
+        }
+        let path = self.segments(true)?;
+        self.skip_ws();
+        let op = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ]
+        .into_iter()
+        .find(|(token, _)| self.eat(token));
+        let Some((_, op)) = op else {
+            return Ok(Filter { path, test: None });
+        };
+        self.skip_ws();
+        let value = if self.rest().starts_with(['\'', '"']) {
+            Value::String(self.quoted()?)
+        } else {
+            let len = self
+                .rest()
+                .find([')', ']', ' '])
+                .unwrap_or(self.rest().len());
+            let literal = &self.rest()[..len];
+            let value = serde_json::from_str(literal)
+                .or_else(|_| self.error(&format!("invalid literal {literal}")))?;
+            self.pos += len;
+            value
+        };
+        Ok(Filter {
+            path,
+            test: Some((op, value)),
+        })
+    }
+}
+
+fn parse(expr: &str) -> Result<Vec<Segment>, String> {
+    let expr = expr.trim();
+    let mut parser = Parser { src: expr, pos: 0 };
+    parser.eat("$");
+    parser.segments(false)
+}
+
+fn children(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(map) => map.values().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn descendants<'a>(value: &'a Value, out: &mut Vec<&'a Value>) {
+    out.push(value);
+    for child in children(value) {
+        descendants(child, out);
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ if a == b => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        let found = evaluate(&self.path, value);
+        let Some((op, expected)) = &self.test else {
+            return !found.is_empty();
+        };
+        found.into_iter().any(|v| {
+            let ord = compare(v, expected);
+            match op {
+                Op::Eq => ord == Some(Ordering::Equal),
+                Op::Ne => ord != Some(Ordering::Equal),
+                Op::Lt => ord == Some(Ordering::Less),
+                Op::Le => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+                Op::Gt => ord == Some(Ordering::Greater),
+                Op::Ge => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+            }
+        })
+    }
+}
+
+fn evaluate<'a>(segments: &[Segment], root: &'a Value) -> Vec<&'a Value> {
+    let mut nodes = vec![root];
+    for segment in segments {
+        let mut next = Vec::new();
+        for node in nodes {
+            match segment {
+                Segment::Child(name) => next.extend(node.get(name)),
+                Segment::Index(i) => {
+                    if let Value::Array(items) = node {
+                        let i = if *i < 0 { items.len() as i64 + i } else { *i };
+                        next.extend(usize::try_from(i).ok().and_then(|i| items.get(i)));
+                    }
+                }
+                Segment::Slice(start, end) => {
+                    if let Value::Array(items) = node {
+                        let len = items.len() as i64;
+                        let bound =
+                            |b: i64| (if b < 0 { len + b } else { b }).clamp(0, len) as usize;
+                        let (start, end) = (bound(start.unwrap_or(0)), bound(end.unwrap_or(len)));
+                        if start < end {
+                            next.extend(&items[start..end]);
+                        }
+                    }
+                }
+                Segment::Wildcard => next.extend(children(node)),
+                Segment::Descendants => descendants(node, &mut next),
+                Segment::Filter(filter) => {
+                    next.extend(children(node).into_iter().filter(|c| filter.matches(c)))
+                }
+            }
+        }
+        nodes = next;
+    }
+    nodes
+}
+
+/// Applies a JSONPath (`$.items[?(@.price < 10)].name`) or jq style path
+/// (`.items[].name`) to a JSON body and returns the matches pretty printed. A path
+/// that can only select one value shows that value, otherwise an array of the matches.
+pub fn filter(expr: &str, body: &str) -> Result<String, String> {
+    let segments = parse(expr)?;
+    let json: Value =
+        serde_json::from_str(body).map_err(|e| format!("response is not JSON: {e}"))?;
+    let found = evaluate(&segments, &json);
+    let single = segments
+        .iter()
+        .all(|s| matches!(s, Segment::Child(_) | Segment::Index(_)));
+
+    let result = match found.as_slice() {
+        [value] if single => (*value).clone(),
+        [] if single => return Err(format!("{expr} matches nothing")),
+        _ => Value::Array(found.into_iter().cloned().collect()),
+    };
+    serde_json::to_string_pretty(&result).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = r#"{
+        "store": {
+            "name": "grillon",
+            "items": [
+                {"name": "a", "price": 5, "tags": ["x"]},
+                {"name": "b", "price": 12},
+                {"name": "c", "price": 8.5, "tags": []}
+            ],
+            "odd key": true
+        }
+    }"#;
+
+    fn query(expr: &str) -> Value {
+        serde_json::from_str(&filter(expr, BODY).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parses_jsonpath_and_jq_paths() {
+        assert_eq!(
+            parse("$.store.items[0]"),
+            Ok(vec![
+                Segment::Child("store".to_string()),
+                Segment::Child("items".to_string()),
+                Segment::Index(0),
+            ])
+        );
+        assert_eq!(parse(".store"), parse("$['store']"));
+        assert_eq!(parse(".items[]"), parse("$.items[*]"));
+        assert_eq!(parse("."), Ok(vec![]));
+        assert_eq!(
+            parse("$[1:-1]"),
+            Ok(vec![Segment::Slice(Some(1), Some(-1))])
+        );
+        assert_eq!(
+            parse("$..[?(@.price >= 8)]"),
+            Ok(vec![
+                Segment::Descendants,
+                Segment::Filter(Filter {
+                    path: vec![Segment::Child("price".to_string())],
+                    test: Some((Op::Ge, Value::from(8))),
+                }),
+            ])
+        );
+    }
+
+    #[test]
+    fn evaluates() {
+        assert_eq!(query("$.store.name"), "grillon");
+        assert_eq!(query(".store.items[-1].name"), "c");
+        assert_eq!(query("$.store['odd key']"), true);
+        assert_eq!(
+            query("$.store.items[*].name"),
+            serde_json::json!(["a", "b", "c"])
+        );
+        assert_eq!(
+            query("$.store.items[1:].price"),
+            serde_json::json!([12, 8.5])
+        );
+        assert_eq!(
+            query("$.store.items[?(@.price < 10)].name"),
+            serde_json::json!(["a", "c"])
+        );
+        assert_eq!(
+            query("$.store.items[?(@.name == 'b')].price"),
+            serde_json::json!([12])
+        );
+        assert_eq!(
+            query("$.store.items[?@.tags].name"),
+            serde_json::json!(["a", "c"])
+        );
+        assert_eq!(query("$..price"), serde_json::json!([5, 12, 8.5]));
+        assert_eq!(query("$.store.items[5:9]"), serde_json::json!([]));
+        assert!(filter("$.store.missing", BODY).is_err());
+        assert!(filter("$.a", "not json").is_err());
+    }
+
+    #[test]
+    fn malformed_expressions_are_errors() {
+        for expr in [
+            "$..",
+            "$[",
+            "$[1",
+            "$[abc]",
+            "$['open",
+            "$[\"open]",
+            "$[?(@.a == )]",
+            "$[?(@.a == nope)]",
+            "$[?(@.a == 1]",
+            "$[?(.a)]",
+            "$[?(@.)]",
+            "$.a b",
+            "$[-]",
+            "$[99999999999999999999]",
+            "$.a[?(@.b < 1)",
+            "$]",
+            "$[?(@[?(@.a",
+        ] {
+            assert!(filter(expr, BODY).is_err(), "{expr}");
+        }
+        // extreme indexes are out of range, not an overflow
+        assert!(filter("$[-9223372036854775808]", "[1]").is_err());
+        assert_eq!(
+            filter("$[-9223372036854775808:9223372036854775807]", "[1]"),
+            Ok("[\n  1\n]".to_string())
+        );
+    }
+}
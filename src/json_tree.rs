@@ -0,0 +1,255 @@
+use std::sync::{Arc, Mutex};
+
+use fltk::{
+    app, button,
+    enums::{CallbackTrigger, Font},
+    frame,
+    group::Flex,
+    prelude::*,
+    tree::{Tree, TreeItem, TreeReason, TreeSelect},
+};
+use serde_json::Value;
+
+/// Child of a collapsed node until it is opened, so the node shows an open toggle.
+const PLACEHOLDER: &str = "...";
+/// Containers with more children are split into ranges of this size.
+const CHUNK: usize = 500;
+const PREVIEW_CHARS: usize = 120;
+
+#[derive(Debug, Clone)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// What a tree item stands for, kept as the item's user data.
+#[derive(Debug, Clone)]
+struct Node {
+    path: Vec<Step>,
+    /// A slice of the children of a large container.
+    range: Option<(usize, usize)>,
+}
+
+/// Collapsible view of a JSON response, children are only created when a node is opened.
+#[derive(Clone)]
+pub struct JsonTreeCtrl {
+    tree: Tree,
+    json: Arc<Mutex<Option<Value>>>,
+}
+
+fn lookup<'a>(json: &'a Value, path: &[Step]) -> Option<&'a Value> {
+    path.iter().try_fold(json, |v, step| match step {
+        Step::Key(k) => v.get(k),
+        Step::Index(i) => v.get(i),
+    })
+}
+
+/// JSONPath of a node, bracket notation for keys that are not plain identifiers.
+fn json_path(path: &[Step]) -> String {
+    let mut text = "$".to_string();
+    for step in path {
+        match step {
+            Step::Key(k)
+                if !k.is_empty()
+                    && !k.starts_with(|c: char| c.is_ascii_digit())
+                    && k.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                text.push('.');
+                text.push_str(k);
+            }
+            Step::Key(k) => text.push_str(&format!(
+                "['{}']",
+                k.replace('\\', "\\\\").replace('\'', "\\'")
+            )),
+            Step::Index(i) => text.push_str(&format!("[{i}]")),
+        }
+    }
+    text
+}
+
+fn size(value: &Value) -> usize {
+    match value {
+        Value::Array(items) => items.len(),
+        Value::Object(map) => map.len(),
+        _ => 0,
+    }
+}
+
+fn preview(value: &Value) -> String {
+    match value {
+        Value::Array(items) => format!("[{}]", items.len()),
+        Value::Object(map) => format!("{{{}}}", map.len()),
+        _ => {
+            let text = value.to_string();
+            match text.char_indices().nth(PREVIEW_CHARS) {
+                Some((i, _)) => format!("{}...", &text[..i]),
+                None => text,
+            }
+        }
+    }
+}
+
+fn is_unloaded(item: &TreeItem) -> bool {
+    item.children() == 1
+        && item
+            .child(0)
+            .and_then(|c| c.label())
+            .is_some_and(|l| l == PLACEHOLDER)
+}
+
+fn node_of(item: &TreeItem) -> Option<Node> {
+    // SAFETY: every item of the tree gets a `Node` as user data when it is inserted
+    unsafe { item.user_data::<Node>() }
+}
+
+fn insert(tree: &mut Tree, parent: &TreeItem, label: &str, node: Node, container: bool) {
+    let Some(mut item) = tree.insert(parent, label, parent.children()) else {
+        return;
+    };
+    item.set_user_data(node);
+    if container {
+        tree.insert(&item, PLACEHOLDER, 0);
+        item.close();
+    }
+}
+
+/// Replaces the placeholder of `item` with its children.
+fn expand(tree: &mut Tree, item: &TreeItem, json: &Value) {
+    let Some(node) = node_of(item) else {
+        return;
+    };
+    let Some(value) = lookup(json, &node.path) else {
+        return;
+    };
+    tree.clear_children(item);
+
+    let (start, end) = node.range.unwrap_or((0, size(value)));
+    if node.range.is_none() && end > CHUNK {
+        for chunk in (0..end).step_by(CHUNK) {
+            let chunk_end = (chunk + CHUNK).min(end);
+            let range = Node {
+                path: node.path.clone(),
+                range: Some((chunk, chunk_end)),
+            };
+            insert(
+                tree,
+                item,
+                &format!("[{chunk} .. {}]", chunk_end - 1),
+                range,
+                true,
+            );
+        }
+        return;
+    }
+
+    let children: Vec<(Step, String, &Value)> = match value {
+        Value::Array(items) => items[start..end]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (Step::Index(start + i), format!("[{}]", start + i), v))
+            .collect(),
+        Value::Object(map) => map
+            .iter()
+            .skip(start)
+            .take(end - start)
+            .map(|(k, v)| (Step::Key(k.clone()), k.clone(), v))
+            .collect(),
+        _ => Vec::new(),
+    };
+    for (step, name, child) in children {
+        let mut path = node.path.clone();
+        path.push(step);
+        let container = size(child) > 0;
+        let label = format!("{name}: {}", preview(child));
+        insert(tree, item, &label, Node { path, range: None }, container);
+    }
+}
+
+impl JsonTreeCtrl {
+    /// Creates the "Tree" tab in the current `Tabs` group.
+    pub fn new() -> Self {
+        let mut grp = Flex::default_fill().with_label("Tree\t\t").column();
+        let mut tree = Tree::default();
+        tree.set_select_mode(TreeSelect::Single);
+        tree.set_item_label_font(Font::Courier);
+        tree.set_trigger(CallbackTrigger::Changed);
+        tree.set_root_label("(no response)");
+        let mut row = Flex::default().row();
+        let mut copy_value_btn = button::Button::default().with_label("Copy value");
+        row.fixed(&copy_value_btn, 128);
+        let mut copy_path_btn = button::Button::default().with_label("Copy path");
+        row.fixed(&copy_path_btn, 128);
+        frame::Frame::default();
+        row.end();
+        grp.fixed(&row, 32);
+        grp.end();
+
+        let json: Arc<Mutex<Option<Value>>> = Arc::new(Mutex::new(None));
+
+        let p_json = json.clone();
+        tree.set_callback(move |t| {
+            if !matches!(t.callback_reason(), TreeReason::Opened) {
+                return;
+            }
+            if let Some(item) = t.callback_item()
+                && is_unloaded(&item)
+                && let Some(json) = p_json.lock().unwrap().as_ref()
+            {
+                expand(t, &item, json);
+                t.redraw();
+            }
+        });
+
+        let (p_tree, p_json) = (tree.clone(), json.clone());
+        copy_value_btn.set_callback(move |_| {
+            let Some(node) = p_tree.first_selected_item().and_then(|i| node_of(&i)) else {
+                return;
+            };
+            if let Some(json) = p_json.lock().unwrap().as_ref()
+                && let Some(value) = lookup(json, &node.path)
+            {
+                let text = match value {
+                    Value::String(s) => s.clone(),
+                    v => serde_json::to_string_pretty(v).unwrap_or_default(),
+                };
+                app::copy(&text);
+            }
+        });
+
+        let p_tree = tree.clone();
+        copy_path_btn.set_callback(move |_| {
+            if let Some(node) = p_tree.first_selected_item().and_then(|i| node_of(&i)) {
+                app::copy(&json_path(&node.path));
+            }
+        });
+
+        Self { tree, json }
+    }
+
+    /// Shows `body` when it is JSON, the first level is expanded.
+    pub fn set_body(&self, body: &str) {
+        let mut tree = self.tree.clone();
+        let Some(mut root) = tree.root() else {
+            return;
+        };
+        tree.clear_children(&root);
+
+        match serde_json::from_str::<Value>(body) {
+            Ok(json) => {
+                tree.set_root_label(&format!("$ {}", preview(&json)));
+                root.set_user_data(Node {
+                    path: Vec::new(),
+                    range: None,
+                });
+                expand(&mut tree, &root, &json);
+                root.open();
+                *self.json.lock().unwrap() = Some(json);
+            }
+            Err(_) => {
+                tree.set_root_label("(not JSON)");
+                *self.json.lock().unwrap() = None;
+            }
+        }
+        tree.redraw();
+    }
+}
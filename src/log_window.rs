@@ -0,0 +1,158 @@
+use std::{cell::Cell, rc::Rc};
+
+use fltk::{
+    app, button, dialog,
+    enums::{self, CallbackTrigger, Event},
+    frame, group, input,
+    menu::Choice,
+    prelude::*,
+    text::{self, TextBuffer},
+    window::{self, DoubleWindow},
+};
+
+use crate::{AppWindow, GlobalAppMsg, HasId, logging, next_window_id};
+
+/// Seconds between checks for new lines.
+const REFRESH: f64 = 1.0;
+
+/// The latest log lines, narrowed to those containing a search text, with the verbosity.
+pub struct LogWindow {
+    id: usize,
+    window: DoubleWindow,
+}
+
+/// Verbosity choices, a filter set in the settings that is not a plain level comes last.
+fn filters() -> Vec<String> {
+    let mut filters: Vec<String> = logging::LEVELS.iter().map(|l| l.to_string()).collect();
+    let current = logging::filter();
+    if !filters.contains(&current) {
+        filters.push(current);
+    }
+    filters
+}
+
+impl LogWindow {
+    pub fn new() -> Self {
+        let id = next_window_id();
+
+        let mut win = window::DoubleWindow::default()
+            .with_size(1200, 700)
+            .with_label("Le Grillon - Logs");
+
+        let mut col = group::Flex::default_fill().column();
+        let mut row = group::Flex::default().row();
+        let level_label = frame::Frame::default().with_label("Log:");
+        row.fixed(&level_label, 48);
+        let mut level_choice = Choice::default();
+        row.fixed(&level_choice, 240);
+        let mut search = input::Input::default();
+        search.set_tooltip("Only lines containing this text");
+        let mut clear_btn = button::Button::default().with_label("Clear");
+        row.fixed(&clear_btn, 96);
+        let files = frame::Frame::default().with_label(&format!(
+            "Files in {}/, kept {} days",
+            logging::LOG_DIR,
+            logging::LOG_FILES_KEPT
+        ));
+        row.fixed(&files, 240);
+        row.end();
+        col.fixed(&row, 32);
+
+        let buf = TextBuffer::default();
+        let mut display = text::TextDisplay::default();
+        display.set_buffer(buf.clone());
+        display.set_text_font(enums::Font::Courier);
+        col.end();
+
+        win.end();
+        win.make_resizable(true);
+        win.show();
+
+        let filters = filters();
+        for f in &filters {
+            level_choice.add_choice(&f.replace('/', "\\/").replace('|', "\\|"));
+        }
+        let current = logging::filter();
+        level_choice.set_value(filters.iter().position(|f| *f == current).unwrap_or(0) as i32);
+        level_choice.set_callback(move |c| {
+            let Some(filter) = usize::try_from(c.value()).ok().and_then(|i| filters.get(i)) else {
+                return;
+            };
+            if let Err(e) = logging::set_filter(filter) {
+                dialog::alert_default(&format!("Could not change the log level:\n{e}"));
+            }
+        });
+
+        // lines logged when the display was last filled
+        let shown = Rc::new(Cell::new(usize::MAX));
+        let fill = {
+            let shown = shown.clone();
+            let search = search.clone();
+            let (buf, display) = (buf.clone(), display.clone());
+            move || {
+                let (mut buf, mut display) = (buf.clone(), display.clone());
+                shown.set(logging::written());
+                let needle = search.value().to_lowercase();
+                let mut text = String::new();
+                for line in logging::recent() {
+                    let line = line.to_string();
+                    if line.to_lowercase().contains(&needle) {
+                        text.push_str(&line);
+                        text.push('\n');
+                    }
+                }
+                buf.set_text(&text);
+                let lines = display.count_lines(0, buf.length(), true);
+                display.scroll(lines, 0);
+            }
+        };
+        let fill = Rc::new(fill);
+        fill();
+
+        search.set_trigger(CallbackTrigger::Changed);
+        let p_fill = fill.clone();
+        search.set_callback(move |_| p_fill());
+
+        let p_fill = fill.clone();
+        clear_btn.set_callback(move |_| {
+            logging::clear();
+            p_fill();
+        });
+
+        let p_win = win.clone();
+        app::add_timeout3(REFRESH, move |handle| {
+            if !p_win.shown() {
+                return;
+            }
+            if shown.get() != logging::written() {
+                fill();
+            }
+            app::repeat_timeout3(REFRESH, handle);
+        });
+
+        let (s, _) = app::channel::<GlobalAppMsg>();
+        win.handle(move |_, e| {
+            if e == Event::Hide {
+                tracing::debug!("window {id} (LogWindow) closed");
+                s.send(GlobalAppMsg::CloseWindow(id));
+                return true;
+            }
+
+            false
+        });
+
+        Self { id, window: win }
+    }
+}
+
+impl HasId for LogWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for LogWindow {
+    fn close(&mut self) {
+        self.window.hide();
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Write},
+    str::FromStr,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    Registry,
+    filter::Targets,
+    layer::{Context, Layer, SubscriberExt},
+    reload,
+    util::SubscriberInitExt,
+};
+
+/// Where the log files go, next to `sqlite.db`.
+pub const LOG_DIR: &str = "logs";
+/// Daily log files kept, older ones are deleted.
+pub const LOG_FILES_KEPT: usize = 7;
+/// Plain filters offered by the log viewer, most quiet first.
+pub const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+/// Used until the settings are read and when they leave the filter empty.
+const DEFAULT_FILTER: &str = "info";
+/// Lines kept in memory for the log viewer.
+const RECENT_LINES: usize = 2000;
+
+/// One logged event, as kept for the log viewer.
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub time: chrono::DateTime<chrono::Local>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl fmt::Display for LogLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:>5} {}: {}",
+            self.time.format("%H:%M:%S%.3f"),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+static RECENT: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+/// Bumped by every line logged and by clearing, tells the log viewer when to refresh.
+static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+static FILTER: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+static FILTER_TEXT: Mutex<String> = Mutex::new(String::new());
+
+/// Logs to the terminal, to a daily file in [`LOG_DIR`] and to the log viewer, at `info`
+/// until [`set_filter`] applies the settings. The guard flushes the file when dropped, it
+/// has to live until the application quits.
+pub fn init() -> Option<WorkerGuard> {
+    let (filter, handle) = reload::Layer::new(parse_filter(DEFAULT_FILTER).unwrap_or_default());
+    let _ = FILTER.set(handle);
+    *FILTER_TEXT.lock().unwrap() = DEFAULT_FILTER.to_string();
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("legrillon")
+        .filename_suffix("log")
+        .max_log_files(LOG_FILES_KEPT)
+        .build(LOG_DIR);
+    let (file, guard, file_error) = match appender {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer);
+            (Some(layer), Some(guard), None)
+        }
+        Err(e) => (None, None, Some(e)),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(file)
+        .with(Recent)
+        .init();
+
+    if let Some(e) = file_error {
+        tracing::warn!("no log file in {LOG_DIR}: {e}");
+    }
+    guard
+}
+
+fn parse_filter(filter: &str) -> Result<Targets, String> {
+    Targets::from_str(filter).map_err(|e| format!("{filter}: {e}"))
+}
+
+/// Sets what is logged, a level, e.g. `debug`, optionally followed by levels for some
+/// modules, e.g. `info,legrillon::db=debug`. Empty means `info`.
+pub fn set_filter(filter: &str) -> Result<(), String> {
+    let filter = match filter.trim() {
+        "" => DEFAULT_FILTER,
+        filter => filter,
+    };
+    let targets = parse_filter(filter)?;
+    let handle = FILTER.get().ok_or("logging is not set up")?;
+    handle.reload(targets).map_err(|e| e.to_string())?;
+    *FILTER_TEXT.lock().unwrap() = filter.to_string();
+    Ok(())
+}
+
+/// The filter in use, as given to [`set_filter`].
+pub fn filter() -> String {
+    FILTER_TEXT.lock().unwrap().clone()
+}
+
+/// The latest lines, oldest first.
+pub fn recent() -> Vec<LogLine> {
+    RECENT.lock().unwrap().iter().cloned().collect()
+}
+
+/// See [`WRITTEN`].
+pub fn written() -> usize {
+    WRITTEN.load(Ordering::Relaxed)
+}
+
+/// Forgets the lines kept for the log viewer, the files keep them.
+pub fn clear() {
+    RECENT.lock().unwrap().clear();
+    WRITTEN.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Keeps the latest lines in memory for the log viewer.
+struct Recent;
+
+impl<S: Subscriber> Layer<S> for Recent {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = Message::default();
+        event.record(&mut message);
+        let meta = event.metadata();
+        let line = LogLine {
+            time: chrono::Local::now(),
+            level: *meta.level(),
+            target: meta.target().to_string(),
+            message: message.0.trim_start().to_string(),
+        };
+
+        let mut recent = RECENT.lock().unwrap();
+        if recent.len() == RECENT_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line);
+        WRITTEN.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The message of an event followed by its other fields as `name=value`.
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        } else {
+            let _ = write!(self.0, " {}={value:?}", field.name());
+        }
+    }
+}
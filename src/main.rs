@@ -1,25 +1,124 @@
 use std::{
+    cell::RefCell,
     collections::BTreeMap,
+    path::PathBuf,
+    rc::Rc,
     sync::{Arc, atomic::AtomicUsize},
 };
 
+use bench_window::BenchWindow;
+use collections_window::CollectionsWindow;
 use controls::MainControls;
-use db::{LeGrillonDb, OpenWindow};
-use fltk::app;
+use db::{
+    BenchRun, Collection, CollectionRequest, LeGrillonDb, MockRoute, OpenWindow, SentRequest,
+    WINDOW_KIND_GRPC, WINDOW_KIND_WEBSOCKET,
+};
+use diff_window::DiffWindow;
+use fltk::{app, dialog};
 use fltk_theme::WidgetTheme;
+use grpc_window::GrpcWindow;
+use history_window::HistoryWindow;
+use log_window::LogWindow;
+use mock_window::MockWindow;
+use palette::PaletteWindow;
+use proxy_window::ProxyWindow;
 use req_window::RequestWindow;
+use schema_window::SchemaWindow;
+use settings::Settings;
+use shortcuts::Action;
+use vars::VariableScopes;
+use ws_window::WebSocketWindow;
 
+mod autosave;
+mod bench;
+mod bench_window;
+mod collections_window;
 mod controls;
 mod db;
+mod diff;
+mod diff_window;
+mod find_bar;
+mod fs_store;
+mod graphql;
+mod grpc;
+mod grpc_window;
+mod guard;
+mod har;
+mod history_window;
+mod host;
+mod http_file;
+mod json_query;
+mod json_tree;
+mod log_window;
+mod logging;
+mod mock;
+mod mock_window;
+mod openapi;
+mod palette;
+mod postman;
+mod proxy;
+mod proxy_window;
 mod req_params;
 mod req_window;
+mod schema_window;
+mod secrets;
+mod settings;
+mod shortcuts;
+mod sidebar;
+mod sse;
+mod timing;
+mod vars;
+mod ws_window;
 
 #[derive(Debug)]
 enum GlobalAppMsg {
     OpenEmptyWindow,
+    OpenWebSocketWindow,
+    OpenGrpcWindow,
     Restore(OpenWindow),
     SaveWindowState(OpenWindow),
+    SaveWindowResponse(i32, SentRequest),
     CloseWindow(usize),
+    ImportPostmanCollection(PathBuf),
+    ImportPostmanEnvironment(PathBuf),
+    ImportOpenApi(String),
+    ImportHttpFile(PathBuf),
+    ScopesLoaded(VariableScopes),
+    CollectionsChanged,
+    SetEnvironmentColor(i64, String),
+    SetEnvironmentReadOnly(i64, bool),
+    /// Marks a variable of the environment as secret or not.
+    SetVariableSecret(i64, String, bool),
+    SaveToCollection(String, CollectionRequest),
+    OpenCollections,
+    ShowCollections(Vec<(Collection, Vec<CollectionRequest>)>),
+    RecordSentRequest(SentRequest),
+    OpenHistory,
+    ImportHar(PathBuf),
+    ShowHistory(String, Vec<SentRequest>),
+    SaveGraphQlSchema(String, String),
+    LoadGraphQlSchema(usize, String, bool),
+    GraphQlSchemaLoaded(usize, String),
+    ShowGraphQlDocs(graphql::Schema),
+    OpenMockServer,
+    ShowMockServer(Vec<MockRoute>),
+    SaveMockRoute(MockRoute),
+    DeleteMockRoute(i64),
+    PromoteToMock(SentRequest),
+    MockRoutesLoaded(Vec<MockRoute>),
+    OpenProxy,
+    OpenLogs,
+    OpenBench(bench::Target),
+    LoadBenchRuns(usize, String),
+    SaveBenchRun(usize, BenchRun),
+    BenchRunsLoaded(usize, Vec<BenchRun>),
+    ShowDiff(Vec<SentRequest>),
+    DiffResponses(usize),
+    RefreshSidebar,
+    SidebarLoaded(Vec<(Collection, Vec<CollectionRequest>)>, Vec<SentRequest>),
+    Notify(String),
+    /// A shortcut or the command palette, with the window it was invoked from.
+    RunAction(Option<usize>, Action),
     CloseApp,
 }
 
@@ -29,6 +128,31 @@ pub(crate) trait HasId {
 
 pub(crate) trait AppWindow {
     fn close(&mut self);
+
+    /// Environments or collection variables were (re)loaded.
+    fn scopes_changed(&mut self) {}
+
+    /// A cached GraphQL introspection result for this window was loaded.
+    fn graphql_schema_loaded(&mut self, _schema: &str) {}
+
+    /// The mock routes were changed.
+    fn mock_routes_loaded(&mut self, _routes: &[MockRoute]) {}
+
+    /// Saved load test runs for this window's URI were loaded.
+    fn bench_runs_loaded(&mut self, _runs: &[BenchRun]) {}
+
+    /// The latest response shown in this window, offered for diffs.
+    fn last_response(&self) -> Option<SentRequest> {
+        None
+    }
+
+    /// What to save for windows that are reopened on the next start.
+    fn window_state(&self) -> Option<OpenWindow> {
+        None
+    }
+
+    /// A shortcut or the command palette ran an action on this window.
+    fn run_action(&mut self, _action: Action) {}
 }
 
 static WINDOW_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
@@ -37,12 +161,44 @@ fn next_window_id() -> usize {
     WINDOW_ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
 }
 
+/// Asks for the master passphrase until the secrets unlock, twice when it is chosen. When
+/// cancelled the secrets stay encrypted until the next start.
+async fn unlock_secrets(db: &LeGrillonDb) {
+    let first_time = !db.has_master_passphrase().await;
+    loop {
+        let prompt = if first_time {
+            "Choose a master passphrase to encrypt secrets:"
+        } else {
+            "Master passphrase:"
+        };
+        let Some(passphrase) = dialog::password_default(prompt, "") else {
+            dialog::message_default(
+                "Secrets stay locked until the next start: new secrets are not saved and \
+                 encrypted ones are not sent.",
+            );
+            return;
+        };
+        if first_time
+            && dialog::password_default("Repeat the master passphrase:", "").as_ref()
+                != Some(&passphrase)
+        {
+            dialog::alert_default("The passphrases do not match.");
+            continue;
+        }
+        match db.unlock_secrets(&passphrase).await {
+            Ok(()) => return,
+            Err(e) => dialog::alert_default(&format!("Could not unlock the secrets: {e}")),
+        }
+    }
+}
+
 struct LeGrillon {
     app: app::App,
     receiver: app::Receiver<GlobalAppMsg>,
     ctrls: MainControls,
     windows: std::collections::BTreeMap<usize, Box<dyn AppWindow>>,
     db: Arc<LeGrillonDb>,
+    scopes: Rc<RefCell<VariableScopes>>,
 }
 
 impl LeGrillon {
@@ -53,11 +209,20 @@ impl LeGrillon {
         let widget_theme = WidgetTheme::new(fltk_theme::ThemeType::Dark);
         widget_theme.apply();
 
-        let ctrls = crate::controls::MainControls::new(s);
+        let settings = Settings::load();
+        if let Err(e) = logging::set_filter(&settings.log_level) {
+            tracing::warn!("invalid log_level {e}");
+        }
+        shortcuts::init(&settings.shortcuts);
+        guard::init(&settings.protected_hosts);
+        let ctrls = crate::controls::MainControls::new(s, settings.tabbed_layout);
 
         let window_map: BTreeMap<usize, Box<dyn AppWindow>> = std::collections::BTreeMap::new();
 
-        let db = Arc::new(LeGrillonDb::new().await);
+        let db = Arc::new(LeGrillonDb::new(&settings).await);
+        if settings.encrypt_secrets {
+            unlock_secrets(&db).await;
+        }
 
         LeGrillon {
             app,
@@ -65,6 +230,7 @@ impl LeGrillon {
             ctrls,
             windows: window_map,
             db,
+            scopes: Rc::new(RefCell::new(VariableScopes::default())),
         }
     }
 
@@ -73,13 +239,120 @@ impl LeGrillon {
 
         while self.app.wait() {
             if let Some(msg) = self.receiver.recv() {
-                println!("MAIN::RUN:: {msg:?}");
+                let flags = match &msg {
+                    GlobalAppMsg::ScopesLoaded(scopes) => scopes.flagged_secrets(),
+                    _ => self.scopes.borrow().flagged_secrets(),
+                };
+                tracing::debug!("{}", secrets::redact_log(&format!("{msg:?}"), &flags));
                 match msg {
                     GlobalAppMsg::OpenEmptyWindow => self.open(None),
+                    GlobalAppMsg::OpenWebSocketWindow => {
+                        let wnd = WebSocketWindow::new(None, self.ctrls.tabs());
+                        self.windows.insert(wnd.id(), Box::new(wnd));
+                    }
+                    GlobalAppMsg::OpenGrpcWindow => {
+                        let wnd = GrpcWindow::new(None, self.ctrls.tabs());
+                        self.windows.insert(wnd.id(), Box::new(wnd));
+                    }
                     GlobalAppMsg::CloseWindow(id) => self.close(id),
                     GlobalAppMsg::SaveWindowState(_) => (),
+                    GlobalAppMsg::SaveWindowResponse(..) => (),
                     GlobalAppMsg::Restore(ref open_window) => self.open(Some(open_window)),
+                    GlobalAppMsg::ImportPostmanCollection(_) => (),
+                    GlobalAppMsg::ImportPostmanEnvironment(_) => (),
+                    GlobalAppMsg::ImportOpenApi(_) => (),
+                    GlobalAppMsg::ImportHttpFile(_) => (),
+                    GlobalAppMsg::CollectionsChanged => (),
+                    GlobalAppMsg::SetEnvironmentColor(..) => (),
+                    GlobalAppMsg::SetEnvironmentReadOnly(..) => (),
+                    GlobalAppMsg::SetVariableSecret(..) => (),
+                    GlobalAppMsg::SaveToCollection(..) => (),
+                    GlobalAppMsg::ScopesLoaded(ref scopes) => {
+                        *self.scopes.borrow_mut() = scopes.clone();
+                        for wnd in self.windows.values_mut() {
+                            wnd.scopes_changed();
+                        }
+                        // collections may have changed with the variables
+                        if self.ctrls.sidebar().is_some() {
+                            let (s, _) = app::channel::<GlobalAppMsg>();
+                            s.send(GlobalAppMsg::RefreshSidebar);
+                        }
+                    }
+                    GlobalAppMsg::OpenCollections => (),
+                    GlobalAppMsg::ShowCollections(ref collections) => {
+                        self.show_collections(collections)
+                    }
+                    GlobalAppMsg::RecordSentRequest(ref sent) => {
+                        if let Some(sidebar) = self.ctrls.sidebar() {
+                            sidebar.add_history(sent);
+                        }
+                    }
+                    GlobalAppMsg::OpenHistory => (),
+                    GlobalAppMsg::ImportHar(_) => (),
+                    GlobalAppMsg::ShowHistory(ref title, ref entries) => {
+                        self.show_history(title, entries)
+                    }
+                    GlobalAppMsg::SaveGraphQlSchema(..) => (),
+                    GlobalAppMsg::LoadGraphQlSchema(..) => (),
+                    GlobalAppMsg::GraphQlSchemaLoaded(id, ref schema) => {
+                        if let Some(wnd) = self.windows.get_mut(&id) {
+                            wnd.graphql_schema_loaded(schema);
+                        }
+                    }
+                    GlobalAppMsg::ShowGraphQlDocs(ref schema) => {
+                        let wnd = SchemaWindow::new(schema);
+                        self.windows.insert(wnd.id(), Box::new(wnd));
+                    }
+                    GlobalAppMsg::OpenMockServer => (),
+                    GlobalAppMsg::ShowMockServer(ref routes) => {
+                        let wnd = MockWindow::new(routes);
+                        self.windows.insert(wnd.id(), Box::new(wnd));
+                    }
+                    GlobalAppMsg::SaveMockRoute(_) => (),
+                    GlobalAppMsg::DeleteMockRoute(_) => (),
+                    GlobalAppMsg::PromoteToMock(_) => (),
+                    GlobalAppMsg::MockRoutesLoaded(ref routes) => {
+                        for wnd in self.windows.values_mut() {
+                            wnd.mock_routes_loaded(routes);
+                        }
+                    }
+                    GlobalAppMsg::OpenProxy => {
+                        let wnd = ProxyWindow::new();
+                        self.windows.insert(wnd.id(), Box::new(wnd));
+                    }
+                    GlobalAppMsg::OpenLogs => {
+                        let wnd = LogWindow::new();
+                        self.windows.insert(wnd.id(), Box::new(wnd));
+                    }
+                    GlobalAppMsg::OpenBench(ref target) => {
+                        let wnd = BenchWindow::new(target.clone());
+                        self.windows.insert(wnd.id(), Box::new(wnd));
+                    }
+                    GlobalAppMsg::LoadBenchRuns(..) => (),
+                    GlobalAppMsg::SaveBenchRun(..) => (),
+                    GlobalAppMsg::BenchRunsLoaded(id, ref runs) => {
+                        if let Some(wnd) = self.windows.get_mut(&id) {
+                            wnd.bench_runs_loaded(runs);
+                        }
+                    }
+                    GlobalAppMsg::ShowDiff(ref entries) => {
+                        if entries.len() >= 2 {
+                            let wnd = DiffWindow::new(entries, 0, 1);
+                            self.windows.insert(wnd.id(), Box::new(wnd));
+                        }
+                    }
+                    GlobalAppMsg::DiffResponses(id) => self.diff_responses(id),
+                    GlobalAppMsg::RefreshSidebar => (),
+                    GlobalAppMsg::SidebarLoaded(ref collections, ref history) => {
+                        if let Some(sidebar) = self.ctrls.sidebar() {
+                            sidebar.set(collections, history);
+                        }
+                    }
+                    GlobalAppMsg::Notify(ref text) => dialog::message_default(text),
+                    GlobalAppMsg::RunAction(origin, action) => self.run_action(origin, action),
                     GlobalAppMsg::CloseApp => {
+                        let states = self.windows.values().filter_map(|w| w.window_state());
+                        self.db.shutdown(states.collect());
                         for wnd in self.windows.values_mut() {
                             wnd.close();
                         }
@@ -96,13 +369,89 @@ impl LeGrillon {
         }
     }
 
+    fn show_collections(&mut self, collections: &[(Collection, Vec<CollectionRequest>)]) {
+        let wnd = CollectionsWindow::new(collections);
+        self.windows.insert(wnd.id(), Box::new(wnd));
+    }
+
+    fn show_history(&mut self, title: &str, entries: &[SentRequest]) {
+        let wnd = HistoryWindow::new(title, entries);
+        self.windows.insert(wnd.id(), Box::new(wnd));
+    }
+
+    /// Diffs the last response of window `id` against the other open windows' responses.
+    fn diff_responses(&mut self, id: usize) {
+        let Some(own) = self.windows.get(&id).and_then(|w| w.last_response()) else {
+            dialog::message_default("Send the request first.");
+            return;
+        };
+        let mut entries: Vec<SentRequest> = self
+            .windows
+            .iter()
+            .filter(|(other, _)| **other != id)
+            .filter_map(|(_, w)| w.last_response())
+            .collect();
+        if entries.is_empty() {
+            dialog::message_default("No other window has a response to compare with.");
+            return;
+        }
+        entries.push(own);
+        let wnd = DiffWindow::new(&entries, 0, entries.len() - 1);
+        self.windows.insert(wnd.id(), Box::new(wnd));
+    }
+
+    /// Runs application wide actions here, the others on the window they were invoked from.
+    fn run_action(&mut self, origin: Option<usize>, action: Action) {
+        let (s, _) = app::channel::<GlobalAppMsg>();
+        match action {
+            Action::NewWindow => self.open(None),
+            Action::NewWebSocket => s.send(GlobalAppMsg::OpenWebSocketWindow),
+            Action::NewGrpc => s.send(GlobalAppMsg::OpenGrpcWindow),
+            Action::OpenCollections => s.send(GlobalAppMsg::OpenCollections),
+            Action::OpenHistory => s.send(GlobalAppMsg::OpenHistory),
+            Action::OpenMockServer => s.send(GlobalAppMsg::OpenMockServer),
+            Action::OpenProxy => s.send(GlobalAppMsg::OpenProxy),
+            Action::OpenLogs => s.send(GlobalAppMsg::OpenLogs),
+            Action::CommandPalette => {
+                let wnd = PaletteWindow::new(origin);
+                self.windows.insert(wnd.id(), Box::new(wnd));
+            }
+            // closing through the message also forgets the window's saved state
+            Action::CloseWindow => {
+                if let Some(id) = origin {
+                    s.send(GlobalAppMsg::CloseWindow(id));
+                }
+            }
+            _ => {
+                if let Some(wnd) = origin.and_then(|id| self.windows.get_mut(&id)) {
+                    wnd.run_action(action);
+                }
+            }
+        }
+    }
+
     fn open(&mut self, wnd: Option<&OpenWindow>) {
-        let req_win = RequestWindow::new(wnd);
+        match wnd.map(|w| w.kind.as_str()) {
+            Some(WINDOW_KIND_WEBSOCKET) => {
+                let ws_win = WebSocketWindow::new(wnd, self.ctrls.tabs());
+                self.windows.insert(ws_win.id(), Box::new(ws_win));
+                return;
+            }
+            Some(WINDOW_KIND_GRPC) => {
+                let grpc_win = GrpcWindow::new(wnd, self.ctrls.tabs());
+                self.windows.insert(grpc_win.id(), Box::new(grpc_win));
+                return;
+            }
+            _ => (),
+        }
+
+        let req_win = RequestWindow::new(wnd, self.scopes.clone(), self.ctrls.tabs());
         self.windows.insert(req_win.id(), Box::new(req_win));
     }
 }
 
 #[tokio::main]
 async fn main() {
+    let _log = logging::init();
     LeGrillon::new().await.run();
 }
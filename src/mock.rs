@@ -0,0 +1,156 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    Request, Response, StatusCode,
+    body::{Bytes, Incoming},
+    header::{HeaderName, HeaderValue},
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use tokio::{net::TcpListener, sync::mpsc::UnboundedSender, task::JoinHandle};
+
+use crate::db::MockRoute;
+
+pub const DEFAULT_PORT: u16 = 8089;
+/// Pause after a failed accept, e.g. out of file descriptors, instead of spinning.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// An incoming call, reported to the mock server window.
+#[derive(Debug)]
+pub struct MockCall {
+    pub at: chrono::DateTime<chrono::Local>,
+    pub method: String,
+    pub uri: String,
+    pub body_len: usize,
+    pub status: u16,
+    pub route: Option<String>,
+}
+
+impl MockRoute {
+    /// `*` matches any method, path segments `:name` / `{name}` match any one segment and a
+    /// trailing `*` matches the rest of the path.
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        if self.method != "*" && !self.method.eq_ignore_ascii_case(method) {
+            return false;
+        }
+
+        let mut pattern = self.path.trim_matches('/').split('/');
+        let mut segments = path.trim_matches('/').split('/');
+        loop {
+            match (pattern.next(), segments.next()) {
+                (Some("*"), _) => return true,
+                (Some(p), Some(s)) => {
+                    let is_param = p.starts_with(':') || (p.starts_with('{') && p.ends_with('}'));
+                    if !is_param && p != s {
+                        return false;
+                    }
+                }
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// Serves `routes` on localhost until the returned task is aborted, the first matching
+/// route answers. Route changes apply to the running server.
+pub fn serve(
+    port: u16,
+    routes: Arc<RwLock<Vec<MockRoute>>>,
+    calls: UnboundedSender<MockCall>,
+) -> Result<JoinHandle<()>, String> {
+    // bound synchronously so the window can report a port that is already in use
+    let listener = std::net::TcpListener::bind(("127.0.0.1", port))
+        .and_then(|l| l.set_nonblocking(true).map(|_| l))
+        .and_then(TcpListener::from_std)
+        .map_err(|e| format!("127.0.0.1:{port}: {e}"))?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("mock server accept: {e}");
+                    tokio::time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+
+            let routes = routes.clone();
+            let calls = calls.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |req| respond(req, routes.clone(), calls.clone()));
+                if let Err(e) = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    tracing::debug!("mock server connection: {e}");
+                }
+            });
+        }
+    }))
+}
+
+async fn respond(
+    req: Request<Incoming>,
+    routes: Arc<RwLock<Vec<MockRoute>>>,
+    calls: UnboundedSender<MockCall>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let method = req.method().to_string();
+    let uri = req.uri().to_string();
+    let route = routes
+        .read()
+        .unwrap()
+        .iter()
+        .find(|r| r.matches(&method, req.uri().path()))
+        .cloned();
+    let body_len = req
+        .into_body()
+        .collect()
+        .await
+        .map_or(0, |b| b.to_bytes().len());
+
+    let response = match &route {
+        Some(route) => {
+            if route.delay_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(route.delay_ms as u64)).await;
+            }
+
+            let mut response = Response::new(Full::new(Bytes::from(route.body.clone())));
+            *response.status_mut() =
+                StatusCode::from_u16(route.status as u16).unwrap_or(StatusCode::OK);
+            for (n, v) in &route.headers.0 {
+                if let (Ok(n), Ok(v)) = (
+                    HeaderName::from_bytes(n.trim().as_bytes()),
+                    HeaderValue::from_str(v.trim()),
+                ) {
+                    response.headers_mut().append(n, v);
+                }
+            }
+            response
+        }
+        None => {
+            let mut response = Response::new(Full::new(Bytes::from(format!(
+                "no mock route for {method} {uri}\n"
+            ))));
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            response
+        }
+    };
+
+    let _ = calls.send(MockCall {
+        at: chrono::Local::now(),
+        method,
+        uri,
+        body_len,
+        status: response.status().as_u16(),
+        route: route.map(|r| format!("{} {}", r.method, r.path)),
+    });
+
+    Ok(response)
+}
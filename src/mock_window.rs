@@ -0,0 +1,340 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::{Arc, RwLock},
+};
+
+use fltk::{
+    app, browser, button,
+    enums::{self, Event},
+    frame,
+    group::{self, Flex, Tabs},
+    input,
+    prelude::*,
+    text::{self, TextBuffer},
+    window::{self, DoubleWindow},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{
+    AppWindow, GlobalAppMsg, HasId,
+    db::MockRoute,
+    mock::{self, MockCall},
+    next_window_id,
+};
+
+/// Edits the mock routes and runs the mock server while the window is open.
+pub struct MockWindow {
+    id: usize,
+    window: DoubleWindow,
+    routes: Arc<RwLock<Vec<MockRoute>>>,
+    list: browser::HoldBrowser,
+    /// Id of the route in the editor, 0 for a new one.
+    selected: Rc<Cell<i64>>,
+    /// Method and path of a new route being saved, to select it once it has an id.
+    saved_new: Rc<RefCell<Option<(String, String)>>>,
+    server: Rc<RefCell<Option<JoinHandle<()>>>>,
+}
+
+fn fill_routes(list: &mut browser::HoldBrowser, routes: &[MockRoute], selected: i64) {
+    list.clear();
+    for (i, r) in routes.iter().enumerate() {
+        list.add(&format!("{}\t{}\t{}", r.method, r.status, r.path));
+        if r.id == selected {
+            list.select(i as i32 + 1);
+        }
+    }
+}
+
+fn log_call(buf: &mut TextBuffer, call: &MockCall) {
+    buf.append(&format!(
+        "{} {} {} -> {} ({} request bytes) {}\n",
+        call.at.format("%H:%M:%S%.3f"),
+        call.method,
+        call.uri,
+        call.status,
+        call.body_len,
+        call.route.as_deref().unwrap_or("no route")
+    ));
+    app::awake();
+}
+
+impl MockWindow {
+    pub fn new(routes: &[MockRoute]) -> Self {
+        let id = next_window_id();
+
+        let mut win = window::DoubleWindow::default()
+            .with_size(1200, 800)
+            .with_label("Le Grillon - Mock server");
+
+        let mut col = group::Flex::default_fill().column();
+        let mut row = group::Flex::default().row();
+        let port_label = frame::Frame::default().with_label("Port:");
+        row.fixed(&port_label, 64);
+        let mut port_input = input::IntInput::default();
+        port_input.set_value(&mock::DEFAULT_PORT.to_string());
+        row.fixed(&port_input, 96);
+        let mut start_btn = button::Button::default().with_label("Start");
+        row.fixed(&start_btn, 128);
+        let mut status = frame::Frame::default();
+        status.set_frame(enums::FrameType::FlatBox);
+        row.end();
+        col.fixed(&row, 32);
+
+        let mut row = group::Flex::default_fill().row();
+        let mut list_col = group::Flex::default().column();
+        let mut list = browser::HoldBrowser::default();
+        list.set_column_char('\t');
+        list.set_column_widths(&[96, 56]);
+        let buttons = group::Flex::default().row();
+        let mut new_btn = button::Button::default().with_label("New");
+        let mut delete_btn = button::Button::default().with_label("Delete");
+        buttons.end();
+        list_col.fixed(&buttons, 32);
+        list_col.end();
+        row.fixed(&list_col, 400);
+
+        let mut edit_col = group::Flex::default().column();
+        let mut edit_row = group::Flex::default().row();
+        let mut method_input = input::Input::default();
+        method_input.set_tooltip("HTTP method, * matches any");
+        edit_row.fixed(&method_input, 96);
+        let mut path_input = input::Input::default();
+        path_input.set_tooltip("/users/:id, /files/{name} or /static/*");
+        edit_row.end();
+        edit_col.fixed(&edit_row, 32);
+
+        let mut edit_row = group::Flex::default().row();
+        let status_label = frame::Frame::default().with_label("Status:");
+        edit_row.fixed(&status_label, 64);
+        let status_input = input::IntInput::default();
+        edit_row.fixed(&status_input, 80);
+        let delay_label = frame::Frame::default().with_label("Delay ms:");
+        edit_row.fixed(&delay_label, 96);
+        let delay_input = input::IntInput::default();
+        edit_row.fixed(&delay_input, 80);
+        frame::Frame::default();
+        let mut save_btn = button::Button::default().with_label("Save");
+        edit_row.fixed(&save_btn, 128);
+        edit_row.end();
+        edit_col.fixed(&edit_row, 32);
+
+        let mut tab = Tabs::default_fill();
+        let grp1 = Flex::default_fill().with_label("Body\t\t").row();
+        let body_buf = text::TextBuffer::default();
+        let mut body = text::TextEditor::default();
+        body.set_buffer(body_buf.clone());
+        body.set_text_font(enums::Font::Courier);
+        grp1.end();
+        let grp2 = Flex::default_fill().with_label("Headers\t\t").row();
+        let headers_buf = text::TextBuffer::default();
+        let mut headers = text::TextEditor::default();
+        headers.set_buffer(headers_buf.clone());
+        headers.set_text_font(enums::Font::Courier);
+        grp2.end();
+        tab.end();
+        tab.auto_layout();
+        edit_col.end();
+        row.end();
+
+        let log_buf = text::TextBuffer::default();
+        let mut log = text::TextDisplay::default();
+        log.set_buffer(log_buf.clone());
+        log.set_text_font(enums::Font::Courier);
+        col.fixed(&log, 200);
+        col.end();
+
+        win.end();
+        win.make_resizable(true);
+        win.show();
+
+        let routes = Arc::new(RwLock::new(routes.to_vec()));
+        let selected = Rc::new(Cell::new(0i64));
+        fill_routes(&mut list, &routes.read().unwrap(), 0);
+
+        let mut clear_editor = {
+            let mut method_input = method_input.clone();
+            let mut path_input = path_input.clone();
+            let mut status_input = status_input.clone();
+            let mut delay_input = delay_input.clone();
+            let mut body_buf = body_buf.clone();
+            let mut headers_buf = headers_buf.clone();
+            let selected = selected.clone();
+            move || {
+                selected.set(0);
+                method_input.set_value("GET");
+                path_input.set_value("/");
+                status_input.set_value("200");
+                delay_input.set_value("0");
+                body_buf.set_text("");
+                headers_buf.set_text("Content-Type: application/json\n");
+            }
+        };
+        clear_editor();
+
+        let p_routes = routes.clone();
+        let p_selected = selected.clone();
+        let mut p_method_input = method_input.clone();
+        let mut p_path_input = path_input.clone();
+        let mut p_status_input = status_input.clone();
+        let mut p_delay_input = delay_input.clone();
+        let mut p_body_buf = body_buf.clone();
+        let mut p_headers_buf = headers_buf.clone();
+        list.set_callback(move |l| {
+            let line = l.value();
+            if line < 1 {
+                return;
+            }
+            let routes = p_routes.read().unwrap();
+            let Some(r) = routes.get(line as usize - 1) else {
+                return;
+            };
+            p_selected.set(r.id);
+            p_method_input.set_value(&r.method);
+            p_path_input.set_value(&r.path);
+            p_status_input.set_value(&r.status.to_string());
+            p_delay_input.set_value(&r.delay_ms.to_string());
+            p_body_buf.set_text(&r.body);
+            p_headers_buf.set_text("");
+            for (n, v) in &r.headers.0 {
+                p_headers_buf.append(&format!("{n}: {v}\n"));
+            }
+        });
+
+        let mut p_list = list.clone();
+        new_btn.set_callback(move |_| {
+            let line = p_list.value();
+            p_list.deselect(line);
+            clear_editor();
+        });
+
+        let (s, _) = app::channel::<GlobalAppMsg>();
+        let p_sender = s;
+        let p_selected = selected.clone();
+        delete_btn.set_callback(move |_| {
+            let id = p_selected.get();
+            if id != 0 {
+                p_sender.send(GlobalAppMsg::DeleteMockRoute(id));
+            }
+        });
+
+        let saved_new: Rc<RefCell<Option<(String, String)>>> = Rc::new(RefCell::new(None));
+        let p_saved_new = saved_new.clone();
+        let p_selected = selected.clone();
+        let p_sender = s;
+        save_btn.set_callback(move |_| {
+            let route = MockRoute {
+                id: p_selected.get(),
+                method: match method_input.value().trim() {
+                    "" => "*".to_string(),
+                    m => m.to_uppercase(),
+                },
+                path: format!("/{}", path_input.value().trim().trim_start_matches('/')),
+                status: status_input.value().parse().unwrap_or(200),
+                headers: sqlx::types::Json(
+                    headers_buf
+                        .text()
+                        .lines()
+                        .filter_map(|l| l.split_once(':'))
+                        .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+                        .collect(),
+                ),
+                body: body_buf.text(),
+                delay_ms: delay_input.value().parse().unwrap_or(0),
+            };
+            if route.id == 0 {
+                *p_saved_new.borrow_mut() = Some((route.method.clone(), route.path.clone()));
+            }
+            p_sender.send(GlobalAppMsg::SaveMockRoute(route));
+        });
+
+        let server: Rc<RefCell<Option<JoinHandle<()>>>> = Rc::new(RefCell::new(None));
+        let p_server = server.clone();
+        let p_routes = routes.clone();
+        start_btn.set_callback(move |btn| {
+            if let Some(running) = p_server.borrow_mut().take() {
+                running.abort();
+                btn.set_label("Start");
+                status.set_label("Stopped");
+                port_input.activate();
+                return;
+            }
+
+            let Ok(port) = port_input.value().parse::<u16>() else {
+                status.set_label("Invalid port");
+                return;
+            };
+            let (tx, mut rx) = mpsc::unbounded_channel::<MockCall>();
+            match mock::serve(port, p_routes.clone(), tx) {
+                Ok(handle) => {
+                    *p_server.borrow_mut() = Some(handle);
+                    btn.set_label("Stop");
+                    status.set_label(&format!("Listening on http://127.0.0.1:{port}"));
+                    port_input.deactivate();
+
+                    let mut log_buf = log_buf.clone();
+                    tokio::spawn(async move {
+                        while let Some(call) = rx.recv().await {
+                            log_call(&mut log_buf, &call);
+                        }
+                    });
+                }
+                Err(e) => status.set_label(&e),
+            }
+        });
+
+        let p_sender = s;
+        win.handle(move |_, e| {
+            if e == Event::Hide {
+                tracing::debug!("window {id} (MockWindow) closed");
+                p_sender.send(GlobalAppMsg::CloseWindow(id));
+                return true;
+            }
+
+            false
+        });
+
+        Self {
+            id,
+            window: win,
+            routes,
+            list,
+            selected,
+            saved_new,
+            server,
+        }
+    }
+}
+
+impl HasId for MockWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for MockWindow {
+    fn close(&mut self) {
+        if let Some(running) = self.server.borrow_mut().take() {
+            running.abort();
+        }
+        self.window.hide();
+    }
+
+    fn mock_routes_loaded(&mut self, routes: &[MockRoute]) {
+        let mut selected = self.selected.get();
+        if let Some((method, path)) = self.saved_new.borrow_mut().take()
+            && let Some(saved) = routes
+                .iter()
+                .filter(|r| r.method == method && r.path == path)
+                .max_by_key(|r| r.id)
+        {
+            selected = saved.id;
+        }
+        if !routes.iter().any(|r| r.id == selected) {
+            selected = 0;
+        }
+        self.selected.set(selected);
+        *self.routes.write().unwrap() = routes.to_vec();
+        fill_routes(&mut self.list, routes, selected);
+    }
+}
@@ -0,0 +1,593 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use crate::db::{Collection, CollectionRequest, Environment};
+
+/// Requests generated from an OpenAPI 3 / Swagger 2 document.
+///
+/// The server URL ends up as the `baseUrl` variable of `environment`, path,
+/// query and header parameters as collection variables.
+#[derive(Debug)]
+pub struct OpenApiImport {
+    pub collection: Collection,
+    pub requests: Vec<CollectionRequest>,
+    pub environment: Environment,
+    pub untranslated: Vec<String>,
+}
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+pub fn import(text: &str, source_url: Option<&str>) -> Result<OpenApiImport, String> {
+    let doc: Value = match serde_json::from_str(text) {
+        Ok(doc) => doc,
+        Err(_) => serde_yaml::from_str(text).map_err(|e| e.to_string())?,
+    };
+
+    let swagger2 = doc.get("swagger").is_some();
+    if !swagger2 && doc.get("openapi").is_none() {
+        return Err("not an OpenAPI 3 or Swagger 2 document".to_string());
+    }
+
+    let title = doc
+        .pointer("/info/title")
+        .and_then(Value::as_str)
+        .unwrap_or("OpenAPI")
+        .to_string();
+
+    let mut untranslated = Vec::new();
+    let base_url = base_url(&doc, swagger2, source_url, &mut untranslated);
+
+    let mut variables: BTreeMap<String, String> = BTreeMap::new();
+    let mut requests = Vec::new();
+
+    let paths = doc.get("paths").and_then(Value::as_object);
+    for (path, item) in paths.into_iter().flatten() {
+        let item = resolve(item, &doc);
+        let path_params = item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+
+        for method in METHODS {
+            let Some(op) = item.get(method) else {
+                continue;
+            };
+            requests.push(translate_operation(
+                &doc,
+                swagger2,
+                path,
+                method,
+                op,
+                &path_params,
+                &mut variables,
+                &mut untranslated,
+            ));
+        }
+    }
+
+    Ok(OpenApiImport {
+        collection: Collection {
+            id: 0,
+            name: title.clone(),
+            variables: sqlx::types::Json(variables.into_iter().collect()),
+            secrets: sqlx::types::Json(BTreeMap::new()),
+        },
+        requests,
+        environment: Environment {
+            id: 0,
+            name: title,
+            variables: sqlx::types::Json(vec![("baseUrl".to_string(), base_url)]),
+            secrets: sqlx::types::Json(BTreeMap::new()),
+            color: String::new(),
+            read_only: false,
+        },
+        untranslated,
+    })
+}
+
+fn base_url(
+    doc: &Value,
+    swagger2: bool,
+    source_url: Option<&str>,
+    untranslated: &mut Vec<String>,
+) -> String {
+    let url = if swagger2 {
+        let host = doc.get("host").and_then(Value::as_str);
+        let base_path = doc.get("basePath").and_then(Value::as_str).unwrap_or("");
+        let scheme = doc
+            .pointer("/schemes/0")
+            .and_then(Value::as_str)
+            .unwrap_or("https");
+        match host {
+            Some(host) => format!("{scheme}://{host}{base_path}"),
+            None => base_path.to_string(),
+        }
+    } else {
+        let server = doc.pointer("/servers/0");
+        let mut url = server
+            .and_then(|s| s.get("url"))
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string();
+        let server_vars = server
+            .and_then(|s| s.get("variables"))
+            .and_then(Value::as_object);
+        for (name, var) in server_vars.into_iter().flatten() {
+            let default = var.get("default").map(value_to_string).unwrap_or_default();
+            url = url.replace(&format!("{{{name}}}"), &default);
+        }
+        if doc
+            .get("servers")
+            .and_then(Value::as_array)
+            .is_some_and(|s| s.len() > 1)
+        {
+            untranslated.push("additional servers besides the first one".to_string());
+        }
+        url
+    };
+
+    // relative server urls are relative to where the document was loaded from
+    if !url.contains("://") {
+        if let Some(joined) = source_url
+            .and_then(|s| reqwest::Url::parse(s).ok())
+            .and_then(|s| s.join(if url.is_empty() { "/" } else { &url }).ok())
+        {
+            return joined.to_string().trim_end_matches('/').to_string();
+        }
+        untranslated.push(format!("relative server URL '{url}'"));
+    }
+
+    url.trim_end_matches('/').to_string()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn translate_operation(
+    doc: &Value,
+    swagger2: bool,
+    path: &str,
+    method: &str,
+    op: &Value,
+    path_params: &[Value],
+    variables: &mut BTreeMap<String, String>,
+    untranslated: &mut Vec<String>,
+) -> CollectionRequest {
+    let method_upper = method.to_uppercase();
+    let name = op
+        .get("operationId")
+        .or_else(|| op.get("summary"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{method_upper} {path}"));
+    let op_name = format!("{method_upper} {path}");
+
+    // operation level parameters override path level ones with the same name and location
+    let mut params: BTreeMap<(String, String), Value> = BTreeMap::new();
+    let op_params = op
+        .get("parameters")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for p in path_params.iter().chain(op_params.iter()) {
+        let p = resolve(p, doc).clone();
+        let name = p
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string();
+        let location = p
+            .get("in")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string();
+        params.insert((location, name), p);
+    }
+
+    let mut uri = format!("{{{{baseUrl}}}}{}", template_path(path));
+    let mut query = Vec::new();
+    let mut headers = Vec::new();
+    let mut body = String::new();
+    let mut form = Map::new();
+
+    for ((location, name), p) in &params {
+        let example = param_example(p, doc);
+        match location.as_str() {
+            "path" => {
+                variables.entry(name.clone()).or_insert(example);
+            }
+            "query" => {
+                query.push(format!("{name}={{{{{name}}}}}"));
+                variables.entry(name.clone()).or_insert(example);
+            }
+            "header" => {
+                headers.push((name.clone(), format!("{{{{{name}}}}}")));
+                variables.entry(name.clone()).or_insert(example);
+            }
+            "body" => {
+                let schema = p.get("schema").cloned().unwrap_or(Value::Null);
+                body = to_pretty(&synthesize(&schema, doc));
+                headers.push(("Content-Type".to_string(), "application/json".to_string()));
+            }
+            "formData" => {
+                if p.get("type").and_then(Value::as_str) == Some("file") {
+                    untranslated.push(format!("file parameter '{name}' on '{op_name}'"));
+                } else {
+                    form.insert(name.clone(), synthesize(p, doc));
+                }
+            }
+            other => untranslated.push(format!("{other} parameter '{name}' on '{op_name}'")),
+        }
+    }
+
+    if !form.is_empty() {
+        body = form_body(&form);
+        headers.push((
+            "Content-Type".to_string(),
+            "application/x-www-form-urlencoded".to_string(),
+        ));
+    }
+
+    if !swagger2 && let Some(request_body) = op.get("requestBody").map(|b| resolve(b, doc)) {
+        translate_request_body(
+            request_body,
+            doc,
+            &op_name,
+            &mut headers,
+            &mut body,
+            untranslated,
+        );
+    }
+
+    if !query.is_empty() {
+        uri.push('?');
+        uri.push_str(&query.join("&"));
+    }
+
+    CollectionRequest {
+        id: 0,
+        collection_id: 0,
+        folder: op
+            .pointer("/tags/0")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string(),
+        name,
+        method: method_upper,
+        uri,
+        headers: sqlx::types::Json(headers),
+        body,
+    }
+}
+
+fn translate_request_body(
+    request_body: &Value,
+    doc: &Value,
+    op_name: &str,
+    headers: &mut Vec<(String, String)>,
+    body: &mut String,
+    untranslated: &mut Vec<String>,
+) {
+    let Some(content) = request_body.get("content").and_then(Value::as_object) else {
+        return;
+    };
+
+    let is_json = |ct: &str| ct == "application/json" || ct.ends_with("+json");
+    let chosen = content
+        .iter()
+        .find(|(ct, _)| is_json(ct))
+        .or_else(|| content.iter().next());
+    let Some((content_type, media)) = chosen else {
+        return;
+    };
+
+    let example = media.get("example").cloned().or_else(|| {
+        media
+            .get("examples")
+            .and_then(Value::as_object)
+            .and_then(|e| e.values().next())
+            .map(|e| resolve(e, doc))
+            .and_then(|e| e.get("value"))
+            .cloned()
+    });
+    let value = example.unwrap_or_else(|| {
+        let schema = media.get("schema").cloned().unwrap_or(Value::Null);
+        synthesize(&schema, doc)
+    });
+
+    if is_json(content_type) {
+        *body = to_pretty(&value);
+    } else if content_type == "application/x-www-form-urlencoded" {
+        *body = value.as_object().map(form_body).unwrap_or_default();
+    } else if let Value::String(s) = &value {
+        *body = s.clone();
+    } else {
+        untranslated.push(format!("'{content_type}' request body on '{op_name}'"));
+    }
+
+    headers.push(("Content-Type".to_string(), content_type.clone()));
+}
+
+/// `/pets/{petId}` -> `/pets/{{petId}}`
+fn template_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len() + 8);
+    for c in path.chars() {
+        match c {
+            '{' => out.push_str("{{"),
+            '}' => out.push_str("}}"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn param_example(p: &Value, doc: &Value) -> String {
+    if let Some(ex) = p.get("example") {
+        return value_to_string(ex);
+    }
+    if let Some(ex) = p
+        .get("examples")
+        .and_then(Value::as_object)
+        .and_then(|e| e.values().next())
+        .and_then(|e| resolve(e, doc).get("value"))
+    {
+        return value_to_string(ex);
+    }
+    // swagger 2 keeps type/default/enum on the parameter itself
+    let schema = p.get("schema").unwrap_or(p);
+    let schema = resolve(schema, doc);
+    schema
+        .get("example")
+        .or_else(|| schema.get("default"))
+        .or_else(|| schema.pointer("/enum/0"))
+        .map(value_to_string)
+        .unwrap_or_default()
+}
+
+fn resolve<'a>(mut v: &'a Value, doc: &'a Value) -> &'a Value {
+    for _ in 0..16 {
+        match v
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|r| r.strip_prefix('#'))
+            .and_then(|pointer| doc.pointer(pointer))
+        {
+            Some(target) => v = target,
+            None => break,
+        }
+    }
+    v
+}
+
+/// Builds an example value that satisfies `schema` closely enough to be edited by hand.
+fn synthesize(schema: &Value, doc: &Value) -> Value {
+    synthesize_inner(schema, doc, &mut Vec::new())
+}
+
+/// `refs` holds the `$ref`s currently being expanded, recursive schemas stop at `null`.
+fn synthesize_inner<'a>(schema: &'a Value, doc: &'a Value, refs: &mut Vec<&'a str>) -> Value {
+    let reference = schema.get("$ref").and_then(Value::as_str);
+    if let Some(r) = reference {
+        if refs.contains(&r) || refs.len() > 16 {
+            return Value::Null;
+        }
+        refs.push(r);
+    }
+
+    let value = synthesize_resolved(resolve(schema, doc), doc, refs);
+
+    if reference.is_some() {
+        refs.pop();
+    }
+    value
+}
+
+fn synthesize_resolved<'a>(schema: &'a Value, doc: &'a Value, refs: &mut Vec<&'a str>) -> Value {
+    if let Some(v) = schema
+        .get("example")
+        .or_else(|| schema.get("default"))
+        .or_else(|| schema.pointer("/enum/0"))
+    {
+        return v.clone();
+    }
+
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        let mut merged = Map::new();
+        for s in all {
+            if let Value::Object(o) = synthesize_inner(s, doc, refs) {
+                merged.extend(o);
+            }
+        }
+        return Value::Object(merged);
+    }
+    if let Some(first) = schema
+        .pointer("/oneOf/0")
+        .or_else(|| schema.pointer("/anyOf/0"))
+    {
+        return synthesize_inner(first, doc, refs);
+    }
+
+    let kind = match schema.get("type") {
+        Some(Value::String(t)) => t.as_str(),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|t| *t != "null")
+            .unwrap_or("null"),
+        _ if schema.get("properties").is_some() => "object",
+        _ => "",
+    };
+
+    match kind {
+        "object" => {
+            let props = schema.get("properties").and_then(Value::as_object);
+            let obj = props
+                .into_iter()
+                .flatten()
+                .map(|(k, v)| (k.clone(), synthesize_inner(v, doc, refs)))
+                .collect();
+            Value::Object(obj)
+        }
+        "array" => {
+            let items = schema.get("items").unwrap_or(&Value::Null);
+            Value::Array(vec![synthesize_inner(items, doc, refs)])
+        }
+        "string" => Value::String(
+            match schema.get("format").and_then(Value::as_str) {
+                Some("date-time") => "1970-01-01T00:00:00Z",
+                Some("date") => "1970-01-01",
+                Some("uuid") => "00000000-0000-0000-0000-000000000000",
+                Some("email") => "user@example.com",
+                Some("uri") | Some("url") => "https://example.com",
+                _ => "string",
+            }
+            .to_string(),
+        ),
+        "integer" => Value::from(0),
+        "number" => Value::from(0.0),
+        "boolean" => Value::Bool(false),
+        _ => Value::Null,
+    }
+}
+
+fn form_body(form: &Map<String, Value>) -> String {
+    form.iter()
+        .map(|(k, v)| format!("{k}={}", value_to_string(v)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn to_pretty(v: &Value) -> String {
+    serde_json::to_string_pretty(v).unwrap_or_default()
+}
+
+fn value_to_string(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn translates_paths_servers_and_parameters() {
+        let doc = json!({
+            "openapi": "3.0.0",
+            "info": {"title": "Pets"},
+            "servers": [
+                {"url": "https://{region}.example.com/v1/", "variables": {"region": {"default": "eu"}}},
+                {"url": "https://backup.example.com"}
+            ],
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [
+                        {"name": "petId", "in": "path", "schema": {"type": "integer", "example": 7}},
+                        {"name": "verbose", "in": "query", "schema": {"default": false}}
+                    ],
+                    "put": {
+                        "operationId": "updatePet",
+                        "tags": ["pets"],
+                        "parameters": [
+                            {"name": "verbose", "in": "query", "example": true},
+                            {"name": "X-Trace", "in": "header", "schema": {"enum": ["on", "off"]}},
+                            {"name": "session", "in": "cookie"}
+                        ],
+                        "requestBody": {"content": {"application/json": {
+                            "schema": {"$ref": "#/components/schemas/Pet"}
+                        }}}
+                    }
+                }
+            },
+            "components": {"schemas": {"Pet": {
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "parent": {"$ref": "#/components/schemas/Pet"}
+                }
+            }}}
+        });
+
+        let import = import(&doc.to_string(), None).unwrap();
+        assert_eq!(
+            import.environment.variables.0,
+            [(
+                "baseUrl".to_string(),
+                "https://eu.example.com/v1".to_string()
+            )]
+        );
+
+        let [req] = &import.requests[..] else {
+            panic!("{:?}", import.requests);
+        };
+        assert_eq!(
+            (req.folder.as_str(), req.name.as_str(), req.method.as_str()),
+            ("pets", "updatePet", "PUT")
+        );
+        assert_eq!(req.uri, "{{baseUrl}}/pets/{{petId}}?verbose={{verbose}}");
+        assert_eq!(
+            req.headers.0,
+            [
+                ("X-Trace".to_string(), "{{X-Trace}}".to_string()),
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ]
+        );
+        let body: Value = serde_json::from_str(&req.body).unwrap();
+        // the recursive schema stops where it refers to itself
+        assert_eq!(body, json!({"name": "string", "parent": null}));
+
+        let variables: BTreeMap<&str, &str> = import
+            .collection
+            .variables
+            .0
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            variables,
+            BTreeMap::from([("X-Trace", "on"), ("petId", "7"), ("verbose", "true")])
+        );
+        assert_eq!(
+            import.untranslated,
+            [
+                "additional servers besides the first one",
+                "cookie parameter 'session' on 'PUT /pets/{petId}'"
+            ]
+        );
+    }
+
+    #[test]
+    fn swagger2_and_relative_servers() {
+        let swagger = json!({
+            "swagger": "2.0",
+            "host": "api.example.com",
+            "basePath": "/v2",
+            "schemes": ["http"],
+            "paths": {"/login": {"post": {"parameters": [
+                {"name": "user", "in": "formData", "type": "string", "default": "admin"},
+                {"name": "pin", "in": "formData", "type": "integer"}
+            ]}}}
+        });
+        let import = import(&swagger.to_string(), None).unwrap();
+        assert_eq!(
+            import.environment.variables.0[0].1,
+            "http://api.example.com/v2"
+        );
+        assert_eq!(import.requests[0].name, "POST /login");
+        assert_eq!(import.requests[0].body, "pin=0&user=admin");
+
+        let relative = "openapi: 3.1.0\nservers:\n  - url: /api\npaths: {}\n";
+        let import = super::import(relative, Some("https://example.com/docs/openapi.yaml"));
+        assert_eq!(
+            import.unwrap().environment.variables.0[0].1,
+            "https://example.com/api"
+        );
+    }
+}
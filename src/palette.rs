@@ -0,0 +1,146 @@
+use std::{cell::RefCell, rc::Rc};
+
+use fltk::{
+    app, browser,
+    enums::{CallbackTrigger, Event, Key},
+    group, input,
+    prelude::*,
+    window::{self, DoubleWindow},
+};
+
+use crate::{
+    AppWindow, GlobalAppMsg, HasId, next_window_id,
+    shortcuts::{self, Action},
+};
+
+/// Lists every action with its shortcut, typing narrows the list with a fuzzy search.
+pub struct PaletteWindow {
+    id: usize,
+    window: DoubleWindow,
+}
+
+/// Actions matching `query`, best match first.
+fn matching(query: &str) -> Vec<Action> {
+    let mut scored: Vec<(i32, Action)> = Action::ALL
+        .into_iter()
+        .filter_map(|a| shortcuts::fuzzy_score(query, a.label()).map(|s| (s, a)))
+        .collect();
+    // stable, so equal scores keep the order of `Action::ALL`
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, a)| a).collect()
+}
+
+fn fill(list: &mut browser::HoldBrowser, actions: &[Action]) {
+    list.clear();
+    for action in actions {
+        let binding = shortcuts::keymap()
+            .binding(*action)
+            .map(|b| b.to_string())
+            .unwrap_or_default();
+        list.add(&format!("{}\t{binding}", action.label()));
+    }
+    if !actions.is_empty() {
+        list.select(1);
+    }
+}
+
+impl PaletteWindow {
+    /// `origin` is the window the actions run on.
+    pub fn new(origin: Option<usize>) -> Self {
+        let id = next_window_id();
+
+        let mut win = window::DoubleWindow::default()
+            .with_size(560, 420)
+            .with_label("Commands");
+
+        let mut col = group::Flex::default_fill().column();
+        let mut search = input::Input::default();
+        search.set_tooltip("Type to filter, Up/Down to pick, Enter to run");
+        col.fixed(&search, 32);
+        let mut list = browser::HoldBrowser::default();
+        list.set_column_widths(&[400]);
+        col.end();
+
+        win.end();
+        win.make_resizable(true);
+        win.show();
+
+        let shown = Rc::new(RefCell::new(matching("")));
+        fill(&mut list, &shown.borrow());
+        let _ = search.take_focus();
+
+        let (s, _) = app::channel::<GlobalAppMsg>();
+
+        let run = {
+            let list = list.clone();
+            let shown = shown.clone();
+            let win = win.clone();
+            move || {
+                let line = list.value();
+                if line < 1 {
+                    return;
+                }
+                if let Some(action) = shown.borrow().get(line as usize - 1) {
+                    s.send(GlobalAppMsg::RunAction(origin, *action));
+                    win.clone().hide();
+                }
+            }
+        };
+        let run = Rc::new(run);
+
+        search.set_trigger(CallbackTrigger::Changed | CallbackTrigger::EnterKeyAlways);
+        let (p_run, p_shown, mut p_list) = (run.clone(), shown.clone(), list.clone());
+        search.set_callback(move |i| {
+            if app::event() == Event::KeyDown && app::event_key() == Key::Enter {
+                p_run();
+                return;
+            }
+            *p_shown.borrow_mut() = matching(&i.value());
+            fill(&mut p_list, &p_shown.borrow());
+        });
+
+        let mut p_list = list.clone();
+        search.handle(move |_, e| {
+            if e != Event::KeyDown {
+                return false;
+            }
+            let step = match app::event_key() {
+                key if key == Key::Up => -1,
+                key if key == Key::Down => 1,
+                _ => return false,
+            };
+            let line = (p_list.value() + step).clamp(1, p_list.size().max(1));
+            p_list.select(line);
+            true
+        });
+
+        list.set_callback(move |_| {
+            if app::event_clicks() {
+                run();
+            }
+        });
+
+        win.handle(move |_, e| {
+            if e == Event::Hide {
+                s.send(GlobalAppMsg::CloseWindow(id));
+                return true;
+            }
+
+            false
+        });
+
+        Self { id, window: win }
+    }
+}
+
+impl HasId for PaletteWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for PaletteWindow {
+    fn close(&mut self) {
+        self.window.hide();
+    }
+}
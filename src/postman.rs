@@ -0,0 +1,628 @@
+use std::collections::BTreeMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    db::{Collection, CollectionRequest, Environment},
+    secrets::SecretFlags,
+};
+
+/// Result of translating a Postman v2.1 collection export.
+///
+/// `untranslated` lists everything that was dropped on the way (scripts,
+/// unsupported auth types, file uploads, ...) so it can be reported back.
+#[derive(Debug)]
+pub struct CollectionImport {
+    pub collection: Collection,
+    pub requests: Vec<CollectionRequest>,
+    pub untranslated: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct EnvironmentImport {
+    pub environment: Environment,
+    pub untranslated: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostmanCollection {
+    info: Info,
+    #[serde(default)]
+    item: Vec<Item>,
+    auth: Option<Auth>,
+    #[serde(default)]
+    variable: Vec<KeyValue>,
+    #[serde(default)]
+    event: Vec<Event>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Info {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    #[serde(default)]
+    name: String,
+    item: Option<Vec<Item>>,
+    request: Option<Request>,
+    auth: Option<Auth>,
+    #[serde(default)]
+    event: Vec<Event>,
+    #[serde(default)]
+    variable: Vec<KeyValue>,
+    #[serde(default)]
+    response: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Request {
+    Url(String),
+    Full(Box<FullRequest>),
+}
+
+#[derive(Debug, Deserialize)]
+struct FullRequest {
+    #[serde(default = "default_method")]
+    method: String,
+    #[serde(default)]
+    header: Headers,
+    body: Option<Body>,
+    url: Option<Url>,
+    auth: Option<Auth>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Headers {
+    List(Vec<KeyValue>),
+    Raw(String),
+}
+
+impl Default for Headers {
+    fn default() -> Self {
+        Headers::List(Vec::new())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Url {
+    Raw(String),
+    Parts(UrlParts),
+}
+
+#[derive(Debug, Deserialize)]
+struct UrlParts {
+    #[serde(default)]
+    raw: String,
+    protocol: Option<String>,
+    host: Option<Value>,
+    port: Option<String>,
+    path: Option<Value>,
+    #[serde(default)]
+    query: Vec<KeyValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyValue {
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Body {
+    mode: Option<String>,
+    raw: Option<String>,
+    #[serde(default)]
+    urlencoded: Vec<KeyValue>,
+    #[serde(default)]
+    formdata: Vec<KeyValue>,
+    graphql: Option<GraphQlBody>,
+    options: Option<Value>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlBody {
+    #[serde(default)]
+    query: String,
+    variables: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Auth {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(flatten)]
+    params: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Event {
+    #[serde(default)]
+    listen: String,
+    script: Option<Script>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Script {
+    #[serde(default)]
+    exec: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostmanEnvironment {
+    name: String,
+    #[serde(default)]
+    values: Vec<EnvironmentValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnvironmentValue {
+    key: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    /// `secret` for values Postman masks.
+    #[serde(default, rename = "type")]
+    kind: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+pub fn import_collection(json: &str) -> Result<CollectionImport, serde_json::Error> {
+    let pm: PostmanCollection = serde_json::from_str(json)?;
+
+    let mut import = CollectionImport {
+        collection: Collection {
+            id: 0,
+            name: pm.info.name,
+            variables: sqlx::types::Json(
+                pm.variable
+                    .iter()
+                    .filter(|v| !v.disabled)
+                    .map(|v| (v.key.clone(), value_to_string(&v.value)))
+                    .collect(),
+            ),
+            secrets: sqlx::types::Json(
+                pm.variable
+                    .iter()
+                    .filter(|v| !v.disabled && v.kind.as_deref() == Some("secret"))
+                    .map(|v| (v.key.clone(), true))
+                    .collect(),
+            ),
+        },
+        requests: Vec::new(),
+        untranslated: Vec::new(),
+    };
+
+    report_events(&pm.event, "collection", &mut import.untranslated);
+
+    for item in &pm.item {
+        translate_item(item, "", pm.auth.as_ref(), &mut import);
+    }
+
+    Ok(import)
+}
+
+pub fn import_environment(json: &str) -> Result<EnvironmentImport, serde_json::Error> {
+    let pm: PostmanEnvironment = serde_json::from_str(json)?;
+
+    let mut untranslated = Vec::new();
+    let mut variables = Vec::new();
+    let mut secrets = SecretFlags::new();
+    for v in pm.values {
+        if v.enabled {
+            if v.kind == "secret" {
+                secrets.insert(v.key.clone(), true);
+            }
+            variables.push((v.key, value_to_string(&v.value)));
+        } else {
+            untranslated.push(format!("disabled variable '{}'", v.key));
+        }
+    }
+
+    Ok(EnvironmentImport {
+        environment: Environment {
+            id: 0,
+            name: pm.name,
+            variables: sqlx::types::Json(variables),
+            secrets: sqlx::types::Json(secrets),
+            color: String::new(),
+            read_only: false,
+        },
+        untranslated,
+    })
+}
+
+fn translate_item(
+    item: &Item,
+    folder: &str,
+    inherited_auth: Option<&Auth>,
+    import: &mut CollectionImport,
+) {
+    let path = if folder.is_empty() {
+        item.name.clone()
+    } else {
+        format!("{folder}/{}", item.name)
+    };
+
+    report_events(&item.event, &path, &mut import.untranslated);
+    if !item.variable.is_empty() {
+        import.untranslated.push(format!(
+            "{} variable(s) scoped to '{path}'",
+            item.variable.len()
+        ));
+    }
+
+    let auth = match &item.auth {
+        Some(a) if a.kind != "inherit" => Some(a),
+        _ => inherited_auth,
+    };
+
+    if let Some(children) = &item.item {
+        for child in children {
+            translate_item(child, &path, auth, import);
+        }
+        return;
+    }
+
+    let Some(request) = &item.request else {
+        import.untranslated.push(format!("'{path}' has no request"));
+        return;
+    };
+
+    if !item.response.is_empty() {
+        import.untranslated.push(format!(
+            "{} saved example response(s) on '{path}'",
+            item.response.len()
+        ));
+    }
+
+    let mut req = CollectionRequest {
+        id: 0,
+        collection_id: 0,
+        folder: folder.to_string(),
+        name: item.name.clone(),
+        method: "GET".to_string(),
+        uri: String::new(),
+        headers: sqlx::types::Json(Vec::new()),
+        body: String::new(),
+    };
+
+    let mut auth = auth;
+    match request {
+        Request::Url(url) => req.uri = url.clone(),
+        Request::Full(full) => {
+            req.method = full.method.to_uppercase();
+            req.uri = full.url.as_ref().map(url_to_string).unwrap_or_default();
+
+            match &full.header {
+                Headers::List(headers) => {
+                    for h in headers.iter().filter(|h| !h.disabled) {
+                        req.headers
+                            .0
+                            .push((h.key.clone(), value_to_string(&h.value)));
+                    }
+                }
+                Headers::Raw(raw) => {
+                    for (n, v) in raw.lines().filter_map(|l| l.split_once(':')) {
+                        req.headers
+                            .0
+                            .push((n.trim().to_string(), v.trim().to_string()));
+                    }
+                }
+            }
+
+            if let Some(a) = full.auth.as_ref().filter(|a| a.kind != "inherit") {
+                auth = Some(a);
+            }
+
+            if let Some(body) = &full.body {
+                translate_body(body, &path, &mut req, &mut import.untranslated);
+            }
+        }
+    }
+
+    if let Some(auth) = auth {
+        translate_auth(auth, &path, &mut req, &mut import.untranslated);
+    }
+
+    import.requests.push(req);
+}
+
+fn translate_auth(
+    auth: &Auth,
+    path: &str,
+    req: &mut CollectionRequest,
+    untranslated: &mut Vec<String>,
+) {
+    let param = |name: &str| auth_param(auth, name).unwrap_or_default();
+
+    match auth.kind.as_str() {
+        "noauth" => (),
+        "bearer" => {
+            set_header_if_missing(req, "Authorization", format!("Bearer {}", param("token")))
+        }
+        "basic" => {
+            let (user, pass) = (param("username"), param("password"));
+            if user.contains("{{") || pass.contains("{{") {
+                untranslated.push(format!(
+                    "basic auth on '{path}' uses variables; they were encoded literally"
+                ));
+            }
+            let token = STANDARD.encode(format!("{user}:{pass}"));
+            set_header_if_missing(req, "Authorization", format!("Basic {token}"));
+        }
+        "apikey" => {
+            let (key, value) = (param("key"), param("value"));
+            if param("in") == "query" {
+                let sep = if req.uri.contains('?') { '&' } else { '?' };
+                req.uri = format!("{}{sep}{key}={value}", req.uri);
+            } else {
+                set_header_if_missing(req, &key, value);
+            }
+        }
+        other => untranslated.push(format!("'{other}' auth on '{path}'")),
+    }
+}
+
+/// Postman v2.1 stores auth parameters as `[{key, value}]`, v2.0 as a plain object.
+fn auth_param(auth: &Auth, name: &str) -> Option<String> {
+    match auth.params.get(&auth.kind)? {
+        Value::Array(kvs) => kvs
+            .iter()
+            .find(|kv| kv.get("key").and_then(Value::as_str) == Some(name))
+            .and_then(|kv| kv.get("value"))
+            .map(value_to_string),
+        Value::Object(map) => map.get(name).map(value_to_string),
+        _ => None,
+    }
+}
+
+fn translate_body(
+    body: &Body,
+    path: &str,
+    req: &mut CollectionRequest,
+    untranslated: &mut Vec<String>,
+) {
+    if body.disabled {
+        return;
+    }
+
+    match body.mode.as_deref() {
+        Some("raw") => {
+            req.body = body.raw.clone().unwrap_or_default();
+            let language = body
+                .options
+                .as_ref()
+                .and_then(|o| o.pointer("/raw/language"))
+                .and_then(Value::as_str);
+            let content_type = match language {
+                Some("json") => Some("application/json"),
+                Some("xml") => Some("application/xml"),
+                Some("html") => Some("text/html"),
+                Some("javascript") => Some("application/javascript"),
+                _ => None,
+            };
+            if let Some(ct) = content_type {
+                set_header_if_missing(req, "Content-Type", ct.to_string());
+            }
+        }
+        Some("urlencoded") => {
+            req.body = body
+                .urlencoded
+                .iter()
+                .filter(|kv| !kv.disabled)
+                .map(|kv| {
+                    format!(
+                        "{}={}",
+                        form_encode(&kv.key),
+                        form_encode(&value_to_string(&kv.value))
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            set_header_if_missing(
+                req,
+                "Content-Type",
+                "application/x-www-form-urlencoded".to_string(),
+            );
+        }
+        Some("formdata") => {
+            let boundary = "----LeGrillonFormBoundary";
+            let mut out = String::new();
+            for kv in body.formdata.iter().filter(|kv| !kv.disabled) {
+                if kv.kind.as_deref() == Some("file") {
+                    untranslated.push(format!("file form field '{}' on '{path}'", kv.key));
+                    continue;
+                }
+                out.push_str(&format!(
+                    "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                    kv.key,
+                    value_to_string(&kv.value)
+                ));
+            }
+            out.push_str(&format!("--{boundary}--\r\n"));
+            req.body = out;
+            set_header_if_missing(
+                req,
+                "Content-Type",
+                format!("multipart/form-data; boundary={boundary}"),
+            );
+        }
+        Some("graphql") => {
+            if let Some(gql) = &body.graphql {
+                let variables = gql
+                    .variables
+                    .as_deref()
+                    .filter(|v| !v.trim().is_empty())
+                    .and_then(|v| serde_json::from_str::<Value>(v).ok())
+                    .unwrap_or(Value::Null);
+                let envelope = serde_json::json!({ "query": gql.query, "variables": variables });
+                req.body = serde_json::to_string_pretty(&envelope).unwrap_or_default();
+                set_header_if_missing(req, "Content-Type", "application/json".to_string());
+            }
+        }
+        Some("file") => untranslated.push(format!("binary file body on '{path}'")),
+        Some(other) => untranslated.push(format!("'{other}' body on '{path}'")),
+        None => (),
+    }
+}
+
+fn report_events(events: &[Event], path: &str, untranslated: &mut Vec<String>) {
+    for ev in events {
+        let has_code = match ev.script.as_ref().map(|s| &s.exec) {
+            Some(Value::Array(lines)) => lines
+                .iter()
+                .any(|l| l.as_str().is_some_and(|l| !l.trim().is_empty())),
+            Some(Value::String(s)) => !s.trim().is_empty(),
+            _ => false,
+        };
+        if has_code {
+            untranslated.push(format!("{} script on '{path}'", ev.listen));
+        }
+    }
+}
+
+fn set_header_if_missing(req: &mut CollectionRequest, name: &str, value: String) {
+    if !req
+        .headers
+        .0
+        .iter()
+        .any(|(n, _)| n.eq_ignore_ascii_case(name))
+    {
+        req.headers.0.push((name.to_string(), value));
+    }
+}
+
+fn url_to_string(url: &Url) -> String {
+    match url {
+        Url::Raw(raw) => raw.clone(),
+        Url::Parts(parts) if !parts.raw.is_empty() => parts.raw.clone(),
+        Url::Parts(parts) => {
+            let join = |v: &Option<Value>, sep: &str| match v {
+                Some(Value::Array(segs)) => segs
+                    .iter()
+                    .map(value_to_string)
+                    .collect::<Vec<_>>()
+                    .join(sep),
+                Some(v) => value_to_string(v),
+                None => String::new(),
+            };
+
+            let mut out = String::new();
+            if let Some(protocol) = &parts.protocol {
+                out.push_str(&format!("{protocol}://"));
+            }
+            out.push_str(&join(&parts.host, "."));
+            if let Some(port) = &parts.port {
+                out.push_str(&format!(":{port}"));
+            }
+            let path = join(&parts.path, "/");
+            if !path.is_empty() {
+                out.push('/');
+                out.push_str(path.trim_start_matches('/'));
+            }
+            let query = parts
+                .query
+                .iter()
+                .filter(|q| !q.disabled)
+                .map(|q| format!("{}={}", q.key, value_to_string(&q.value)))
+                .collect::<Vec<_>>();
+            if !query.is_empty() {
+                out.push('?');
+                out.push_str(&query.join("&"));
+            }
+            out
+        }
+    }
+}
+
+fn value_to_string(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// `application/x-www-form-urlencoded` encoding that leaves `{{variable}}`
+/// placeholders readable.
+fn form_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'{' | b'}' => {
+                out.push(b as char)
+            }
+            b' ' => out.push('+'),
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_secret_type() {
+        let import = import_environment(
+            r#"{
+                "name": "prod",
+                "values": [
+                    {"key": "host", "value": "api.example.com", "type": "default"},
+                    {"key": "signingKey", "value": "s3cr3t", "type": "secret"},
+                    {"key": "pin", "value": "1234", "type": "secret", "enabled": false}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let env = import.environment;
+        assert_eq!(env.variables.0.len(), 2);
+        assert_eq!(
+            env.secrets.0,
+            SecretFlags::from([("signingKey".to_string(), true)])
+        );
+        assert_eq!(import.untranslated, ["disabled variable 'pin'"]);
+
+        let import = import_collection(
+            r#"{
+                "info": {"name": "api"},
+                "variable": [
+                    {"key": "host", "value": "api.example.com", "type": "string"},
+                    {"key": "signingKey", "value": "s3cr3t", "type": "secret"},
+                    {"key": "pin", "value": "1234", "type": "secret", "disabled": true}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let collection = import.collection;
+        assert_eq!(collection.variables.0.len(), 2);
+        assert_eq!(
+            collection.secrets.0,
+            SecretFlags::from([("signingKey".to_string(), true)])
+        );
+    }
+}
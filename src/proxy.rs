@@ -0,0 +1,504 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::Datelike;
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::{Bytes, Incoming},
+    header::{HOST, HeaderMap},
+    server::conn::http1,
+    service::service_fn,
+    upgrade::Upgraded,
+};
+use hyper_util::rt::TokioIo;
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, Issuer, KeyPair, KeyUsagePurpose};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc::UnboundedSender,
+    task::JoinHandle,
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        self, ServerConfig,
+        pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+    },
+};
+
+use crate::db::SentRequest;
+
+pub const DEFAULT_PORT: u16 = 8090;
+/// Kept in [`ca_dir`], the certificate is what clients have to trust.
+pub const CA_CERT_FILE: &str = "legrillon-ca.pem";
+/// Only readable by the user, anyone holding it can intercept the user's HTTPS.
+const CA_KEY_FILE: &str = "legrillon-ca.key";
+/// Pause after a failed accept, e.g. out of file descriptors, instead of spinning.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Headers that only concern one connection, they are not forwarded.
+const HOP_BY_HOP: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Local CA signing a certificate for every intercepted host.
+pub struct CertificateAuthority {
+    issuer: Issuer<'static, KeyPair>,
+    pub cert_pem: String,
+    configs: Mutex<HashMap<String, Arc<ServerConfig>>>,
+}
+
+/// The per-user application data directory, `$XDG_DATA_HOME/legrillon`,
+/// `~/Library/Application Support/legrillon` or `%APPDATA%\legrillon`.
+pub fn ca_dir() -> Result<PathBuf, String> {
+    let env = |name: &str| {
+        std::env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = if cfg!(windows) {
+        env("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env("XDG_DATA_HOME").or_else(|| env("HOME").map(|home| home.join(".local/share")))
+    };
+    base.map(|base| base.join("legrillon"))
+        .ok_or_else(|| "no home directory for the proxy CA".to_string())
+}
+
+/// The PEM of the CA key, `None` when there is none yet. On unix a key other users can read
+/// is refused, it has to be fixed or deleted to get a new CA.
+fn read_key(path: &Path) -> Result<Option<String>, String> {
+    let error = |e: io::Error| format!("{}: {e}", path.display());
+    let meta = match fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(error(e)),
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = meta.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(format!(
+                "{} can be read by other users (mode {:o}), it should be 600",
+                path.display(),
+                mode & 0o777
+            ));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = meta;
+    fs::read_to_string(path).map(Some).map_err(error)
+}
+
+/// Creates the key only readable by the user, never overwriting an existing file.
+fn create_key(path: &Path, pem: &str) -> Result<(), String> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(pem.as_bytes()))
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The issuer name has to be the same every time the stored key is loaded.
+fn ca_params() -> CertificateParams {
+    let mut params = CertificateParams::default();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "Le Grillon proxy CA");
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    params
+}
+
+impl CertificateAuthority {
+    /// Loads the CA from [`ca_dir`] or generates it on first use.
+    pub fn load_or_create() -> Result<Self, String> {
+        let dir = ca_dir()?;
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder
+            .create(&dir)
+            .map_err(|e| format!("{}: {e}", dir.display()))?;
+        let (cert_path, key_path) = (dir.join(CA_CERT_FILE), dir.join(CA_KEY_FILE));
+
+        let key = match read_key(&key_path)? {
+            Some(key_pem) => {
+                KeyPair::from_pem(&key_pem).map_err(|e| format!("{}: {e}", key_path.display()))?
+            }
+            None => {
+                let key = KeyPair::generate().map_err(|e| e.to_string())?;
+                create_key(&key_path, &key.serialize_pem())?;
+                // a certificate left from an earlier key would not match
+                let _ = fs::remove_file(&cert_path);
+                key
+            }
+        };
+
+        let cert_pem = match fs::read_to_string(&cert_path) {
+            Ok(cert_pem) => cert_pem,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let cert = ca_params().self_signed(&key).map_err(|e| e.to_string())?;
+                fs::write(&cert_path, cert.pem())
+                    .map_err(|e| format!("{}: {e}", cert_path.display()))?;
+                tracing::info!("created the proxy CA {}", cert_path.display());
+                cert.pem()
+            }
+            Err(e) => return Err(format!("{}: {e}", cert_path.display())),
+        };
+
+        Ok(CertificateAuthority {
+            issuer: Issuer::new(ca_params(), key),
+            cert_pem,
+            configs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn server_config(&self, host: &str) -> Result<Arc<ServerConfig>, String> {
+        if let Some(config) = self.configs.lock().unwrap().get(host) {
+            return Ok(config.clone());
+        }
+
+        // clients reject leaf certificates valid for more than about a year
+        let today = chrono::Utc::now().date_naive();
+        let (from, to) = (
+            today.pred_opt().unwrap_or(today),
+            today + chrono::Days::new(365),
+        );
+        let mut params =
+            CertificateParams::new(vec![host.to_string()]).map_err(|e| e.to_string())?;
+        params.distinguished_name.push(DnType::CommonName, host);
+        params.not_before = rcgen::date_time_ymd(from.year(), from.month() as u8, from.day() as u8);
+        params.not_after = rcgen::date_time_ymd(to.year(), to.month() as u8, to.day() as u8);
+        let key = KeyPair::generate().map_err(|e| e.to_string())?;
+        let cert = params
+            .signed_by(&key, &self.issuer)
+            .map_err(|e| e.to_string())?;
+
+        let mut config =
+            ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .map_err(|e| e.to_string())?
+                .with_no_client_auth()
+                .with_single_cert(
+                    vec![cert.der().clone()],
+                    PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der())),
+                )
+                .map_err(|e| e.to_string())?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        let config = Arc::new(config);
+        self.configs
+            .lock()
+            .unwrap()
+            .insert(host.to_string(), config.clone());
+        Ok(config)
+    }
+}
+
+struct Proxy {
+    client: reqwest::Client,
+    ca: Option<Arc<CertificateAuthority>>,
+    captured: UnboundedSender<SentRequest>,
+}
+
+/// Forward proxy on localhost until the returned task is aborted, every call passing
+/// through is sent to `captured`. Without `ca` HTTPS is tunnelled and not captured.
+pub fn serve(
+    port: u16,
+    ca: Option<Arc<CertificateAuthority>>,
+    captured: UnboundedSender<SentRequest>,
+) -> Result<JoinHandle<()>, String> {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", port))
+        .and_then(|l| l.set_nonblocking(true).map(|_| l))
+        .and_then(TcpListener::from_std)
+        .map_err(|e| format!("127.0.0.1:{port}: {e}"))?;
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| e.to_string())?;
+    let proxy = Arc::new(Proxy {
+        client,
+        ca,
+        captured,
+    });
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("proxy accept: {e}");
+                    tokio::time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+
+            let proxy = proxy.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |req| proxy.clone().handle(req));
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .with_upgrades()
+                    .await
+                {
+                    tracing::debug!("proxy connection: {e}");
+                }
+            });
+        }
+    }))
+}
+
+fn text_response(status: StatusCode, text: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(text)));
+    *response.status_mut() = status;
+    response
+}
+
+fn forwarded_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    for name in HOP_BY_HOP {
+        headers.remove(name);
+    }
+    headers
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(n, v)| {
+            (
+                n.to_string(),
+                String::from_utf8_lossy(v.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+impl Proxy {
+    async fn handle(
+        self: Arc<Self>,
+        req: Request<Incoming>,
+    ) -> Result<Response<Full<Bytes>>, Infallible> {
+        if req.method() == Method::CONNECT {
+            return Ok(self.connect(req));
+        }
+        if req.uri().scheme().is_none() {
+            return Ok(text_response(
+                StatusCode::BAD_REQUEST,
+                format!("{} is not a proxy request\n", req.uri()),
+            ));
+        }
+
+        let uri = req.uri().to_string();
+        Ok(self.forward(req, uri).await)
+    }
+
+    fn connect(self: Arc<Self>, req: Request<Incoming>) -> Response<Full<Bytes>> {
+        let Some(authority) = req.uri().authority().map(|a| a.to_string()) else {
+            return text_response(
+                StatusCode::BAD_REQUEST,
+                "CONNECT needs host:port\n".to_string(),
+            );
+        };
+
+        tokio::spawn(async move {
+            let upgraded = match hyper::upgrade::on(req).await {
+                Ok(upgraded) => TokioIo::new(upgraded),
+                Err(e) => {
+                    tracing::warn!("proxy upgrade to {authority}: {e}");
+                    return;
+                }
+            };
+            let result = match self.ca.clone() {
+                Some(ca) => self.intercept(upgraded, authority.clone(), ca).await,
+                None => tunnel(upgraded, &authority).await,
+            };
+            if let Err(e) = result {
+                tracing::warn!("proxy tunnel to {authority}: {e}");
+            }
+        });
+
+        Response::new(Full::new(Bytes::new()))
+    }
+
+    /// Terminates TLS with a certificate for the host and serves the decrypted requests.
+    async fn intercept(
+        self: Arc<Self>,
+        upgraded: TokioIo<Upgraded>,
+        authority: String,
+        ca: Arc<CertificateAuthority>,
+    ) -> Result<(), String> {
+        let host = authority
+            .rsplit_once(':')
+            .map_or(authority.as_str(), |(host, _)| host);
+        let acceptor = TlsAcceptor::from(ca.server_config(host)?);
+        let tls = acceptor
+            .accept(upgraded)
+            .await
+            .map_err(|e| format!("TLS handshake: {e}"))?;
+
+        let origin = authority
+            .strip_suffix(":443")
+            .unwrap_or(&authority)
+            .to_string();
+        let service = service_fn(move |req: Request<Incoming>| {
+            let uri = format!(
+                "https://{origin}{}",
+                req.uri().path_and_query().map_or("/", |p| p.as_str())
+            );
+            let proxy = self.clone();
+            async move { Ok::<_, Infallible>(proxy.forward(req, uri).await) }
+        });
+        http1::Builder::new()
+            .serve_connection(TokioIo::new(tls), service)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Sends the request on to `uri` and reports both sides to the capture channel.
+    async fn forward(&self, req: Request<Incoming>, uri: String) -> Response<Full<Bytes>> {
+        let (parts, body) = req.into_parts();
+        let mut headers = forwarded_headers(&parts.headers);
+        headers.remove(HOST);
+        let body = body
+            .collect()
+            .await
+            .map(|b| b.to_bytes())
+            .unwrap_or_default();
+
+        let mut sent = SentRequest {
+            id: 0,
+            sent_at: chrono::Local::now(),
+            method: parts.method.to_string(),
+            uri: uri.clone(),
+            path: "".to_string(),
+            query: "".to_string(),
+            headers: sqlx::types::Json(header_pairs(&headers)),
+            body: String::from_utf8_lossy(&body).to_string(),
+            status: 0,
+            response_headers: sqlx::types::Json(Vec::new()),
+            response_body: String::new(),
+            wait_ms: 0.0,
+            receive_ms: 0.0,
+            dns_ms: 0.0,
+            connect_ms: 0.0,
+            tls_ms: 0.0,
+        };
+
+        let start = Instant::now();
+        let sent_response = self
+            .client
+            .request(parts.method, &uri)
+            .headers(headers)
+            .body(body)
+            .send()
+            .await;
+        let waited = start.elapsed();
+
+        let received = match sent_response {
+            Ok(upstream) => {
+                let status = upstream.status();
+                let headers = forwarded_headers(upstream.headers());
+                upstream
+                    .bytes()
+                    .await
+                    .map(|bytes| (status, headers, bytes))
+                    .map_err(|e| format!("{uri}: {e}\n"))
+            }
+            Err(e) => Err(format!("{uri}: {e}\n")),
+        };
+        sent.wait_ms = waited.as_secs_f64() * 1000.0;
+        sent.receive_ms = (start.elapsed() - waited).as_secs_f64() * 1000.0;
+
+        let response = match received {
+            Ok((status, headers, bytes)) => {
+                sent.status = status.as_u16() as i64;
+                sent.response_headers = sqlx::types::Json(header_pairs(&headers));
+                sent.response_body = String::from_utf8_lossy(&bytes).to_string();
+                let mut response = Response::new(Full::new(bytes));
+                *response.status_mut() = status;
+                *response.headers_mut() = headers;
+                response
+            }
+            Err(e) => {
+                sent.status = StatusCode::BAD_GATEWAY.as_u16() as i64;
+                sent.response_body = e.clone();
+                text_response(StatusCode::BAD_GATEWAY, e)
+            }
+        };
+
+        let _ = self.captured.send(sent);
+        response
+    }
+}
+
+/// Copies bytes both ways without looking at them.
+async fn tunnel(mut upgraded: TokioIo<Upgraded>, authority: &str) -> Result<(), String> {
+    let mut server = TcpStream::connect(authority)
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::io::copy_bidirectional(&mut upgraded, &mut server)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn key_is_private() {
+        let dir = std::env::temp_dir().join(format!("legrillon-ca-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CA_KEY_FILE);
+
+        assert_eq!(read_key(&path), Ok(None));
+        create_key(&path, "key").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(create_key(&path, "other").is_err());
+        assert_eq!(read_key(&path), Ok(Some("key".to_string())));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(read_key(&path).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use fltk::{
+    app, browser, button, dialog,
+    enums::{self, Event},
+    frame, group, input,
+    prelude::*,
+    window::{self, DoubleWindow},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{
+    AppWindow, GlobalAppMsg, HasId, controls,
+    db::{OpenWindow, SentRequest},
+    next_window_id,
+    proxy::{self, CertificateAuthority},
+};
+
+/// Recording forward proxy, captured calls go to the history and can be replayed.
+pub struct ProxyWindow {
+    id: usize,
+    window: DoubleWindow,
+    server: Rc<RefCell<Option<JoinHandle<()>>>>,
+}
+
+impl ProxyWindow {
+    pub fn new() -> Self {
+        let id = next_window_id();
+
+        let mut win = window::DoubleWindow::default()
+            .with_size(1200, 700)
+            .with_label("Le Grillon - Recording proxy");
+
+        let mut col = group::Flex::default_fill().column();
+        let mut row = group::Flex::default().row();
+        let port_label = frame::Frame::default().with_label("Port:");
+        row.fixed(&port_label, 64);
+        let mut port_input = input::IntInput::default();
+        port_input.set_value(&proxy::DEFAULT_PORT.to_string());
+        row.fixed(&port_input, 96);
+        let mut intercept_toggle = button::CheckButton::default().with_label("Intercept HTTPS");
+        intercept_toggle.set_tooltip(&format!(
+            "Decrypt HTTPS with a local CA, clients have to trust {} (Export CA...)",
+            proxy::CA_CERT_FILE
+        ));
+        row.fixed(&intercept_toggle, 160);
+        let mut start_btn = button::Button::default().with_label("Start");
+        row.fixed(&start_btn, 128);
+        let mut export_btn = button::Button::default().with_label("Export CA...");
+        row.fixed(&export_btn, 128);
+        let mut status = frame::Frame::default();
+        status.set_frame(enums::FrameType::FlatBox);
+        row.end();
+        col.fixed(&row, 32);
+
+        let mut list = browser::HoldBrowser::default();
+        list.set_column_char('\t');
+        list.set_column_widths(&[120, 96, 64]);
+        let mut row = group::Flex::default().row();
+        let mut open_btn = button::Button::default().with_label("Open");
+        row.fixed(&open_btn, 128);
+        let mut clear_btn = button::Button::default().with_label("Clear");
+        row.fixed(&clear_btn, 128);
+        frame::Frame::default();
+        row.end();
+        col.fixed(&row, 32);
+        col.end();
+
+        win.end();
+        win.make_resizable(true);
+        win.show();
+
+        let captured: Arc<Mutex<Vec<SentRequest>>> = Arc::new(Mutex::new(Vec::new()));
+        let (s, _) = app::channel::<GlobalAppMsg>();
+
+        let open = {
+            let list = list.clone();
+            let captured = captured.clone();
+            move || {
+                let line = list.value();
+                if line < 1 {
+                    return;
+                }
+                if let Some(e) = captured.lock().unwrap().get(line as usize - 1) {
+                    s.send(GlobalAppMsg::Restore(OpenWindow::new_from(
+                        &e.method, &e.uri, &e.headers, &e.body, None,
+                    )));
+                }
+            }
+        };
+        let open_ptr = Rc::new(open);
+        let open_cl = open_ptr.clone();
+        list.set_callback(move |_| {
+            if app::event_clicks() {
+                open_cl();
+            }
+        });
+        open_btn.set_callback(move |_| open_ptr());
+
+        let p_captured = captured.clone();
+        let mut p_list = list.clone();
+        clear_btn.set_callback(move |_| {
+            p_captured.lock().unwrap().clear();
+            p_list.clear();
+        });
+
+        export_btn.set_callback(move |_| {
+            let ca = match CertificateAuthority::load_or_create() {
+                Ok(ca) => ca,
+                Err(e) => {
+                    dialog::alert_default(&format!("Could not create the proxy CA:\n{e}"));
+                    return;
+                }
+            };
+            let Some(path) = controls::pick_save_file("*.pem", proxy::CA_CERT_FILE) else {
+                return;
+            };
+            if let Err(e) = std::fs::write(&path, &ca.cert_pem) {
+                dialog::alert_default(&format!("Could not export {}:\n{e}", path.display()));
+            }
+        });
+
+        let server: Rc<RefCell<Option<JoinHandle<()>>>> = Rc::new(RefCell::new(None));
+        let p_server = server.clone();
+        start_btn.set_callback(move |btn| {
+            if let Some(running) = p_server.borrow_mut().take() {
+                running.abort();
+                btn.set_label("Start");
+                status.set_label("Stopped");
+                port_input.activate();
+                intercept_toggle.activate();
+                return;
+            }
+
+            let Ok(port) = port_input.value().parse::<u16>() else {
+                status.set_label("Invalid port");
+                return;
+            };
+            let ca = if intercept_toggle.is_checked() {
+                match CertificateAuthority::load_or_create() {
+                    Ok(ca) => Some(Arc::new(ca)),
+                    Err(e) => {
+                        status.set_label(&e);
+                        return;
+                    }
+                }
+            } else {
+                None
+            };
+
+            let (tx, mut rx) = mpsc::unbounded_channel::<SentRequest>();
+            match proxy::serve(port, ca, tx) {
+                Ok(handle) => {
+                    *p_server.borrow_mut() = Some(handle);
+                    btn.set_label("Stop");
+                    status.set_label(&format!("Proxy on http://127.0.0.1:{port}"));
+                    port_input.deactivate();
+                    intercept_toggle.deactivate();
+
+                    let captured = captured.clone();
+                    let mut list = list.clone();
+                    tokio::spawn(async move {
+                        while let Some(sent) = rx.recv().await {
+                            list.add(&format!(
+                                "{}\t{}\t{}\t{}",
+                                sent.sent_at.format("%H:%M:%S%.3f"),
+                                sent.method,
+                                sent.status,
+                                sent.uri
+                            ));
+                            s.send(GlobalAppMsg::RecordSentRequest(sent.clone()));
+                            captured.lock().unwrap().push(sent);
+                            app::awake();
+                        }
+                    });
+                }
+                Err(e) => status.set_label(&e),
+            }
+        });
+
+        win.handle(move |_, e| {
+            if e == Event::Hide {
+                tracing::debug!("window {id} (ProxyWindow) closed");
+                s.send(GlobalAppMsg::CloseWindow(id));
+                return true;
+            }
+
+            false
+        });
+
+        Self {
+            id,
+            window: win,
+            server,
+        }
+    }
+}
+
+impl HasId for ProxyWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for ProxyWindow {
+    fn close(&mut self) {
+        if let Some(running) = self.server.borrow_mut().take() {
+            running.abort();
+        }
+        self.window.hide();
+    }
+}
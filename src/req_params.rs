@@ -1,16 +1,41 @@
-use std::str::FromStr;
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use fltk::{
-    enums::{self},
+    app, button,
+    enums::{self, Event, Key},
+    frame,
     group::{Flex, Tabs},
+    menu,
     prelude::*,
     text::{self, TextBuffer},
 };
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+
+use crate::{
+    controls,
+    graphql::{self, Schema},
+    vars,
+};
+
+pub const BODY_MODE_OPTION: &str = "body_mode";
+pub const BODY_MODE_GRAPHQL: &str = "graphql";
+const GRAPHQL_QUERY_OPTION: &str = "graphql_query";
+const GRAPHQL_VARIABLES_OPTION: &str = "graphql_variables";
 
 pub struct RequestParamsCtrl {
+    tabs: Tabs,
     headers_buf: TextBuffer,
     body_buf: TextBuffer,
+    graphql_toggle: button::CheckButton,
+    graphql_query_buf: TextBuffer,
+    graphql_variables_buf: TextBuffer,
+    introspect_btn: button::Button,
+    docs_btn: button::Button,
+    schema: Arc<Mutex<Option<Schema>>>,
 }
 
 impl RequestParamsCtrl {
@@ -37,6 +62,31 @@ impl RequestParamsCtrl {
         headers.set_text_font(enums::Font::Courier);
 
         grp2.end();
+
+        let mut grp3 = Flex::default_fill().with_label("GraphQL\t\t").column();
+        let mut bar = Flex::default().row();
+        let graphql_toggle = button::CheckButton::default().with_label("Send as GraphQL");
+        let introspect_btn = button::Button::default().with_label("Introspect");
+        bar.fixed(&introspect_btn, 128);
+        let docs_btn = button::Button::default().with_label("Docs");
+        bar.fixed(&docs_btn, 96);
+        bar.end();
+        grp3.fixed(&bar, 32);
+
+        let graphql_query_buf = text::TextBuffer::default();
+        let mut graphql_query = text::TextEditor::default();
+        graphql_query.set_buffer(graphql_query_buf.clone());
+        graphql_query.set_text_font(enums::Font::Courier);
+        graphql_query.set_tooltip("Ctrl+Space completes fields and arguments");
+        let variables_label = frame::Frame::default().with_label("Variables (JSON)");
+        grp3.fixed(&variables_label, 24);
+        let graphql_variables_buf = text::TextBuffer::default();
+        let mut graphql_variables = text::TextEditor::default();
+        graphql_variables.set_buffer(graphql_variables_buf.clone());
+        graphql_variables.set_text_font(enums::Font::Courier);
+        grp3.fixed(&graphql_variables, 160);
+        grp3.end();
+
         tab.end();
         tab.auto_layout();
 
@@ -66,27 +116,152 @@ impl RequestParamsCtrl {
                 }
             });
 
+        let schema: Arc<Mutex<Option<Schema>>> = Arc::new(Mutex::new(None));
+        let p_schema = schema.clone();
+        let mut p_query_buf = graphql_query_buf.clone();
+        graphql_query.handle(move |ed, ev| {
+            if ev != Event::KeyDown
+                || app::event_key() != Key::from_char(' ')
+                || !app::is_event_ctrl()
+            {
+                return false;
+            }
+
+            let pos = ed.insert_position();
+            let (replace, candidates) = match p_schema.lock().unwrap().as_ref() {
+                Some(schema) => graphql::complete(schema, &p_query_buf.text(), pos as usize),
+                None => return true,
+            };
+            if candidates.is_empty() {
+                return true;
+            }
+
+            // `MenuItem::new` takes static labels, it keeps copies of them for good anyway
+            let labels: Vec<&'static str> = candidates
+                .into_iter()
+                .map(|c| &*Box::leak(c.into_boxed_str()))
+                .collect();
+            let (x, y) = ed.position_to_xy(pos);
+            if let Some(item) = menu::MenuItem::new(&labels).popup(x, y + 16)
+                && let Some(label) = item.label()
+            {
+                let start = pos - replace as i32;
+                p_query_buf.replace(start, pos, &label);
+                ed.set_insert_position(start + label.len() as i32);
+            }
+            true
+        });
+
         Self {
+            tabs: tab,
             headers_buf,
             body_buf,
+            graphql_toggle,
+            graphql_query_buf,
+            graphql_variables_buf,
+            introspect_btn,
+            docs_btn,
+            schema,
         }
     }
 
+    /// The raw body or, in GraphQL mode, the query and variables as JSON envelope.
     pub fn get_body(&self) -> String {
+        if self.is_graphql() {
+            return graphql::envelope(
+                &self.graphql_query_buf.text(),
+                &self.graphql_variables_buf.text(),
+            );
+        }
+
         self.body_buf.text()
     }
 
-    pub fn get_headers(&self) -> HeaderMap {
-        let possibles = self
-            .headers_buf
+    pub fn is_graphql(&self) -> bool {
+        self.graphql_toggle.is_checked()
+    }
+
+    /// State kept in [`crate::db::OpenWindow::options`].
+    pub fn get_options(&self) -> BTreeMap<String, String> {
+        let mut options = BTreeMap::new();
+        if self.is_graphql() {
+            options.insert(BODY_MODE_OPTION.to_string(), BODY_MODE_GRAPHQL.to_string());
+        }
+        let query = self.graphql_query_buf.text();
+        if !query.is_empty() {
+            options.insert(GRAPHQL_QUERY_OPTION.to_string(), query);
+            options.insert(
+                GRAPHQL_VARIABLES_OPTION.to_string(),
+                self.graphql_variables_buf.text(),
+            );
+        }
+
+        options
+    }
+
+    pub fn active_tab(&self) -> i32 {
+        controls::active_tab(&self.tabs)
+    }
+
+    /// Shows the body (0), headers (1) or GraphQL (2) tab.
+    pub fn select_tab(&self, index: i32) {
+        controls::select_tab(&mut self.tabs.clone(), index);
+    }
+
+    pub fn schema(&self) -> Arc<Mutex<Option<Schema>>> {
+        self.schema.clone()
+    }
+
+    pub fn on_introspect(&self, mut cb: impl FnMut() + 'static) {
+        self.introspect_btn.clone().set_callback(move |_| cb());
+    }
+
+    pub fn on_docs(&self, mut cb: impl FnMut() + 'static) {
+        self.docs_btn.clone().set_callback(move |_| cb());
+    }
+
+    /// Calls `cb` whenever the body, the headers or the GraphQL fields are edited.
+    pub fn on_change(&self, cb: impl Fn() + Clone + 'static) {
+        for buf in [
+            &self.body_buf,
+            &self.headers_buf,
+            &self.graphql_query_buf,
+            &self.graphql_variables_buf,
+        ] {
+            let cb = cb.clone();
+            buf.clone().add_modify_callback(move |_, i, d, _, _| {
+                if i > 0 || d > 0 {
+                    cb();
+                }
+            });
+        }
+        self.graphql_toggle.clone().set_callback(move |_| cb());
+    }
+
+    pub fn get_header_pairs(&self) -> Vec<(String, String)> {
+        self.headers_buf
             .text()
             .lines()
             .filter_map(|l| l.split_once(':'))
-            .map(|(n, v)| (HeaderName::from_str(n), HeaderValue::from_str(v)))
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    /// Parsed headers with `{{variables}}` substituted, invalid lines are skipped.
+    pub fn get_headers(&self, vars: &[(String, String)]) -> HeaderMap {
+        let mut possibles = self
+            .get_header_pairs()
+            .iter()
+            .map(|(n, v)| (vars::substitute(n, vars), vars::substitute(v, vars)))
+            .map(|(n, v)| (HeaderName::from_str(&n), HeaderValue::from_str(&v)))
             .filter(|(n, v)| n.is_ok() && v.is_ok())
             .map(|(n, v)| (n.unwrap(), v.unwrap()))
             .collect::<HeaderMap>();
 
+        if self.is_graphql() && !possibles.contains_key(CONTENT_TYPE) {
+            possibles.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        }
+
         possibles
     }
 
@@ -96,5 +271,17 @@ impl RequestParamsCtrl {
         for (n, v) in wnd.headers.0.iter() {
             self.headers_buf.append(format!("{n}:{v}\n").as_str());
         }
+
+        let options = &wnd.options.0;
+        self.graphql_toggle.set_checked(
+            options.get(BODY_MODE_OPTION).map(String::as_str) == Some(BODY_MODE_GRAPHQL),
+        );
+        if let Some(query) = options.get(GRAPHQL_QUERY_OPTION) {
+            self.graphql_query_buf.set_text(query);
+        }
+        if let Some(variables) = options.get(GRAPHQL_VARIABLES_OPTION) {
+            self.graphql_variables_buf.set_text(variables);
+        }
+        controls::select_tab(&mut self.tabs, wnd.layout.params_tab);
     }
 }
@@ -1,24 +1,52 @@
-use std::{rc::Rc, str::FromStr};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 
 use fltk::{
-    app, button,
-    enums::{self, Event},
-    frame, group, input,
+    app, button, dialog, enums, frame, group, input,
+    menu::{self, Choice, MenuButton},
+    misc::InputChoice,
     prelude::*,
     text::{self},
-    window::{self, DoubleWindow},
 };
-use reqwest::Method;
-
-use crate::{db::OpenWindow, next_window_id, req_params::RequestParamsCtrl, AppWindow, GlobalAppMsg, HasId};
+use reqwest::{
+    Method,
+    header::{CONTENT_LENGTH, HeaderMap},
+};
+use tokio::sync::oneshot;
+
+use crate::{
+    AppWindow, GlobalAppMsg, HasId,
+    autosave::Autosave,
+    bench, controls,
+    db::{CollectionRequest, Environment, OpenWindow, SentRequest, WINDOW_KIND_HTTP, WindowLayout},
+    find_bar::FindBarCtrl,
+    graphql, guard,
+    host::Host,
+    json_tree::JsonTreeCtrl,
+    next_window_id,
+    req_params::{BODY_MODE_GRAPHQL, BODY_MODE_OPTION, RequestParamsCtrl},
+    secrets,
+    shortcuts::Action,
+    sse,
+    timing::{self, Timings},
+    vars::{self, VariableScopes},
+};
 
 pub struct RequestWindow {
-    uri: String,
     param_ctrl: Rc<RequestParamsCtrl>,
-    method: reqwest::Method,
-    global: app::Sender<GlobalAppMsg>,
     id: usize,
-    window: DoubleWindow,
+    host: Host,
+    env_choice: Choice,
+    environment_id: Rc<Cell<Option<i64>>>,
+    scopes: Rc<RefCell<VariableScopes>>,
+    last_response: Arc<Mutex<Option<SentRequest>>>,
+    state: Rc<dyn Fn() -> OpenWindow>,
+    autosave: Autosave,
+    actions: Rc<dyn Fn(Action)>,
+    update_title: Rc<dyn Fn(bool)>,
 }
 
 const UNIT: f64 = 1000.0;
@@ -42,28 +70,256 @@ pub fn human_bytes<T: Into<f64>>(bytes: T) -> String {
     }
 }
 
+/// Fills the environment selector, index 0 is "no environment".
+fn fill_environments(choice: &mut Choice, scopes: &VariableScopes, selected: Option<i64>) {
+    choice.clear();
+    choice.add_choice("No environment");
+    choice.set_value(0);
+    for (i, env) in scopes.environments.iter().enumerate() {
+        choice.add_choice(&env.name.replace('/', "\\/").replace('|', "\\|"));
+        if Some(env.id) == selected {
+            choice.set_value(i as i32 + 1);
+        }
+    }
+}
+
+/// Offered by the method field, any other method can be typed in, e.g. PROPFIND or PURGE.
+const METHODS: [&str; 9] = [
+    "GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "TRACE", "CONNECT",
+];
+
+const METHOD_TOOLTIP: &str = "Pick a method or type a custom one";
+
+/// Methods are case-sensitive, but custom ones are upper case by convention, so typing
+/// `propfind` sends PROPFIND. An empty field means GET.
+fn parse_method(text: &str) -> Result<Method, String> {
+    let text = text.trim().to_ascii_uppercase();
+    if text.is_empty() {
+        return Ok(Method::GET);
+    }
+    Method::from_bytes(text.as_bytes()).map_err(|_| format!("{text:?} is not a valid HTTP method"))
+}
+
+fn selected_method(choice: &InputChoice) -> Result<Method, String> {
+    parse_method(&choice.value().unwrap_or_default())
+}
+
+/// What to know about sending `method`, mostly bodies the method gives no meaning to.
+fn method_hint(method: &Method, has_body: bool) -> Option<String> {
+    match (method.as_str(), has_body) {
+        ("TRACE", true) => Some("TRACE requests must not have a body".to_string()),
+        ("GET" | "HEAD" | "OPTIONS" | "DELETE" | "CONNECT", true) => Some(format!(
+            "{method} with a body, many servers and proxies ignore or reject it"
+        )),
+        ("HEAD", false) => Some("HEAD only returns the response headers".to_string()),
+        ("CONNECT", false) => {
+            Some("CONNECT asks for a tunnel to the host and port of the URI".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The window's name or else its URI. Environments with a colour label or in read-only
+/// mode are named in front, so a window aimed at production says so in its title.
+fn window_title(name: &str, uri: &str, environment: Option<&Environment>) -> String {
+    let title = [name, uri]
+        .into_iter()
+        .map(str::trim)
+        .find(|t| !t.is_empty())
+        .unwrap_or("Le Grillon");
+    match environment {
+        Some(env) if env.read_only => format!("[{} read-only] {title}", env.name),
+        Some(env) if !env.color.is_empty() => format!("[{}] {title}", env.name),
+        _ => title.to_string(),
+    }
+}
+
+/// Checks a request that may change data: read-only environments refuse it and protected
+/// hosts ask first. `Err` tells why it was not sent.
+fn confirm_send(verb: &Method, uri: &str, environment: Option<&Environment>) -> Result<(), String> {
+    if guard::is_safe(verb) {
+        return Ok(());
+    }
+    if let Some(env) = environment.filter(|e| e.read_only) {
+        return Err(format!(
+            "NOT SENT | {verb} is blocked, the environment '{}' is read-only",
+            env.name
+        ));
+    }
+    if let Some(host) = guard::protected_host(uri) {
+        let text = format!("{host} is a protected host, send this request anyway?\n\n{verb} {uri}");
+        if dialog::choice2_default(&text, "Cancel", "Send", "") != Some(1) {
+            return Err(format!(
+                "NOT SENT | {verb} to the protected host {host} was cancelled"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Refuses a request holding a secret that is still encrypted.
+fn check_secrets(uri: &str, body: &str, headers: &HeaderMap) -> Result<(), String> {
+    let values = headers.values().filter_map(|v| v.to_str().ok());
+    secrets::check_sendable([uri, body].into_iter().chain(values))
+}
+
+fn label_color(environment: Option<&Environment>) -> Option<enums::Color> {
+    environment.and_then(|e| enums::Color::from_hex_str(&e.color).ok())
+}
+
+/// Black or white, whichever reads better on `background`.
+fn text_color(background: enums::Color) -> enums::Color {
+    let (r, g, b) = background.to_rgb();
+    if 299 * r as u32 + 587 * g as u32 + 114 * b as u32 > 128_000 {
+        enums::Color::Black
+    } else {
+        enums::Color::White
+    }
+}
+
+/// Asks for the colour label of `env`, `None` when cancelled and an empty string to
+/// remove the label.
+fn ask_environment_color(env: &Environment) -> Option<String> {
+    let text = format!("Colour label of the environment '{}':", env.name);
+    match dialog::choice2_default(&text, "Cancel", "Pick...", "None")? {
+        1 => dialog::color_chooser("Environment colour", dialog::ColorMode::Byte)
+            .map(|(r, g, b)| enums::Color::from_rgb(r, g, b).to_hex_str()),
+        2 => Some(String::new()),
+        _ => None,
+    }
+}
+
+/// Asks which variable of the environment holds a secret, or does not although its name
+/// suggests it.
+fn ask_variable_secret(env: &Environment) -> Option<(String, bool)> {
+    let text = format!("Variable of the environment '{}':", env.name);
+    let name = dialog::input_default(&text, "")?.trim().to_string();
+    if !env.variables.0.iter().any(|(n, _)| *n == name) {
+        dialog::alert_default(&format!("There is no variable '{name}' in '{}'.", env.name));
+        return None;
+    }
+    let text = format!("Is '{name}' a secret? Secrets are encrypted and left out of exports.");
+    match dialog::choice2_default(&text, "Cancel", "Secret", "Not secret")? {
+        1 => Some((name, true)),
+        2 => Some((name, false)),
+        _ => None,
+    }
+}
+
+/// Response headers one per line, shown in place of the body when there is none.
+fn headers_text(headers: &HeaderMap) -> String {
+    header_pairs(headers)
+        .iter()
+        .map(|(n, v)| format!("{n}: {v}\n"))
+        .collect()
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(n, v)| {
+            (
+                n.to_string(),
+                String::from_utf8_lossy(v.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Gives the request parameters `ratio` of the width, 0 means half.
+fn set_split(split: &mut group::Tile, ratio: f64) {
+    let ratio = if ratio > 0.0 {
+        ratio.clamp(0.1, 0.9)
+    } else {
+        0.5
+    };
+    let params_w = (split.w() as f64 * ratio) as i32;
+    if let (Some(mut params), Some(mut result)) = (split.child(0), split.child(1)) {
+        params.resize(split.x(), split.y(), params_w, split.h());
+        result.resize(
+            split.x() + params_w,
+            split.y(),
+            split.w() - params_w,
+            split.h(),
+        );
+    }
+    split.redraw();
+}
+
+fn split_ratio(split: &group::Tile) -> f64 {
+    split
+        .child(0)
+        .map_or(0.5, |params| params.w() as f64 / split.w().max(1) as f64)
+}
+
+/// Asks for a collection and a request name and saves the window's request there.
+fn save_to_collection(state: OpenWindow, sender: app::Sender<GlobalAppMsg>) {
+    let Some(collection) =
+        dialog::input_default("Save to collection:", "").filter(|c| !c.trim().is_empty())
+    else {
+        return;
+    };
+    let preset = format!("{} {}", state.method, state.uri);
+    let Some(name) =
+        dialog::input_default("Request name:", &preset).filter(|n| !n.trim().is_empty())
+    else {
+        return;
+    };
+    sender.send(GlobalAppMsg::SaveToCollection(
+        collection.trim().to_string(),
+        CollectionRequest {
+            id: 0,
+            collection_id: 0,
+            folder: String::new(),
+            name: name.trim().to_string(),
+            method: state.method,
+            uri: state.uri,
+            headers: state.headers,
+            body: state.body,
+        },
+    ));
+}
+
+fn set_timings(sent: &mut SentRequest, timings: &Timings) {
+    sent.dns_ms = timings.dns.as_secs_f64() * 1000.0;
+    sent.connect_ms = timings.connect.as_secs_f64() * 1000.0;
+    sent.tls_ms = timings.tls.as_secs_f64() * 1000.0;
+    sent.wait_ms = timings.ttfb.as_secs_f64() * 1000.0;
+    sent.receive_ms = timings.download.as_secs_f64() * 1000.0;
+}
+
 impl RequestWindow {
-    pub fn new(wnd: Option<&OpenWindow>) -> Self {
+    pub fn new(
+        wnd: Option<&OpenWindow>,
+        scopes: Rc<RefCell<VariableScopes>>,
+        tabs: Option<&group::Tabs>,
+    ) -> Self {
         let id = wnd.map_or(next_window_id(), |f| f.id as usize);
+        let environment_id = Rc::new(Cell::new(wnd.and_then(|w| w.environment_id)));
+        let name = Rc::new(RefCell::new(
+            wnd.map(|w| w.name.clone()).unwrap_or_default(),
+        ));
+        let collection_id = wnd.and_then(|w| w.collection_id);
 
-        let mut win = window::DoubleWindow::default()
-            .with_size(1200, 800)
-            .with_label("Le Grillon");
+        let mut host = Host::new(tabs, "Le Grillon", wnd.map(|w| &w.layout.0));
 
         let result_buf = text::TextBuffer::default();
 
         let mut col = group::Flex::default_fill().column();
         let mut row = group::Flex::default().row();
-        let mut verb_choice = fltk::menu::Choice::default();
-        verb_choice.add_choice("GET");
-        verb_choice.add_choice("POST");
-        verb_choice.add_choice("PUT");
-        verb_choice.add_choice("PATCH");
-        verb_choice.add_choice("DELETE");
-        verb_choice.set_value(0);
+        let mut verb_choice = InputChoice::default();
+        for verb in METHODS {
+            verb_choice.add(verb);
+        }
+        verb_choice.set_value("GET");
+        verb_choice.set_tooltip(METHOD_TOOLTIP);
 
         row.fixed(&verb_choice, 196);
 
+        let mut env_choice = Choice::default();
+        fill_environments(&mut env_choice, &scopes.borrow(), environment_id.get());
+        row.fixed(&env_choice, 196);
+
         let uri_label = frame::Frame::default().with_label("URI:");
 
         row.fixed(&uri_label, 64);
@@ -73,12 +329,32 @@ impl RequestWindow {
 
         runbtn.set_compact(true);
         row.fixed(&runbtn, 64);
+        let mut stopbtn = button::Button::default().with_label("Stop");
+        stopbtn.set_tooltip("Stop following an event stream");
+        stopbtn.deactivate();
+        row.fixed(&stopbtn, 64);
+        let mut benchbtn = button::Button::default().with_label("Load test");
+        benchbtn.set_tooltip("Repeat this request concurrently and measure latency");
+        row.fixed(&benchbtn, 96);
+        let mut diffbtn = button::Button::default().with_label("Diff");
+        diffbtn.set_tooltip("Compare the last response with the one of another window");
+        row.fixed(&diffbtn, 64);
+        let mut window_menu = MenuButton::default().with_label("☰");
+        window_menu.set_tooltip("Duplicate, rename or save this window, label its environment");
+        row.fixed(&window_menu, 48);
         row.end();
         col.fixed(&row, 32);
-        let row = group::Flex::default_fill().row();
+        // request parameters and response side by side, the border can be dragged
+        let mut split = group::Tile::default_fill();
 
         let mut req_params = RequestParamsCtrl::new();
 
+        let last_response: Arc<Mutex<Option<SentRequest>>> = Arc::new(Mutex::new(None));
+
+        let mut result_tabs = group::Tabs::default_fill();
+        let mut grp = group::Flex::default_fill()
+            .with_label("Response\t\t")
+            .column();
         let mut result = text::TextDisplay::default();
 
         result.set_linenumber_width(12 * 3);
@@ -87,38 +363,58 @@ impl RequestWindow {
 
         result.set_text_font(enums::Font::Courier);
         result.wrap_mode(text::WrapMode::AtBounds, 4);
-        row.end();
+        let find_bar = FindBarCtrl::new(&mut grp, &result, last_response.clone());
+        grp.end();
+
+        let json_tree = JsonTreeCtrl::new();
+
+        let grp = group::Flex::default_fill().with_label("Timing\t\t").row();
+        let timing_buf = text::TextBuffer::default();
+        let mut timing_display = text::TextDisplay::default();
+        timing_display.set_buffer(timing_buf.clone());
+        timing_display.set_text_font(enums::Font::Courier);
+        grp.end();
+        result_tabs.end();
+        result_tabs.auto_layout();
+        split.end();
         let mut status = frame::Frame::default();
         status.set_frame(enums::FrameType::FlatBox);
         col.fixed(&status, 32);
         col.end();
 
-        win.end();
-        win.make_resizable(true);
-        win.show();
+        host.show();
+        set_split(&mut split, wnd.map_or(0.0, |w| w.layout.split));
 
-        if let Some(wnd) = wnd{
+        if let Some(wnd) = wnd {
             uri_input.set_value(wnd.uri.as_str());
-            win.set_label(wnd.uri.as_str());
-            if let Some(item) = verb_choice.find_item(wnd.method.as_str()){
-                verb_choice.set_item(&item);
-            }
+            verb_choice.set_value(&wnd.method);
             req_params.set(wnd);
+            controls::select_tab(&mut result_tabs, wnd.layout.result_tab);
+
+            if let Some(sent) = &wnd.response {
+                result_buf.clone().set_text(&sent.response_body);
+                json_tree.set_body(&sent.response_body);
+                timing_buf
+                    .clone()
+                    .set_text(&Timings::from(&sent.0).waterfall());
+                status.set_label(&format!(
+                    "STATUS={} | RESTORED FROM {}",
+                    sent.status,
+                    sent.sent_at.format("%Y-%m-%d %H:%M:%S")
+                ));
+                *last_response.lock().unwrap() = Some(sent.0.clone());
+            }
         }
 
-        
-
-
         let (s, _) = app::channel();
-        let p_sender = s.clone();
-        win.handle(move |_, e| {
-            if e == Event::Hide {
-                println!("HANDLE WINDOW EVENT| (RequestWindow) | WINID={id}| {e:?}");
-                p_sender.send(GlobalAppMsg::CloseWindow(id));
-                return true;
-            }
+        host.on_events(id, "RequestWindow");
 
-            false
+        let stop: Rc<RefCell<Option<oneshot::Sender<()>>>> = Rc::new(RefCell::new(None));
+        let p_stop = stop.clone();
+        stopbtn.set_callback(move |_| {
+            if let Some(tx) = p_stop.borrow_mut().take() {
+                let _ = tx.send(());
+            }
         });
 
         let btn_ptr = runbtn.clone();
@@ -128,81 +424,502 @@ impl RequestWindow {
         let params_ptr = Rc::new(req_params);
         let params_ptr_run_cl = params_ptr.clone();
 
-        let p_sender = s.clone();
-        let p_win = win.clone();
-        runbtn.set_callback(move |_| {
-            let uri = uri_input.value();
+        let state = {
+            let (verb_choice, uri_input, params) =
+                (verb_choice.clone(), uri_input.clone(), params_ptr.clone());
+            let (environment_id, host, last_response, name) = (
+                environment_id.clone(),
+                host.clone(),
+                last_response.clone(),
+                name.clone(),
+            );
+            move || OpenWindow {
+                id: id as i32,
+                method: verb_choice
+                    .value()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_ascii_uppercase(),
+                uri: uri_input.value(),
+                body: params.get_body(),
+                path: "".to_string(),
+                query: "".to_string(),
+                headers: sqlx::types::Json(params.get_header_pairs()),
+                environment_id: environment_id.get(),
+                collection_id,
+                kind: WINDOW_KIND_HTTP.to_string(),
+                options: sqlx::types::Json(params.get_options()),
+                layout: sqlx::types::Json(WindowLayout {
+                    split: split_ratio(&split),
+                    params_tab: params.active_tab(),
+                    result_tab: controls::active_tab(&result_tabs),
+                    ..host.layout()
+                }),
+                response: last_response.lock().unwrap().clone().map(sqlx::types::Json),
+                name: name.borrow().clone(),
+            }
+        };
+        let state: Rc<dyn Fn() -> OpenWindow> = Rc::new(state);
+
+        let autosave = {
+            let state = state.clone();
+            // the response is saved when it arrives and on quit, not with every edit
+            Autosave::new(Rc::new(move || {
+                s.send(GlobalAppMsg::SaveWindowState(OpenWindow {
+                    response: None,
+                    ..state()
+                }))
+            }))
+        };
+
+        // unnamed windows show the URI last sent, `sent` is true when sending; the status bar
+        // takes the colour of the environment
+        let update_title = {
+            let (host, name, uri_input) = (host.clone(), name.clone(), uri_input.clone());
+            let (scopes, environment_id, status) =
+                (scopes.clone(), environment_id.clone(), status.clone());
+            let sent_uri = RefCell::new(wnd.map(|w| w.uri.clone()).unwrap_or_default());
+            move |sent: bool| {
+                if sent {
+                    *sent_uri.borrow_mut() = uri_input.value();
+                }
+                let (mut host, mut status) = (host.clone(), status.clone());
+                let scopes = scopes.borrow();
+                let env = environment_id.get().and_then(|id| scopes.environment(id));
+                host.set_label(&window_title(&name.borrow(), &sent_uri.borrow(), env));
+                let color = label_color(env);
+                host.set_label_color(color);
+                status.set_color(color.unwrap_or(enums::Color::Background));
+                status.set_label_color(color.map_or(enums::Color::Foreground, text_color));
+                status.redraw();
+            }
+        };
+        let update_title: Rc<dyn Fn(bool)> = Rc::new(update_title);
+        update_title(false);
+        // the method field explains itself, a body edit can change what it has to say
+        let update_hint = {
+            let (verb_choice, params) = (verb_choice.clone(), params_ptr.clone());
+            move || {
+                let hint = selected_method(&verb_choice)
+                    .map(|m| method_hint(&m, !params.get_body().trim().is_empty()))
+                    .unwrap_or_else(Some);
+                verb_choice
+                    .clone()
+                    .set_tooltip(hint.as_deref().unwrap_or(METHOD_TOOLTIP));
+                hint
+            }
+        };
+        update_hint();
+        let (p_autosave, p_update_hint) = (autosave.clone(), update_hint.clone());
+        params_ptr.on_change(move || {
+            p_update_hint();
+            p_autosave.touch();
+        });
+        let p_autosave = autosave.clone();
+        uri_input.set_trigger(enums::CallbackTrigger::Changed);
+        uri_input.set_callback(move |_| p_autosave.touch());
+        let p_autosave = autosave.clone();
+        let mut p_status = status.clone();
+        verb_choice.set_trigger(enums::CallbackTrigger::Changed);
+        verb_choice.set_callback(move |_| {
+            if let Some(hint) = update_hint() {
+                p_status.set_label(&hint);
+            }
+            p_autosave.touch();
+        });
 
-            win.set_label(uri.clone().as_str());
+        let actions = {
+            let (run_btn, uri_input, params, state) = (
+                runbtn.clone(),
+                uri_input.clone(),
+                params_ptr.clone(),
+                state.clone(),
+            );
+            let (name, update_title, autosave) =
+                (name.clone(), update_title.clone(), autosave.clone());
+            let (scopes, environment_id, status) =
+                (scopes.clone(), environment_id.clone(), status.clone());
+            move |action: Action| match action {
+                Action::Send if run_btn.active() => run_btn.clone().do_callback(),
+                Action::FocusUri => {
+                    let _ = uri_input.clone().take_focus();
+                }
+                Action::BodyTab => params.select_tab(0),
+                Action::HeadersTab => params.select_tab(1),
+                Action::Duplicate => s.send(GlobalAppMsg::Restore(state().duplicate())),
+                Action::SaveToCollection => save_to_collection(state(), s),
+                Action::Rename => {
+                    let current = name.borrow().clone();
+                    if let Some(new_name) =
+                        dialog::input_default("Window name, empty shows the URI:", &current)
+                    {
+                        *name.borrow_mut() = new_name.trim().to_string();
+                        update_title(false);
+                        autosave.touch();
+                    }
+                }
+                Action::EnvironmentColor | Action::ToggleReadOnly | Action::SecretVariable => {
+                    let env = environment_id
+                        .get()
+                        .and_then(|id| scopes.borrow().environment(id).cloned());
+                    let Some(env) = env else {
+                        status.clone().set_label("Pick an environment first");
+                        return;
+                    };
+                    if action == Action::ToggleReadOnly {
+                        s.send(GlobalAppMsg::SetEnvironmentReadOnly(env.id, !env.read_only));
+                    } else if action == Action::SecretVariable {
+                        if let Some((name, secret)) = ask_variable_secret(&env) {
+                            s.send(GlobalAppMsg::SetVariableSecret(env.id, name, secret));
+                        }
+                    } else if let Some(color) = ask_environment_color(&env) {
+                        s.send(GlobalAppMsg::SetEnvironmentColor(env.id, color));
+                    }
+                }
+                _ => (),
+            }
+        };
+        let actions: Rc<dyn Fn(Action)> = Rc::new(actions);
+        for action in [
+            Action::Duplicate,
+            Action::Rename,
+            Action::EnvironmentColor,
+            Action::ToggleReadOnly,
+            Action::SecretVariable,
+            Action::SaveToCollection,
+        ] {
+            let actions = actions.clone();
+            window_menu.add(
+                action.label(),
+                enums::Shortcut::None,
+                menu::MenuFlag::Normal,
+                move |_| actions(action),
+            );
+        }
 
-            let body = params_ptr_run_cl.get_body();
-            let headers = params_ptr_run_cl.get_headers();
+        let p_scopes = scopes.clone();
+        let p_environment_id = environment_id.clone();
+        let p_autosave = autosave.clone();
+        let p_update_title = update_title.clone();
+        env_choice.set_callback(move |c| {
+            let idx = c.value();
+            let env_id = if idx > 0 {
+                p_scopes
+                    .borrow()
+                    .environments
+                    .get(idx as usize - 1)
+                    .map(|e| e.id)
+            } else {
+                None
+            };
+            p_environment_id.set(env_id);
+            p_update_title(false);
+            p_autosave.touch();
+        });
 
-            let verb =
-                reqwest::Method::from_str(ptr_verb.choice().unwrap_or("GET".to_string()).as_str())
-                    .unwrap_or(Method::GET);
+        if let Some(wnd) = wnd
+            && wnd.options.0.get(BODY_MODE_OPTION).map(String::as_str) == Some(BODY_MODE_GRAPHQL)
+        {
+            s.send(GlobalAppMsg::LoadGraphQlSchema(id, wnd.uri.clone(), false));
+        }
 
-            
+        let p_params = params_ptr.clone();
+        let p_uri = uri_input.clone();
+        let p_scopes = scopes.clone();
+        let p_environment_id = environment_id.clone();
+        let p_status = status.clone();
+        let p_sender = s;
+        params_ptr.on_introspect(move || {
+            let uri_template = p_uri.value();
+            let vars = p_scopes
+                .borrow()
+                .resolve(p_environment_id.get(), collection_id);
+            let uri = vars::substitute(&uri_template, &vars);
+            let headers = p_params.get_headers(&vars);
+            let schema = p_params.schema();
+            let mut status = p_status.clone();
+            if let Err(e) = check_secrets(&uri, "", &headers) {
+                status.set_label(&e);
+                return;
+            }
+            status.set_label("Fetching GraphQL schema...");
+
+            tokio::spawn(async move {
+                match graphql::introspect(&uri, headers).await {
+                    Ok((json, introspected)) => {
+                        status.set_label(
+                            format!("SCHEMA | TYPES={}", introspected.types.len()).as_str(),
+                        );
+                        *schema.lock().unwrap() = Some(introspected);
+                        p_sender.send(GlobalAppMsg::SaveGraphQlSchema(uri_template, json));
+                    }
+                    Err(e) => status.set_label(format!("SCHEMA | {e}").as_str()),
+                }
+                app::awake();
+            });
+        });
+
+        let p_params = params_ptr.clone();
+        let p_uri = uri_input.clone();
+        let p_sender = s;
+        params_ptr.on_docs(move || {
+            let schema = p_params.schema().lock().unwrap().clone();
+            match schema {
+                Some(schema) => p_sender.send(GlobalAppMsg::ShowGraphQlDocs(schema)),
+                None => p_sender.send(GlobalAppMsg::LoadGraphQlSchema(id, p_uri.value(), true)),
+            }
+        });
+
+        let p_params = params_ptr.clone();
+        let p_uri = uri_input.clone();
+        let p_verb = verb_choice.clone();
+        let p_scopes = scopes.clone();
+        let p_environment_id = environment_id.clone();
+        let p_sender = s;
+        let mut p_status = status.clone();
+        benchbtn.set_callback(move |_| {
+            let method = match selected_method(&p_verb) {
+                Ok(method) => method,
+                Err(e) => {
+                    p_status.set_label(&e);
+                    return;
+                }
+            };
+            let vars = p_scopes
+                .borrow()
+                .resolve(p_environment_id.get(), collection_id);
+            let uri = vars::substitute(&p_uri.value(), &vars);
+            let headers = p_params.get_headers(&vars);
+            let body = vars::substitute(&p_params.get_body(), &vars);
+            // a load test repeats the request, so it is checked like a single send
+            let environment = p_environment_id
+                .get()
+                .and_then(|id| p_scopes.borrow().environment(id).cloned());
+            if let Err(e) = check_secrets(&uri, &body, &headers)
+                .and_then(|_| confirm_send(&method, &uri, environment.as_ref()))
+            {
+                p_status.set_label(&e);
+                return;
+            }
+            p_sender.send(GlobalAppMsg::OpenBench(bench::Target {
+                method,
+                uri,
+                headers,
+                body,
+            }));
+        });
+
+        let p_sender = s;
+        diffbtn.set_callback(move |_| p_sender.send(GlobalAppMsg::DiffResponses(id)));
+
+        let p_last_response = last_response.clone();
+        let p_sender = s;
+        let p_host = host.clone();
+        let p_state = state.clone();
+        let p_autosave = autosave.clone();
+        let p_scopes = scopes.clone();
+        let p_environment_id = environment_id.clone();
+        let p_update_title = update_title.clone();
+        runbtn.set_callback(move |_| {
+            let verb = match selected_method(&ptr_verb) {
+                Ok(verb) => verb,
+                Err(e) => {
+                    status.set_label(&e);
+                    return;
+                }
+            };
+            let uri_template = uri_input.value();
+
+            p_update_title(true);
+
+            let vars = p_scopes
+                .borrow()
+                .resolve(p_environment_id.get(), collection_id);
+            let flags = p_scopes
+                .borrow()
+                .secret_flags(p_environment_id.get(), collection_id);
+            let uri = vars::substitute(&uri_template, &vars);
+            let body_template = params_ptr_run_cl.get_body();
+            let body = vars::substitute(&body_template, &vars);
+            let headers = params_ptr_run_cl.get_headers(&vars);
+
+            let environment = p_environment_id
+                .get()
+                .and_then(|id| p_scopes.borrow().environment(id).cloned());
+            if let Err(e) = check_secrets(&uri, &body, &headers)
+                .and_then(|_| confirm_send(&verb, &uri, environment.as_ref()))
+            {
+                status.set_label(&e);
+                return;
+            }
+
+            // neither has a body to read, HEAD answers with headers only and a CONNECT
+            // response turns the connection into a tunnel
+            let no_body = verb == Method::HEAD || verb == Method::CONNECT;
 
             let mut result = result_buf.clone();
+            let mut timing_buf = timing_buf.clone();
             let inner_btn_ptr = btn_ptr.clone();
             let mut inner_status_ptr = status.clone();
             let mut ptr_result_text = ptr_result_text.clone();
+            let mut inner_stop_ptr = stopbtn.clone();
+            let (stop_tx, stop_rx) = oneshot::channel();
+            *stop.borrow_mut() = Some(stop_tx);
             btn_ptr.clone().deactivate();
 
-            status.set_label(format!("Sending {verb} request...").as_str());
-
-            let save_window = GlobalAppMsg::SaveWindowState(OpenWindow {
-id: id as i32,
-method: verb.to_string(),
-uri: uri.clone(),
-body: body.clone(),
-path: "".to_string(),
-query: "".to_string(),
-headers: sqlx::types::Json(headers.iter().map(|f| (f.0.to_string(), f.1.to_str().unwrap().to_string())).collect())
-            });
+            match method_hint(&verb, !body.trim().is_empty()) {
+                Some(hint) => status.set_label(&format!("Sending {verb} request... ({hint})")),
+                None => status.set_label(format!("Sending {verb} request...").as_str()),
+            }
 
-            p_sender.send(save_window);
+            p_autosave.cancel();
+            p_sender.send(GlobalAppMsg::SaveWindowState(p_state()));
+
+            let mut sent = SentRequest {
+                id: 0,
+                sent_at: chrono::Local::now(),
+                method: verb.to_string(),
+                uri: uri.clone(),
+                path: "".to_string(),
+                query: "".to_string(),
+                headers: sqlx::types::Json(header_pairs(&headers)),
+                body: body.clone(),
+                status: 0,
+                response_headers: sqlx::types::Json(Vec::new()),
+                response_body: String::new(),
+                wait_ms: 0.0,
+                receive_ms: 0.0,
+                dns_ms: 0.0,
+                connect_ms: 0.0,
+                tls_ms: 0.0,
+            };
+            // the secrets substituted into the request are not kept
+            let redact = move |sent: &SentRequest| secrets::redact_sent(sent, &vars, &flags);
+            let record_sender = p_sender;
+            let last_response = p_last_response.clone();
+            let find_bar = find_bar.clone();
+            let json_tree = json_tree.clone();
 
             tokio::spawn(async move {
                 let client = reqwest::Client::new();
-                let start = std::time::Instant::now();                
-                let req_builder = client.request(verb, uri).body(body).headers(headers);
-                match req_builder.send().await {
-                    Ok(resp) => {
+                let reconnect = {
+                    let (client, verb, uri, headers, body) = (
+                        client.clone(),
+                        verb.clone(),
+                        uri.clone(),
+                        headers.clone(),
+                        body.clone(),
+                    );
+                    move |last_event_id: Option<&str>| {
+                        let builder = client
+                            .request(verb.clone(), uri.clone())
+                            .body(body.clone())
+                            .headers(headers.clone());
+                        match last_event_id {
+                            Some(id) => builder.header("Last-Event-ID", id),
+                            None => builder,
+                        }
+                    }
+                };
+                let sent_result = timing::send(verb, &uri, headers, body).await;
+                find_bar.clear();
+                match sent_result {
+                    Ok((resp, mut timings)) if sse::is_event_stream(resp.headers()) => {
+                        let stream_start = std::time::Instant::now();
+                        timing_buf.set_text(&timings.waterfall());
+                        sent.status = resp.status().as_u16() as i64;
+                        sent.response_headers = sqlx::types::Json(header_pairs(resp.headers()));
+                        result.set_text("");
+                        json_tree.set_body("");
+                        inner_stop_ptr.activate();
+
+                        let mut events_buf = result.clone();
+                        let mut events_text = String::new();
+                        let mut stream_status = inner_status_ptr.clone();
+                        sse::follow(
+                            resp,
+                            reconnect,
+                            |event| {
+                                let text = event.display();
+                                events_buf.append(&text);
+                                events_text.push_str(&text);
+                                app::awake();
+                            },
+                            |s| {
+                                stream_status.set_label(s);
+                                app::awake();
+                            },
+                            stop_rx,
+                        )
+                        .await;
+
+                        sent.response_body = events_text;
+                        timings.download = stream_start.elapsed();
+                        set_timings(&mut sent, &timings);
+                        let sent = redact(&sent);
+                        *last_response.lock().unwrap() = Some(sent.clone());
+                        record_sender
+                            .send(GlobalAppMsg::SaveWindowResponse(id as i32, sent.clone()));
+                        record_sender.send(GlobalAppMsg::RecordSentRequest(sent));
+                        inner_stop_ptr.deactivate();
+                    }
+                    Ok((resp, mut timings)) => {
                         // set result
                         let resp_time = std::time::Instant::now();
                         let mut cl = resp.content_length().unwrap_or(0);
                         let resp_status = resp.status();
-
-                        match resp.text().await {
-                            Ok(txt) => {
-                                if cl == 0 {
-                                    cl = txt.len() as u64;
+                        sent.status = resp_status.as_u16() as i64;
+                        sent.response_headers = sqlx::types::Json(header_pairs(resp.headers()));
+
+                        if no_body {
+                            let declared = resp
+                                .headers()
+                                .get(CONTENT_LENGTH)
+                                .and_then(|v| v.to_str().ok());
+                            cl = declared.and_then(|v| v.parse().ok()).unwrap_or(0);
+                            result.set_text(&headers_text(resp.headers()));
+                            json_tree.set_body("");
+                        } else {
+                            match resp.text().await {
+                                Ok(txt) => {
+                                    sent.response_body = txt.clone();
+                                    if cl == 0 {
+                                        cl = txt.len() as u64;
+                                    }
+                                    let txt1 = txt.as_str();
+                                    let endpos = txt1.len();
+                                    result.set_text(txt1);
+                                    json_tree.set_body(txt1);
+
+                                    let lc = result.count_lines(0, endpos as i32) + 1;
+                                    let lc_width = ((f64::log10(lc as f64) as i64) + 1) * 12;
+                                    let lc_width = lc_width.max(3 * 12);
+                                    ptr_result_text.set_linenumber_width(lc_width as i32);
                                 }
-                                let txt1 = txt.as_str();
-                                let endpos = txt1.len();
-                                result.set_text(txt1);
-
-                                let lc = result.count_lines(0, endpos as i32) + 1;
-                                let lc_width = ((f64::log10(lc as f64) as i64) + 1) * 12;
-                                let lc_width = lc_width.max(3 * 12);
-                                ptr_result_text.set_linenumber_width(lc_width as i32);
+                                Err(e) => result.set_text(format!("{e:?}").as_str()),
                             }
-                            Err(e) => result.set_text(format!("{e:?}").as_str()),
-                        };
-
-                        let total_resp_time = std::time::Instant::now() - start;
-                        let latency = resp_time - start;
+                        }
+
+                        timings.download = resp_time.elapsed();
+                        timing_buf.set_text(&timings.waterfall());
+                        set_timings(&mut sent, &timings);
+                        let sent = redact(&sent);
+                        *last_response.lock().unwrap() = Some(sent.clone());
+                        record_sender
+                            .send(GlobalAppMsg::SaveWindowResponse(id as i32, sent.clone()));
+                        record_sender.send(GlobalAppMsg::RecordSentRequest(sent));
                         let hbytes = human_bytes(cl as f64);
-                        inner_status_ptr.set_label(
-                            format!("STATUS={resp_status} | BYTES={hbytes} | RTT={total_resp_time:?} | LAT={latency:?}")
-                                .as_str(),
-                        );
+                        let (total_resp_time, ttfb) = (timings.total(), timings.ttfb);
+                        inner_status_ptr.set_label(&format!(
+                            "STATUS={resp_status} | BYTES={hbytes} | RTT={total_resp_time:?} \
+                             | TTFB={ttfb:?}"
+                        ));
                     }
                     Err(e) => {
                         inner_status_ptr.set_label("");
-                        result.set_text(format!("{e:?}").as_str());
+                        timing_buf.set_text("");
+                        result.set_text(&e);
+                        json_tree.set_body("");
                     }
                 }
 
@@ -213,12 +930,17 @@ headers: sqlx::types::Json(headers.iter().map(|f| (f.0.to_string(), f.1.to_str()
         });
 
         Self {
-            uri: String::new(),
-            method: reqwest::Method::GET,
-            global: s,
             id,
-            window: p_win,
+            host: p_host,
             param_ctrl: params_ptr,
+            env_choice,
+            environment_id,
+            scopes,
+            last_response,
+            state,
+            autosave,
+            actions,
+            update_title,
         }
     }
 }
@@ -230,7 +952,37 @@ impl HasId for RequestWindow {
 }
 
 impl AppWindow for RequestWindow {
-    fn close(&mut self) {        
-        self.window.hide();
+    fn close(&mut self) {
+        self.autosave.cancel();
+        self.host.close();
+    }
+
+    fn scopes_changed(&mut self) {
+        fill_environments(
+            &mut self.env_choice,
+            &self.scopes.borrow(),
+            self.environment_id.get(),
+        );
+        // a colour label may have changed
+        (self.update_title)(false);
+    }
+
+    fn graphql_schema_loaded(&mut self, schema: &str) {
+        match graphql::parse(schema) {
+            Ok(schema) => *self.param_ctrl.schema().lock().unwrap() = Some(schema),
+            Err(e) => tracing::warn!("cached GraphQL schema: {e}"),
+        }
+    }
+
+    fn last_response(&self) -> Option<SentRequest> {
+        self.last_response.lock().unwrap().clone()
+    }
+
+    fn window_state(&self) -> Option<OpenWindow> {
+        Some((self.state)())
+    }
+
+    fn run_action(&mut self, action: Action) {
+        (self.actions)(action)
     }
 }
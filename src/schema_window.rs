@@ -0,0 +1,101 @@
+use fltk::{
+    app, browser,
+    enums::{self, Event},
+    group,
+    prelude::*,
+    text::{self},
+    window::{self, DoubleWindow},
+};
+
+use crate::{AppWindow, GlobalAppMsg, HasId, graphql::Schema, next_window_id};
+
+/// Docs browser for an introspected GraphQL schema, root types first.
+pub struct SchemaWindow {
+    id: usize,
+    window: DoubleWindow,
+}
+
+impl SchemaWindow {
+    pub fn new(schema: &Schema) -> Self {
+        let id = next_window_id();
+
+        let mut win = window::DoubleWindow::default()
+            .with_size(1000, 700)
+            .with_label("GraphQL schema");
+
+        let mut row = group::Flex::default_fill().row();
+        let mut list = browser::HoldBrowser::default();
+        row.fixed(&list, 280);
+        let docs_buf = text::TextBuffer::default();
+        let mut docs = text::TextDisplay::default();
+        docs.set_buffer(docs_buf.clone());
+        docs.set_text_font(enums::Font::Courier);
+        row.end();
+
+        win.end();
+        win.make_resizable(true);
+        win.show();
+
+        let roots: Vec<&str> = [
+            &schema.query_type,
+            &schema.mutation_type,
+            &schema.subscription_type,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|t| t.name.as_str())
+        .collect();
+        let mut names: Vec<&str> = schema
+            .types
+            .iter()
+            .map(|t| t.name.as_str())
+            .filter(|n| !n.starts_with("__") && !roots.contains(n))
+            .collect();
+        names.sort_unstable();
+
+        let names: Vec<String> = roots.into_iter().chain(names).map(str::to_string).collect();
+        for name in &names {
+            list.add(name);
+        }
+
+        let schema = schema.clone();
+        let mut p_docs_buf = docs_buf.clone();
+        list.set_callback(move |l| {
+            let line = l.value();
+            if line < 1 {
+                return;
+            }
+            if let Some(name) = names.get(line as usize - 1) {
+                p_docs_buf.set_text(&schema.describe(name));
+            }
+        });
+        if list.size() > 0 {
+            list.select(1);
+            list.do_callback();
+        }
+
+        let (s, _) = app::channel::<GlobalAppMsg>();
+        win.handle(move |_, e| {
+            if e == Event::Hide {
+                s.send(GlobalAppMsg::CloseWindow(id));
+                return true;
+            }
+
+            false
+        });
+
+        Self { id, window: win }
+    }
+}
+
+impl HasId for SchemaWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for SchemaWindow {
+    fn close(&mut self) {
+        self.window.hide();
+    }
+}
@@ -0,0 +1,393 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chacha20poly1305::{
+    XChaCha20Poly1305, XNonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore},
+};
+use reqwest::Url;
+
+use crate::db::SentRequest;
+
+/// Starts an encrypted value, followed by the base64 of the nonce and the ciphertext.
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+/// Encrypted with the key when it is created, to tell a wrong passphrase on unlock.
+const CHECK: &str = "legrillon";
+
+/// Shown in place of a secret in the history, logs and exports.
+pub const REDACTED: &str = "********";
+
+/// Parts of header, variable and query parameter names that hold credentials.
+const SECRET_NAMES: [&str; 11] = [
+    "authorization",
+    "cookie",
+    "token",
+    "secret",
+    "password",
+    "passwd",
+    "apikey",
+    "api-key",
+    "api_key",
+    "private-key",
+    "credential",
+];
+
+pub fn is_secret_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_NAMES.iter().any(|s| name.contains(s))
+}
+
+/// A credential by its name. Values made of `{{variable}}` references hold nothing
+/// secret themselves and are kept, e.g. `Bearer {{token}}`.
+pub fn is_secret(name: &str, value: &str) -> bool {
+    is_secret_name(name) && !value.is_empty() && !value.contains("{{")
+}
+
+/// Variables marked as secret or not by name, e.g. from Postman's `"type": "secret"`. A flag
+/// overrides the guess from the name either way.
+pub type SecretFlags = BTreeMap<String, bool>;
+
+/// A variable holding a credential, by its flag or else by its name.
+pub fn is_secret_variable(name: &str, value: &str, flags: &SecretFlags) -> bool {
+    match flags.get(name) {
+        Some(&secret) => secret && !value.is_empty() && !value.contains("{{"),
+        None => is_secret(name, value),
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Refuses to send values that are still encrypted, e.g. restored or substituted while the
+/// secrets are locked, as the ciphertext would go out in place of the secret.
+pub fn check_sendable<'a>(values: impl IntoIterator<Item = &'a str>) -> Result<(), String> {
+    if values.into_iter().any(|v| v.contains(PREFIX)) {
+        return Err(
+            "NOT SENT | a secret is still encrypted, enter the master passphrase on the next start"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Salt and check value stored with the secrets, to derive the key again.
+#[derive(Debug, Clone)]
+pub struct VaultHeader {
+    pub salt: String,
+    pub check: String,
+}
+
+/// Encrypts secrets with a key derived from the master passphrase (argon2id,
+/// XChaCha20-Poly1305).
+#[derive(Clone)]
+pub struct Vault {
+    cipher: XChaCha20Poly1305,
+}
+
+impl Vault {
+    /// A new key for `passphrase` with a random salt.
+    pub fn create(passphrase: &str) -> Result<(Self, VaultHeader), String> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let vault = Self::derive(passphrase, &salt)?;
+        let check = vault.encrypt(CHECK)?;
+        let header = VaultHeader {
+            salt: STANDARD.encode(salt),
+            check,
+        };
+        Ok((vault, header))
+    }
+
+    pub fn unlock(passphrase: &str, header: &VaultHeader) -> Result<Self, String> {
+        let salt = STANDARD.decode(&header.salt).map_err(|e| e.to_string())?;
+        let vault = Self::derive(passphrase, &salt)?;
+        match vault.decrypt(&header.check) {
+            Ok(check) if check == CHECK => Ok(vault),
+            _ => Err("Wrong passphrase".to_string()),
+        }
+    }
+
+    fn derive(passphrase: &str, salt: &[u8]) -> Result<Self, String> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            cipher: XChaCha20Poly1305::new(&key.into()),
+        })
+    }
+
+    pub fn encrypt(&self, plain: &str) -> Result<String, String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = self
+            .cipher
+            .encrypt(&nonce, plain.as_bytes())
+            .map_err(|e| e.to_string())?;
+        let mut bytes = nonce.to_vec();
+        bytes.extend(sealed);
+        Ok(format!("{PREFIX}{}", STANDARD.encode(bytes)))
+    }
+
+    pub fn decrypt(&self, text: &str) -> Result<String, String> {
+        let data = text.strip_prefix(PREFIX).ok_or("not an encrypted value")?;
+        let bytes = STANDARD.decode(data).map_err(|e| e.to_string())?;
+        if bytes.len() < NONCE_LEN {
+            return Err("encrypted value too short".to_string());
+        }
+        let (nonce, sealed) = bytes.split_at(NONCE_LEN);
+        let plain = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), sealed)
+            .map_err(|_| "encrypted with another passphrase or damaged".to_string())?;
+        String::from_utf8(plain).map_err(|e| e.to_string())
+    }
+
+    /// Encrypts the secret values among `pairs`, encrypted ones are left alone.
+    pub fn seal(&self, pairs: &mut [(String, String)], flags: &SecretFlags) {
+        for (name, value) in pairs.iter_mut() {
+            if is_secret_variable(name, value, flags) && !is_encrypted(value) {
+                match self.encrypt(value) {
+                    Ok(sealed) => *value = sealed,
+                    Err(e) => tracing::error!("could not encrypt {name}: {e}"),
+                }
+            }
+        }
+    }
+
+    /// Decrypts the encrypted values among `pairs`.
+    pub fn open(&self, pairs: &mut [(String, String)]) {
+        for (name, value) in pairs.iter_mut() {
+            if is_encrypted(value) {
+                match self.decrypt(value) {
+                    Ok(plain) => *value = plain,
+                    Err(e) => tracing::warn!("could not decrypt {name}: {e}"),
+                }
+            }
+        }
+    }
+}
+
+/// `pairs` with the secret values, encrypted or not, replaced by [`REDACTED`].
+pub fn redact(pairs: &[(String, String)]) -> Vec<(String, String)> {
+    redact_variables(pairs, &SecretFlags::new())
+}
+
+/// [`redact`] for variables, with their secret flags.
+pub fn redact_variables(pairs: &[(String, String)], flags: &SecretFlags) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_secret_variable(name, value, flags) {
+                REDACTED.to_string()
+            } else {
+                value.clone()
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+/// `uri` with the values of secret query parameters, e.g. `api_key`, replaced.
+pub fn redact_uri(uri: &str) -> String {
+    let Ok(mut url) = Url::parse(uri) else {
+        return uri.to_string();
+    };
+    let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    if !pairs.iter().any(|(n, v)| is_secret(n, v)) {
+        return uri.to_string();
+    }
+    url.query_pairs_mut().clear().extend_pairs(redact(&pairs));
+    url.to_string()
+}
+
+/// A sent request as it may be kept in the history or exported. The values of the secret
+/// `variables` are replaced wherever they were substituted, e.g. in a JSON body or a path.
+pub fn redact_sent(
+    sent: &SentRequest,
+    variables: &[(String, String)],
+    flags: &SecretFlags,
+) -> SentRequest {
+    let mut values: Vec<&str> = variables
+        .iter()
+        .filter(|(name, value)| is_secret_variable(name, value, flags))
+        .map(|(_, value)| value.as_str())
+        .collect();
+    // longest first, so a secret containing another one is replaced whole
+    values.sort_by_key(|v| Reverse(v.len()));
+    let hide = |text: &str| {
+        values.iter().fold(text.to_string(), |text, value| {
+            text.replace(value, REDACTED)
+        })
+    };
+
+    SentRequest {
+        uri: redact_uri(&hide(&sent.uri)),
+        headers: sqlx::types::Json(
+            redact(&sent.headers.0)
+                .into_iter()
+                .map(|(name, value)| (name, hide(&value)))
+                .collect(),
+        ),
+        body: hide(&sent.body),
+        response_headers: sqlx::types::Json(redact(&sent.response_headers.0)),
+        ..sent.clone()
+    }
+}
+
+/// Redacts the name/value pairs in a `{:?}` dump, e.g. `("Authorization", "Bearer x")`,
+/// so whole messages can be logged. `flags` are those of the variables it may hold.
+pub fn redact_log(text: &str, flags: &SecretFlags) -> String {
+    const SEPARATOR: &str = "\", \"";
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("(\"") {
+        out.push_str(&rest[..start + 2]);
+        rest = &rest[start + 2..];
+        let Some(name_len) = quoted_len(rest) else {
+            break;
+        };
+        let Some(value) = rest[name_len..].strip_prefix(SEPARATOR) else {
+            continue;
+        };
+        let Some(value_len) = quoted_len(value) else {
+            break;
+        };
+        if !value[value_len..].starts_with("\")") {
+            continue;
+        }
+        let (name, value) = (&rest[..name_len], &value[..value_len]);
+        let end = name_len + SEPARATOR.len() + value_len + 2;
+        if is_secret_variable(name, value, flags) {
+            out.push_str(&format!("{name}{SEPARATOR}{REDACTED}\")"));
+        } else {
+            out.push_str(&rest[..end]);
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Length of the `{:?}` string at the start of `text` up to its closing quote, quotes and
+/// backslashes inside are escaped.
+fn quoted_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_override_the_name() {
+        let flags = SecretFlags::from([
+            ("signingKey".to_string(), true),
+            ("tokenUrl".to_string(), false),
+        ]);
+        assert!(is_secret_variable("signingKey", "s3cr3t", &flags));
+        assert!(!is_secret_variable("signingKey", "{{key}}", &flags));
+        assert!(!is_secret_variable("tokenUrl", "https://auth", &flags));
+        assert!(is_secret_variable("password", "hunter2", &flags));
+        assert!(!is_secret_variable("host", "localhost", &flags));
+
+        let pairs = [
+            ("signingKey".to_string(), "s3cr3t".to_string()),
+            ("tokenUrl".to_string(), "https://auth".to_string()),
+        ];
+        assert_eq!(
+            redact_variables(&pairs, &flags),
+            [
+                ("signingKey".to_string(), REDACTED.to_string()),
+                ("tokenUrl".to_string(), "https://auth".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn vault_roundtrip() {
+        let (vault, header) = Vault::create("correct horse").unwrap();
+        let sealed = vault.encrypt("s3cr3t").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert_ne!(sealed, vault.encrypt("s3cr3t").unwrap());
+
+        let unlocked = Vault::unlock("correct horse", &header).unwrap();
+        assert_eq!(unlocked.decrypt(&sealed).unwrap(), "s3cr3t");
+
+        let mut pairs = vec![
+            ("password".to_string(), "hunter2".to_string()),
+            ("host".to_string(), "localhost".to_string()),
+        ];
+        unlocked.seal(&mut pairs, &SecretFlags::new());
+        assert!(is_encrypted(&pairs[0].1));
+        assert_eq!(pairs[1].1, "localhost");
+        vault.open(&mut pairs);
+        assert_eq!(pairs[0].1, "hunter2");
+    }
+
+    #[test]
+    fn wrong_passphrase_is_detected() {
+        let (vault, header) = Vault::create("correct horse").unwrap();
+        assert_eq!(
+            Vault::unlock("battery staple", &header).err().as_deref(),
+            Some("Wrong passphrase")
+        );
+
+        let (other, _) = Vault::create("battery staple").unwrap();
+        let sealed = vault.encrypt("s3cr3t").unwrap();
+        assert!(other.decrypt(&sealed).is_err());
+        assert!(vault.decrypt("enc:v1:AAAA").is_err());
+        assert!(vault.decrypt("s3cr3t").is_err());
+    }
+
+    #[test]
+    fn encrypted_values_are_not_sent() {
+        let (vault, _) = Vault::create("correct horse").unwrap();
+        let sealed = vault.encrypt("s3cr3t").unwrap();
+        assert!(check_sendable(["http://localhost", "Bearer abc"]).is_ok());
+        assert!(check_sendable(["http://localhost", &format!("Bearer {sealed}")]).is_err());
+    }
+
+    #[test]
+    fn redacts_debug_dumps() {
+        let headers = vec![
+            (
+                "Authorization".to_string(),
+                "Bearer \"quoted\") \\".to_string(),
+            ),
+            ("Accept".to_string(), "*/*".to_string()),
+            ("X-Api-Key".to_string(), "{{key}}".to_string()),
+        ];
+        let dump = format!("Send({headers:?})");
+        assert_eq!(
+            redact_log(&dump, &SecretFlags::new()),
+            format!(
+                "Send([(\"Authorization\", \"{REDACTED}\"), (\"Accept\", \"*/*\"), \
+                 (\"X-Api-Key\", \"{{{{key}}}}\")])"
+            )
+        );
+
+        let cookie = format!("{:?}", ("na\"me", "Cookie"));
+        assert_eq!(redact_log(&cookie, &SecretFlags::new()), cookie);
+        assert_eq!(
+            redact_log("(\"token\", \"unterminated", &SecretFlags::new()),
+            "(\"token\", \"unterminated"
+        );
+        assert_eq!(
+            redact_log("(\"token\")", &SecretFlags::new()),
+            "(\"token\")"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use serde::Deserialize;
+
+const SETTINGS_PATH: &str = "legrillon.toml";
+
+/// User settings read from `legrillon.toml` next to `sqlite.db`, every key is optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Store collections as one file per request below this directory instead of in the database.
+    pub collections_dir: Option<PathBuf>,
+    /// Open requests as tabs of the main window, next to a collections and history sidebar.
+    pub tabbed_layout: bool,
+    /// Key bindings by action, e.g. `send = "F5"`, an empty binding turns a default off.
+    pub shortcuts: BTreeMap<String, String>,
+    /// Hosts where sending anything but GET, HEAD, OPTIONS or TRACE needs a confirmation,
+    /// `*` matches any part of a name, e.g. `*.prod.example.com`.
+    pub protected_hosts: Vec<String>,
+    /// Encrypt tokens, passwords and other secrets in the database with a master passphrase
+    /// asked for at start.
+    pub encrypt_secrets: bool,
+    /// What to log, a level, e.g. `debug`, optionally followed by levels for some modules,
+    /// e.g. `info,legrillon::db=debug`. `info` when empty, see also the Logs window.
+    pub log_level: String,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let text = match std::fs::read_to_string(SETTINGS_PATH) {
+            Ok(text) => text,
+            Err(_) => return Settings::default(),
+        };
+
+        match toml::from_str(&text) {
+            Ok(settings) => settings,
+            Err(e) => {
+                tracing::warn!("{SETTINGS_PATH}: {e}");
+                Settings::default()
+            }
+        }
+    }
+}
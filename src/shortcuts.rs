@@ -0,0 +1,283 @@
+use std::{collections::BTreeMap, fmt, str::FromStr, sync::OnceLock};
+
+/// Something that can be run from the keyboard or the command palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Send,
+    NewWindow,
+    Duplicate,
+    CloseWindow,
+    FocusUri,
+    BodyTab,
+    HeadersTab,
+    SaveToCollection,
+    Rename,
+    EnvironmentColor,
+    ToggleReadOnly,
+    SecretVariable,
+    NewWebSocket,
+    NewGrpc,
+    OpenCollections,
+    OpenHistory,
+    OpenMockServer,
+    OpenProxy,
+    OpenLogs,
+    CommandPalette,
+}
+
+impl Action {
+    pub const ALL: [Action; 20] = [
+        Action::Send,
+        Action::NewWindow,
+        Action::Duplicate,
+        Action::CloseWindow,
+        Action::FocusUri,
+        Action::BodyTab,
+        Action::HeadersTab,
+        Action::SaveToCollection,
+        Action::Rename,
+        Action::EnvironmentColor,
+        Action::ToggleReadOnly,
+        Action::SecretVariable,
+        Action::NewWebSocket,
+        Action::NewGrpc,
+        Action::OpenCollections,
+        Action::OpenHistory,
+        Action::OpenMockServer,
+        Action::OpenProxy,
+        Action::OpenLogs,
+        Action::CommandPalette,
+    ];
+
+    /// Name of the action in the `[shortcuts]` table of the settings.
+    pub fn key(self) -> &'static str {
+        match self {
+            Action::Send => "send",
+            Action::NewWindow => "new_window",
+            Action::Duplicate => "duplicate",
+            Action::CloseWindow => "close_window",
+            Action::FocusUri => "focus_uri",
+            Action::BodyTab => "body_tab",
+            Action::HeadersTab => "headers_tab",
+            Action::SaveToCollection => "save_to_collection",
+            Action::Rename => "rename",
+            Action::EnvironmentColor => "environment_color",
+            Action::ToggleReadOnly => "toggle_read_only",
+            Action::SecretVariable => "secret_variable",
+            Action::NewWebSocket => "new_websocket",
+            Action::NewGrpc => "new_grpc",
+            Action::OpenCollections => "open_collections",
+            Action::OpenHistory => "open_history",
+            Action::OpenMockServer => "open_mock_server",
+            Action::OpenProxy => "open_proxy",
+            Action::OpenLogs => "open_logs",
+            Action::CommandPalette => "command_palette",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Send => "Send request",
+            Action::NewWindow => "New request window",
+            Action::Duplicate => "Duplicate window",
+            Action::CloseWindow => "Close window",
+            Action::FocusUri => "Focus URI",
+            Action::BodyTab => "Show body",
+            Action::HeadersTab => "Show headers",
+            Action::SaveToCollection => "Save to collection...",
+            Action::Rename => "Rename window...",
+            Action::EnvironmentColor => "Environment colour...",
+            Action::ToggleReadOnly => "Read-only environment",
+            Action::SecretVariable => "Secret variable...",
+            Action::NewWebSocket => "New WebSocket window",
+            Action::NewGrpc => "New gRPC window",
+            Action::OpenCollections => "Open collections",
+            Action::OpenHistory => "Open history",
+            Action::OpenMockServer => "Open mock server",
+            Action::OpenProxy => "Open proxy",
+            Action::OpenLogs => "Open logs",
+            Action::CommandPalette => "Command palette",
+        }
+    }
+
+    /// Defaults avoid keys the text editors consume, e.g. Ctrl+Enter inserts a line.
+    fn default_binding(self) -> Option<&'static str> {
+        match self {
+            Action::Send => Some("Ctrl+R"),
+            Action::NewWindow => Some("Ctrl+N"),
+            Action::Duplicate => Some("Ctrl+Shift+D"),
+            Action::CloseWindow => Some("Ctrl+W"),
+            Action::FocusUri => Some("Ctrl+L"),
+            Action::BodyTab => Some("Alt+1"),
+            Action::HeadersTab => Some("Alt+2"),
+            Action::SaveToCollection => Some("Ctrl+S"),
+            Action::Rename => Some("F2"),
+            Action::CommandPalette => Some("Ctrl+Shift+P"),
+            _ => None,
+        }
+    }
+}
+
+/// A key without modifiers, letters are kept lowercase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Escape,
+    Tab,
+    Function(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub key: KeyCode,
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    /// Parses bindings like `Ctrl+Shift+P`, `Alt+1` or `F5`, case does not matter.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut ctrl, mut shift, mut alt) = (false, false, false);
+        let mut key = None;
+        for part in s.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" => ctrl = true,
+                "shift" => shift = true,
+                "alt" | "option" => alt = true,
+                _ if key.is_some() => return Err(format!("more than one key in {s}")),
+                "enter" | "return" => key = Some(KeyCode::Enter),
+                "esc" | "escape" => key = Some(KeyCode::Escape),
+                "tab" => key = Some(KeyCode::Tab),
+                name => {
+                    let mut chars = name.chars();
+                    key = match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(KeyCode::Char(c)),
+                        (Some('f'), Some(_)) => name[1..]
+                            .parse()
+                            .ok()
+                            .filter(|n| (1..=12).contains(n))
+                            .map(KeyCode::Function),
+                        _ => None,
+                    };
+                    if key.is_none() {
+                        return Err(format!("unknown key {part} in {s}"));
+                    }
+                }
+            }
+        }
+        let key = key.ok_or_else(|| format!("no key in {s}"))?;
+        Ok(Binding {
+            ctrl,
+            shift,
+            alt,
+            key,
+        })
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (on, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+        ] {
+            if on {
+                f.write_str(name)?;
+            }
+        }
+        match self.key {
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Enter => f.write_str("Enter"),
+            KeyCode::Escape => f.write_str("Esc"),
+            KeyCode::Tab => f.write_str("Tab"),
+            KeyCode::Function(n) => write!(f, "F{n}"),
+        }
+    }
+}
+
+/// Bindings in effect, the defaults with the `[shortcuts]` settings applied. An empty
+/// binding in the settings removes the default.
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: Vec<(Action, Binding)>,
+}
+
+impl Keymap {
+    pub fn new(overrides: &BTreeMap<String, String>) -> Self {
+        for name in overrides.keys() {
+            if !Action::ALL.iter().any(|a| a.key() == name) {
+                tracing::warn!("unknown action {name} in the shortcuts");
+            }
+        }
+
+        let mut bindings = Vec::new();
+        for action in Action::ALL {
+            let text = match overrides.get(action.key()) {
+                Some(text) => text.as_str(),
+                None => action.default_binding().unwrap_or_default(),
+            };
+            if text.trim().is_empty() {
+                continue;
+            }
+            match text.parse() {
+                Ok(binding) => bindings.push((action, binding)),
+                Err(e) => tracing::warn!("shortcut {}: {e}", action.key()),
+            }
+        }
+
+        Self { bindings }
+    }
+
+    pub fn action(&self, pressed: &Binding) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, b)| b == pressed)
+            .map(|(a, _)| *a)
+    }
+
+    pub fn binding(&self, action: Action) -> Option<Binding> {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, b)| *b)
+    }
+}
+
+static KEYMAP: OnceLock<Keymap> = OnceLock::new();
+
+/// Sets up the keymap from the settings, before the first window opens.
+pub fn init(overrides: &BTreeMap<String, String>) {
+    let _ = KEYMAP.set(Keymap::new(overrides));
+}
+
+pub fn keymap() -> &'static Keymap {
+    KEYMAP.get_or_init(|| Keymap::new(&BTreeMap::new()))
+}
+
+/// Scores `text` for the palette search, `None` unless all characters of `query` appear
+/// in order. Matches at word starts and runs of consecutive matches score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = pos + text[pos..].iter().position(|&c| c == q)?;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        if last.is_some_and(|l| l + 1 == found) {
+            score += 4;
+        }
+        last = Some(found);
+        pos = found + 1;
+    }
+    // prefer shorter labels among equal matches
+    Some(score * 100 - text.len() as i32)
+}
@@ -0,0 +1,102 @@
+use std::{cell::RefCell, rc::Rc};
+
+use fltk::{
+    app::{self, Sender},
+    browser::HoldBrowser,
+    group::{Flex, Tabs},
+    prelude::*,
+};
+
+use crate::{
+    GlobalAppMsg, collections_window,
+    db::{Collection, CollectionRequest, OpenWindow, SIDEBAR_HISTORY, SentRequest},
+};
+
+/// Lines of [`collections_window::fill_collections`], `None` for headings.
+type CollectionLines = Rc<RefCell<Vec<(usize, Option<CollectionRequest>)>>>;
+
+/// Collections and recent history next to the request tabs, double click opens a tab.
+pub struct SidebarCtrl {
+    collections: HoldBrowser,
+    history: HoldBrowser,
+    collection_lines: CollectionLines,
+    history_entries: Rc<RefCell<Vec<SentRequest>>>,
+}
+
+fn history_line(e: &SentRequest) -> String {
+    format!("{} {} {}", e.method, e.status, e.uri)
+}
+
+impl SidebarCtrl {
+    pub fn new(parent: &mut Flex, sender: Sender<GlobalAppMsg>) -> Self {
+        let mut tabs = Tabs::default_fill();
+        let grp = Flex::default_fill().with_label("Collections\t").column();
+        let mut collections = HoldBrowser::default();
+        grp.end();
+        let grp = Flex::default_fill().with_label("History\t").column();
+        let mut history = HoldBrowser::default();
+        history.set_tooltip("Method, status, URI");
+        grp.end();
+        tabs.end();
+        tabs.auto_layout();
+        parent.fixed(&tabs, 320);
+
+        let collection_lines: CollectionLines = Rc::new(RefCell::new(Vec::new()));
+        let history_entries: Rc<RefCell<Vec<SentRequest>>> = Rc::new(RefCell::new(Vec::new()));
+
+        let p_lines = collection_lines.clone();
+        collections.set_callback(move |l| {
+            if !app::event_clicks() || l.value() < 1 {
+                return;
+            }
+            if let Some((_, Some(r))) = p_lines.borrow().get(l.value() as usize - 1) {
+                sender.send(collections_window::restore_request(r));
+            }
+        });
+
+        let p_entries = history_entries.clone();
+        history.set_callback(move |l| {
+            if !app::event_clicks() || l.value() < 1 {
+                return;
+            }
+            if let Some(e) = p_entries.borrow().get(l.value() as usize - 1) {
+                sender.send(GlobalAppMsg::Restore(OpenWindow::new_from(
+                    &e.method, &e.uri, &e.headers, &e.body, None,
+                )));
+            }
+        });
+
+        Self {
+            collections,
+            history,
+            collection_lines,
+            history_entries,
+        }
+    }
+
+    pub fn set(
+        &mut self,
+        collections: &[(Collection, Vec<CollectionRequest>)],
+        history: &[SentRequest],
+    ) {
+        *self.collection_lines.borrow_mut() =
+            collections_window::fill_collections(&mut self.collections, collections);
+
+        self.history.clear();
+        for e in history {
+            self.history.add(&history_line(e));
+        }
+        *self.history_entries.borrow_mut() = history.to_vec();
+    }
+
+    /// Puts a just sent request on top of the history list.
+    pub fn add_history(&mut self, sent: &SentRequest) {
+        let mut entries = self.history_entries.borrow_mut();
+        entries.insert(0, sent.clone());
+        self.history.insert(1, &history_line(sent));
+        if entries.len() > SIDEBAR_HISTORY {
+            entries.truncate(SIDEBAR_HISTORY);
+            self.history.remove(self.history.size());
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use reqwest::{RequestBuilder, Response, StatusCode, header::HeaderMap};
+use tokio::sync::oneshot;
+
+/// Reconnect delay until the server sends a `retry:` field.
+const DEFAULT_RETRY_MS: u64 = 3000;
+
+/// One dispatched `text/event-stream` event.
+#[derive(Debug, Clone)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: String,
+    pub data: String,
+    pub retry: Option<u64>,
+}
+
+impl SseEvent {
+    pub fn display(&self) -> String {
+        let mut out = format!("event: {}", self.event);
+        if let Some(id) = &self.id {
+            out.push_str(&format!(" | id: {id}"));
+        }
+        if let Some(retry) = self.retry {
+            out.push_str(&format!(" | retry: {retry}"));
+        }
+        out.push('\n');
+        for line in self.data.lines() {
+            out.push_str(&format!("  {line}\n"));
+        }
+        out
+    }
+}
+
+/// Incremental parser, chunks may split lines and even UTF-8 sequences.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    pending: Vec<u8>,
+    id: Option<String>,
+    event: String,
+    data: Vec<String>,
+    retry: Option<u64>,
+    pub last_event_id: Option<String>,
+    pub retry_ms: Option<u64>,
+}
+
+impl SseParser {
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.pending.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            if let Some(event) = self.line(line) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => {
+                self.id = Some(value.to_string());
+                self.last_event_id = Some(value.to_string());
+            }
+            "retry" => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(ms);
+                    self.retry_ms = Some(ms);
+                }
+            }
+            _ => (),
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let id = self.id.take();
+        let event = std::mem::take(&mut self.event);
+        let retry = self.retry.take();
+        if self.data.is_empty() {
+            return None;
+        }
+
+        Some(SseEvent {
+            id,
+            event: if event.is_empty() {
+                "message".to_string()
+            } else {
+                event
+            },
+            data: std::mem::take(&mut self.data).join("\n"),
+            retry,
+        })
+    }
+}
+
+pub fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim_start().starts_with("text/event-stream"))
+}
+
+/// Reads events from `resp` until `stop` fires. Lost connections are re-established
+/// with `request`, which gets the `Last-Event-ID` to resume from.
+pub async fn follow(
+    mut resp: Response,
+    request: impl Fn(Option<&str>) -> RequestBuilder,
+    mut on_event: impl FnMut(SseEvent),
+    mut on_status: impl FnMut(&str),
+    mut stop: oneshot::Receiver<()>,
+) {
+    let mut parser = SseParser::default();
+    let mut count = 0;
+
+    loop {
+        on_status(&format!("STREAMING {} | EVENTS={count}", resp.status()));
+        loop {
+            let chunk = tokio::select! {
+                chunk = resp.chunk() => chunk,
+                _ = &mut stop => {
+                    on_status(&format!("STOPPED | EVENTS={count}"));
+                    return;
+                }
+            };
+
+            match chunk {
+                Ok(Some(bytes)) => {
+                    for event in parser.feed(&bytes) {
+                        count += 1;
+                        on_event(event);
+                    }
+                    on_status(&format!("STREAMING {} | EVENTS={count}", resp.status()));
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::warn!("event stream: {e}");
+                    break;
+                }
+            }
+        }
+
+        // reconnect until the server answers with something that is not a stream
+        loop {
+            let retry = parser.retry_ms.unwrap_or(DEFAULT_RETRY_MS);
+            on_status(&format!("RECONNECTING in {retry} ms | EVENTS={count}"));
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(retry)) => (),
+                _ = &mut stop => {
+                    on_status(&format!("STOPPED | EVENTS={count}"));
+                    return;
+                }
+            }
+
+            match request(parser.last_event_id.as_deref()).send().await {
+                Ok(r) if r.status() == StatusCode::NO_CONTENT || !is_event_stream(r.headers()) => {
+                    on_status(&format!("ENDED {} | EVENTS={count}", r.status()));
+                    return;
+                }
+                Ok(r) => {
+                    resp = r;
+                    break;
+                }
+                Err(e) => tracing::warn!("event stream reconnect: {e}"),
+            }
+        }
+    }
+}
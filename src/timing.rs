@@ -0,0 +1,361 @@
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper_util::rt::TokioIo;
+use reqwest::{
+    Method, StatusCode, Url,
+    header::{
+        ACCEPT, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, HOST, HeaderMap, HeaderValue,
+        LOCATION, PROXY_AUTHORIZATION, WWW_AUTHENTICATE,
+    },
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+
+use crate::db::SentRequest;
+
+const MAX_REDIRECTS: usize = 10;
+const WATERFALL_WIDTH: usize = 60;
+
+/// Phases of one request, summed over redirects. A phase that did not happen stays zero.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timings {
+    pub dns: Duration,
+    pub connect: Duration,
+    pub tls: Duration,
+    /// From writing the request until the response head arrived.
+    pub ttfb: Duration,
+    /// Reading the body, measured by whoever reads it.
+    pub download: Duration,
+}
+
+impl From<&SentRequest> for Timings {
+    fn from(sent: &SentRequest) -> Self {
+        let ms = |ms: f64| Duration::from_secs_f64(ms.max(0.0) / 1000.0);
+        Timings {
+            dns: ms(sent.dns_ms),
+            connect: ms(sent.connect_ms),
+            tls: ms(sent.tls_ms),
+            ttfb: ms(sent.wait_ms),
+            download: ms(sent.receive_ms),
+        }
+    }
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.dns + self.connect + self.tls + self.ttfb + self.download
+    }
+
+    /// One bar per phase, each starting where the previous one ended.
+    pub fn waterfall(&self) -> String {
+        let total = self.total().as_secs_f64().max(f64::EPSILON);
+        let mut text = String::new();
+        let mut offset = 0.0;
+        for (name, phase) in [
+            ("DNS", self.dns),
+            ("Connect", self.connect),
+            ("TLS", self.tls),
+            ("TTFB", self.ttfb),
+            ("Download", self.download),
+        ] {
+            let start = (offset / total * WATERFALL_WIDTH as f64).round() as usize;
+            offset += phase.as_secs_f64();
+            let end = (offset / total * WATERFALL_WIDTH as f64).round() as usize;
+            let bar = if phase.is_zero() {
+                String::new()
+            } else {
+                "#".repeat((end - start).max(1))
+            };
+            text.push_str(&format!(
+                "{name:<9} {:>9.1} ms |{}{bar}\n",
+                phase.as_secs_f64() * 1000.0,
+                " ".repeat(start)
+            ));
+        }
+        text.push_str(&format!(
+            "{:<9} {:>9.1} ms\n",
+            "Total",
+            self.total().as_secs_f64() * 1000.0
+        ));
+        text
+    }
+}
+
+/// Whether reqwest would send a request to `url` through a proxy set in the environment.
+fn env_proxy(url: &Url) -> bool {
+    let names = match url.scheme() {
+        "https" => ["https_proxy", "HTTPS_PROXY", "all_proxy", "ALL_PROXY"],
+        _ => ["http_proxy", "HTTP_PROXY", "all_proxy", "ALL_PROXY"],
+    };
+    names
+        .iter()
+        .any(|name| std::env::var_os(name).is_some_and(|v| !v.is_empty()))
+}
+
+/// Credentials only go to the origin they were meant for, like reqwest does.
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme()
+        && a.host_str() == b.host_str()
+        && a.port_or_known_default() == b.port_or_known_default()
+}
+
+/// Sends the request on a fresh connection so every phase can be measured, following
+/// redirects the way reqwest does. The body is left unread in the returned response.
+/// Through a proxy from the environment reqwest sends it and the whole exchange counts as
+/// time to first byte.
+pub async fn send(
+    method: Method,
+    uri: &str,
+    mut headers: HeaderMap,
+    body: String,
+) -> Result<(reqwest::Response, Timings), String> {
+    let mut url = Url::parse(uri).map_err(|e| format!("{uri}: {e}"))?;
+    let mut method = method;
+    let mut body = Bytes::from(body);
+    let mut timings = Timings::default();
+
+    for _ in 0..=MAX_REDIRECTS {
+        if env_proxy(&url) {
+            let start = Instant::now();
+            let response = reqwest::Client::new()
+                .request(method, url.clone())
+                .headers(headers)
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| format!("{url}: {e}"))?;
+            timings.ttfb += start.elapsed();
+            return Ok((response, timings));
+        }
+
+        let response = send_once(&method, &url, &headers, body.clone(), &mut timings).await?;
+        let status = response.status();
+        let next = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| url.join(l).ok());
+        let redirected = matches!(
+            status,
+            StatusCode::MOVED_PERMANENTLY
+                | StatusCode::FOUND
+                | StatusCode::SEE_OTHER
+                | StatusCode::TEMPORARY_REDIRECT
+                | StatusCode::PERMANENT_REDIRECT
+        );
+
+        match next {
+            Some(next) if redirected => {
+                if status != StatusCode::TEMPORARY_REDIRECT
+                    && status != StatusCode::PERMANENT_REDIRECT
+                    && method != Method::HEAD
+                {
+                    method = Method::GET;
+                    body = Bytes::new();
+                    headers.remove(CONTENT_TYPE);
+                    headers.remove(CONTENT_LENGTH);
+                }
+                if !same_origin(&next, &url) {
+                    for name in [
+                        AUTHORIZATION,
+                        COOKIE,
+                        PROXY_AUTHORIZATION,
+                        WWW_AUTHENTICATE,
+                        HOST,
+                    ] {
+                        headers.remove(name);
+                    }
+                }
+                url = next;
+            }
+            _ => {
+                let response = response.map(reqwest::Body::wrap);
+                return Ok((reqwest::Response::from(response), timings));
+            }
+        }
+    }
+
+    Err(format!("{uri}: more than {MAX_REDIRECTS} redirects"))
+}
+
+async fn send_once(
+    method: &Method,
+    url: &Url,
+    headers: &HeaderMap,
+    body: Bytes,
+    timings: &mut Timings,
+) -> Result<hyper::Response<hyper::body::Incoming>, String> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("{url}: missing host"))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let https = match url.scheme() {
+        "https" => true,
+        "http" => false,
+        scheme => return Err(format!("{url}: unsupported scheme {scheme}")),
+    };
+
+    let start = Instant::now();
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .map_err(|e| format!("{host}: {e}"))?
+        .collect();
+    timings.dns += start.elapsed();
+
+    let start = Instant::now();
+    let mut stream = Err(format!("{host}: no address"));
+    for addr in addrs {
+        stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| format!("{addr}: {e}"));
+        if stream.is_ok() {
+            break;
+        }
+    }
+    let stream = stream?;
+    timings.connect += start.elapsed();
+
+    // CONNECT names the host to tunnel to instead of a path
+    let target = match url.query() {
+        _ if *method == Method::CONNECT => format!("{host}:{port}"),
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    };
+    let mut request = hyper::Request::builder()
+        .method(method.clone())
+        .uri(target)
+        .body(Full::new(body))
+        .map_err(|e| e.to_string())?;
+    *request.headers_mut() = headers.clone();
+    if !request.headers().contains_key(HOST) {
+        let authority = match url.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+        let value = HeaderValue::from_str(&authority).map_err(|e| e.to_string())?;
+        request.headers_mut().insert(HOST, value);
+    }
+    if !request.headers().contains_key(ACCEPT) {
+        request
+            .headers_mut()
+            .insert(ACCEPT, HeaderValue::from_static("*/*"));
+    }
+
+    if https {
+        let start = Instant::now();
+        let connector = native_tls::TlsConnector::new().map_err(|e| e.to_string())?;
+        // IPv6 literals are bracketed in URLs but not in certificates
+        let stream = tokio_native_tls::TlsConnector::from(connector)
+            .connect(host.trim_matches(['[', ']']), stream)
+            .await
+            .map_err(|e| format!("{host}: {e}"))?;
+        timings.tls += start.elapsed();
+        exchange(stream, request, timings).await
+    } else {
+        exchange(stream, request, timings).await
+    }
+}
+
+async fn exchange<S>(
+    stream: S,
+    request: hyper::Request<Full<Bytes>>,
+    timings: &mut Timings,
+) -> Result<hyper::Response<hyper::body::Incoming>, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::debug!("timed connection: {e}");
+        }
+    });
+
+    let start = Instant::now();
+    let response = sender
+        .send_request(request)
+        .await
+        .map_err(|e| e.to_string())?;
+    timings.ttfb += start.elapsed();
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[test]
+    fn origin_includes_scheme_and_port() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert!(same_origin(
+            &url("https://a.test/x"),
+            &url("https://a.test:443/y")
+        ));
+        assert!(!same_origin(
+            &url("https://a.test/"),
+            &url("http://a.test/")
+        ));
+        assert!(!same_origin(
+            &url("http://a.test/"),
+            &url("http://a.test:8080/")
+        ));
+        assert!(!same_origin(&url("http://a.test/"), &url("http://b.test/")));
+    }
+
+    /// Answers one request with `response`, returns the port and the request received.
+    async fn serve_once(response: String) -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let task = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).to_lowercase()
+        });
+        (port, task)
+    }
+
+    #[tokio::test]
+    async fn redirect_to_another_port_drops_credentials() {
+        let (to, received) =
+            serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_string()).await;
+        let (from, _) = serve_once(format!(
+            "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:{to}/\r\nContent-Length: 0\r\n\r\n"
+        ))
+        .await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.insert(COOKIE, HeaderValue::from_static("session=secret"));
+        let (response, _) = send(
+            Method::GET,
+            &format!("http://127.0.0.1:{from}/"),
+            headers,
+            String::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let received = received.await.unwrap();
+        assert!(received.contains(&format!("host: 127.0.0.1:{to}")));
+        assert!(!received.contains("secret"));
+    }
+}
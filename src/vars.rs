@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    db::{Collection, Environment},
+    secrets::SecretFlags,
+};
+
+/// Environments and collection variables known to the app, shared by all request windows.
+#[derive(Debug, Clone, Default)]
+pub struct VariableScopes {
+    pub environments: Vec<Environment>,
+    pub collections: BTreeMap<i64, Collection>,
+}
+
+impl VariableScopes {
+    /// Variables visible to a request, environment values shadow collection values.
+    pub fn resolve(
+        &self,
+        environment_id: Option<i64>,
+        collection_id: Option<i64>,
+    ) -> Vec<(String, String)> {
+        let mut vars = Vec::new();
+        if let Some(env) = environment_id.and_then(|id| self.environment(id)) {
+            vars.extend(env.variables.0.iter().cloned());
+        }
+        if let Some(collection) = collection_id.and_then(|id| self.collections.get(&id)) {
+            vars.extend(collection.variables.0.iter().cloned());
+        }
+        vars
+    }
+
+    /// Secret flags of the variables [`Self::resolve`] returns, environment flags shadow
+    /// collection flags.
+    pub fn secret_flags(
+        &self,
+        environment_id: Option<i64>,
+        collection_id: Option<i64>,
+    ) -> SecretFlags {
+        let mut flags = SecretFlags::new();
+        if let Some(collection) = collection_id.and_then(|id| self.collections.get(&id)) {
+            flags.extend(collection.secrets.0.clone());
+        }
+        if let Some(env) = environment_id.and_then(|id| self.environment(id)) {
+            flags.extend(env.secrets.0.clone());
+        }
+        flags
+    }
+
+    /// Variables marked as secret in any environment or collection, to redact them in logs.
+    pub fn flagged_secrets(&self) -> SecretFlags {
+        self.environments
+            .iter()
+            .map(|env| &env.secrets.0)
+            .chain(self.collections.values().map(|c| &c.secrets.0))
+            .flatten()
+            .filter(|(_, secret)| **secret)
+            .map(|(name, secret)| (name.clone(), *secret))
+            .collect()
+    }
+
+    pub fn environment(&self, id: i64) -> Option<&Environment> {
+        self.environments.iter().find(|e| e.id == id)
+    }
+}
+
+/// Replaces `{{name}}` placeholders, unknown names are left untouched.
+pub fn substitute(text: &str, vars: &[(String, String)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let name = after[..end].trim();
+        match vars.iter().find(|(n, _)| n == name) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::secrets;
+
+    use super::*;
+
+    fn environment(id: i64, variables: &[(&str, &str)], secrets: &[(&str, bool)]) -> Environment {
+        Environment {
+            id,
+            name: format!("env {id}"),
+            variables: sqlx::types::Json(
+                variables
+                    .iter()
+                    .map(|(n, v)| (n.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            secrets: sqlx::types::Json(secrets.iter().map(|(n, s)| (n.to_string(), *s)).collect()),
+            color: String::new(),
+            read_only: false,
+        }
+    }
+
+    #[test]
+    fn substitutes_known_variables_only() {
+        let vars = [
+            ("host".to_string(), "localhost".to_string()),
+            ("url".to_string(), "http://{{host}}".to_string()),
+        ];
+        assert_eq!(
+            substitute("GET {{ host }}/{{id}}?q={{host}}", &vars),
+            "GET localhost/{{id}}?q=localhost"
+        );
+        // values are inserted as they are, references in them are not followed
+        assert_eq!(substitute("{{url}}/a", &vars), "http://{{host}}/a");
+        assert_eq!(substitute("{{host}} {{host", &vars), "localhost {{host");
+    }
+
+    #[test]
+    fn environment_shadows_collection() {
+        let mut scopes = VariableScopes {
+            environments: vec![environment(1, &[("host", "prod")], &[("pin", true)])],
+            collections: BTreeMap::new(),
+        };
+        scopes.collections.insert(
+            5,
+            Collection {
+                id: 5,
+                name: "api".to_string(),
+                variables: sqlx::types::Json(vec![
+                    ("host".to_string(), "localhost".to_string()),
+                    ("pin".to_string(), "1234".to_string()),
+                ]),
+                secrets: sqlx::types::Json(SecretFlags::from([("pin".to_string(), false)])),
+            },
+        );
+
+        let vars = scopes.resolve(Some(1), Some(5));
+        assert_eq!(substitute("{{host}}:{{pin}}", &vars), "prod:1234");
+        assert_eq!(
+            substitute("{{host}}", &scopes.resolve(None, Some(5))),
+            "localhost"
+        );
+        assert_eq!(
+            substitute("{{host}}", &scopes.resolve(Some(2), None)),
+            "{{host}}"
+        );
+        assert!(scopes.secret_flags(Some(1), Some(5))["pin"]);
+        assert!(!scopes.secret_flags(None, Some(5))["pin"]);
+    }
+
+    #[test]
+    fn logged_scopes_hide_flagged_variables() {
+        let scopes = VariableScopes {
+            environments: vec![
+                environment(
+                    1,
+                    &[("signingKey", "s3cr3t"), ("host", "prod")],
+                    &[("signingKey", true)],
+                ),
+                environment(2, &[("password", "hunter2")], &[("password", false)]),
+            ],
+            collections: BTreeMap::new(),
+        };
+
+        let logged = secrets::redact_log(&format!("{scopes:?}"), &scopes.flagged_secrets());
+        assert!(!logged.contains("s3cr3t"), "{logged}");
+        assert!(!logged.contains("hunter2"), "{logged}");
+        assert!(logged.contains("prod"), "{logged}");
+    }
+}
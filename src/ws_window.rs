@@ -0,0 +1,398 @@
+use std::{cell::RefCell, rc::Rc};
+
+use fltk::{
+    app, button, enums, frame,
+    group::{self, Tabs},
+    input,
+    menu::Choice,
+    prelude::*,
+    text::{self, TextBuffer},
+};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::header::HeaderValue;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_tungstenite::tungstenite::{Message, client::IntoClientRequest};
+
+use crate::{
+    AppWindow, GlobalAppMsg, HasId,
+    autosave::Autosave,
+    db::{OpenWindow, WINDOW_KIND_WEBSOCKET, WindowLayout},
+    host::Host,
+    next_window_id,
+    req_params::RequestParamsCtrl,
+    secrets,
+    shortcuts::Action,
+};
+
+const PROTOCOLS_OPTION: &str = "protocols";
+const FRAME_KINDS: [&str; 3] = ["Text", "JSON", "Binary (hex)"];
+
+/// WebSocket client, the body tab holds the next frame to send and the headers tab
+/// the headers of the opening handshake.
+pub struct WebSocketWindow {
+    id: usize,
+    host: Host,
+    outgoing: Rc<RefCell<Option<UnboundedSender<Message>>>>,
+    state: Rc<dyn Fn() -> OpenWindow>,
+    autosave: Autosave,
+    actions: Rc<dyn Fn(Action)>,
+}
+
+fn log(buf: &mut TextBuffer, direction: &str, text: &str) {
+    let at = chrono::Local::now().format("%H:%M:%S%.3f");
+    buf.append(&format!("{at} {direction} {text}\n"));
+    app::awake();
+}
+
+fn describe(msg: &Message) -> String {
+    match msg {
+        Message::Text(t) => format!("TEXT {}", t.as_str()),
+        Message::Binary(b) => format!("BIN  {} bytes: {}", b.len(), to_hex(b)),
+        Message::Ping(b) => format!("PING {}", to_hex(b)),
+        Message::Pong(b) => format!("PONG {}", to_hex(b)),
+        Message::Close(c) => format!("CLOSE {c:?}"),
+        Message::Frame(f) => format!("FRAME {f:?}"),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Accepts `0a ff 10`, `0aff10` or `0x0a 0xff`.
+fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text
+        .split_whitespace()
+        .map(|w| w.trim_start_matches("0x"))
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err("odd number of hex digits".to_string());
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            digits
+                .get(i..i + 2)
+                .and_then(|d| u8::from_str_radix(d, 16).ok())
+                .ok_or_else(|| format!("invalid hex at position {i}"))
+        })
+        .collect()
+}
+
+fn frame_from(kind: i32, body: &str) -> Result<Message, String> {
+    match kind {
+        1 => serde_json::from_str::<serde_json::Value>(body)
+            .map(|_| Message::text(body.to_string()))
+            .map_err(|e| format!("invalid JSON: {e}")),
+        2 => from_hex(body).map(Message::binary),
+        _ => Ok(Message::text(body.to_string())),
+    }
+}
+
+impl WebSocketWindow {
+    pub fn new(wnd: Option<&OpenWindow>, tabs: Option<&Tabs>) -> Self {
+        let id = wnd.map_or(next_window_id(), |f| f.id as usize);
+
+        let mut host = Host::new(tabs, "Le Grillon - WebSocket", wnd.map(|w| &w.layout.0));
+
+        let mut col = group::Flex::default_fill().column();
+        let mut row = group::Flex::default().row();
+        let uri_label = frame::Frame::default().with_label("WS:");
+        row.fixed(&uri_label, 64);
+        let mut uri_input = input::Input::default();
+        let mut connect_btn = button::Button::default().with_label("Connect");
+        row.fixed(&connect_btn, 128);
+        row.end();
+        col.fixed(&row, 32);
+
+        let mut row = group::Flex::default().row();
+        let protocols_label = frame::Frame::default().with_label("Protocols:");
+        row.fixed(&protocols_label, 128);
+        let mut protocols_input = input::Input::default();
+        protocols_input.set_tooltip("Comma separated subprotocols, e.g. graphql-ws, mqtt");
+        row.end();
+        col.fixed(&row, 32);
+
+        let row = group::Flex::default_fill().row();
+        let mut req_params = RequestParamsCtrl::new();
+        let log_buf = text::TextBuffer::default();
+        let mut log_display = text::TextDisplay::default();
+        log_display.set_buffer(log_buf.clone());
+        log_display.set_text_font(enums::Font::Courier);
+        log_display.wrap_mode(text::WrapMode::AtBounds, 4);
+        row.end();
+
+        let mut row = group::Flex::default().row();
+        let mut frame_choice = Choice::default();
+        for kind in FRAME_KINDS {
+            frame_choice.add_choice(kind);
+        }
+        frame_choice.set_value(0);
+        row.fixed(&frame_choice, 196);
+        let mut send_btn = button::Button::default().with_label("Send");
+        row.fixed(&send_btn, 128);
+        let mut ping_btn = button::Button::default().with_label("Ping");
+        row.fixed(&ping_btn, 128);
+        let mut clear_btn = button::Button::default().with_label("Clear log");
+        row.fixed(&clear_btn, 128);
+        let mut status = frame::Frame::default();
+        status.set_frame(enums::FrameType::FlatBox);
+        row.end();
+        col.fixed(&row, 32);
+        col.end();
+
+        host.show();
+
+        if let Some(wnd) = wnd {
+            uri_input.set_value(&wnd.uri);
+            host.set_label(&wnd.uri);
+            if let Some(protocols) = wnd.options.0.get(PROTOCOLS_OPTION) {
+                protocols_input.set_value(protocols);
+            }
+            req_params.set(wnd);
+        }
+
+        let (s, _) = app::channel();
+        host.on_events(id, "WebSocketWindow");
+
+        let params = Rc::new(req_params);
+        let outgoing: Rc<RefCell<Option<UnboundedSender<Message>>>> = Rc::new(RefCell::new(None));
+
+        let state = {
+            let params = params.clone();
+            let uri_input = uri_input.clone();
+            let protocols_input = protocols_input.clone();
+            let host = host.clone();
+            move || {
+                let mut options = params.get_options();
+                options.insert(PROTOCOLS_OPTION.to_string(), protocols_input.value());
+                OpenWindow {
+                    id: id as i32,
+                    method: "GET".to_string(),
+                    uri: uri_input.value(),
+                    path: "".to_string(),
+                    query: "".to_string(),
+                    headers: sqlx::types::Json(params.get_header_pairs()),
+                    body: params.get_body(),
+                    environment_id: None,
+                    collection_id: None,
+                    kind: WINDOW_KIND_WEBSOCKET.to_string(),
+                    options: sqlx::types::Json(options),
+                    layout: sqlx::types::Json(WindowLayout {
+                        params_tab: params.active_tab(),
+                        ..host.layout()
+                    }),
+                    response: None,
+                    name: String::new(),
+                }
+            }
+        };
+        let state: Rc<dyn Fn() -> OpenWindow> = Rc::new(state);
+        let save_state: Rc<dyn Fn()> = {
+            let state = state.clone();
+            Rc::new(move || s.send(GlobalAppMsg::SaveWindowState(state())))
+        };
+
+        let autosave = Autosave::new(save_state.clone());
+        let p_autosave = autosave.clone();
+        params.on_change(move || p_autosave.touch());
+        for mut input in [uri_input.clone(), protocols_input.clone()] {
+            let p_autosave = autosave.clone();
+            input.set_trigger(enums::CallbackTrigger::Changed);
+            input.set_callback(move |_| p_autosave.touch());
+        }
+
+        let actions: Rc<dyn Fn(Action)> = {
+            let (send_btn, uri_input, params) =
+                (send_btn.clone(), uri_input.clone(), params.clone());
+            let state = state.clone();
+            Rc::new(move |action: Action| match action {
+                Action::Send => send_btn.clone().do_callback(),
+                Action::FocusUri => {
+                    let _ = uri_input.clone().take_focus();
+                }
+                Action::BodyTab => params.select_tab(0),
+                Action::HeadersTab => params.select_tab(1),
+                Action::Duplicate => s.send(GlobalAppMsg::Restore(state().duplicate())),
+                _ => (),
+            })
+        };
+
+        let p_outgoing = outgoing.clone();
+        let p_params = params.clone();
+        let p_save_state = save_state.clone();
+        let mut p_log = log_buf.clone();
+        let mut p_status = status.clone();
+        let mut p_host = host.clone();
+        connect_btn.set_callback(move |btn| {
+            // a second click disconnects, dropping the sender makes the task send a close frame
+            if let Some(tx) = p_outgoing.borrow_mut().take()
+                && !tx.is_closed()
+            {
+                return;
+            }
+
+            let uri = uri_input.value();
+            p_host.set_label(&uri);
+            p_save_state();
+
+            let mut request = match uri.as_str().into_client_request() {
+                Ok(request) => request,
+                Err(e) => {
+                    log(&mut p_log, "!!", &format!("{e}"));
+                    return;
+                }
+            };
+            request.headers_mut().extend(p_params.get_headers(&[]));
+            let values = request.headers().values().filter_map(|v| v.to_str().ok());
+            if let Err(e) = secrets::check_sendable(std::iter::once(uri.as_str()).chain(values)) {
+                log(&mut p_log, "!!", &e);
+                return;
+            }
+            let protocols = protocols_input.value();
+            if !protocols.trim().is_empty()
+                && let Ok(value) = HeaderValue::from_str(protocols.trim())
+            {
+                request
+                    .headers_mut()
+                    .insert("Sec-WebSocket-Protocol", value);
+            }
+
+            let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+            *p_outgoing.borrow_mut() = Some(tx);
+
+            btn.set_label("Disconnect");
+            p_status.set_label(&format!("Connecting to {uri}..."));
+            let mut btn = btn.clone();
+            let mut log_buf = p_log.clone();
+            let mut status = p_status.clone();
+            tokio::spawn(async move {
+                match tokio_tungstenite::connect_async(request).await {
+                    Ok((stream, response)) => {
+                        let protocol = response
+                            .headers()
+                            .get("Sec-WebSocket-Protocol")
+                            .and_then(|p| p.to_str().ok())
+                            .map(|p| format!(" | PROTOCOL={p}"))
+                            .unwrap_or_default();
+                        status.set_label(&format!("CONNECTED {}{protocol}", response.status()));
+                        log(&mut log_buf, "--", &format!("connected to {uri}"));
+
+                        let (mut write, mut read) = stream.split();
+                        loop {
+                            tokio::select! {
+                                out = rx.recv() => match out {
+                                    Some(msg) => {
+                                        let text = describe(&msg);
+                                        match write.send(msg).await {
+                                            Ok(()) => log(&mut log_buf, "->", &text),
+                                            Err(e) => {
+                                                log(&mut log_buf, "!!", &format!("{e}"));
+                                                break;
+                                            }
+                                        }
+                                    }
+                                    None => {
+                                        let _ = write.send(Message::Close(None)).await;
+                                        log(&mut log_buf, "--", "disconnected");
+                                        break;
+                                    }
+                                },
+                                incoming = read.next() => match incoming {
+                                    Some(Ok(msg)) => log(&mut log_buf, "<-", &describe(&msg)),
+                                    Some(Err(e)) => {
+                                        log(&mut log_buf, "!!", &format!("{e}"));
+                                        break;
+                                    }
+                                    None => {
+                                        log(&mut log_buf, "--", "closed by server");
+                                        break;
+                                    }
+                                },
+                            }
+                        }
+                        status.set_label("");
+                    }
+                    Err(e) => {
+                        status.set_label("");
+                        log(&mut log_buf, "!!", &format!("{e}"));
+                    }
+                }
+
+                rx.close();
+                btn.set_label("Connect");
+                app::awake();
+                app::redraw();
+            });
+        });
+
+        let p_outgoing = outgoing.clone();
+        let mut p_log = log_buf.clone();
+        send_btn.set_callback(move |_| {
+            let Some(tx) = p_outgoing.borrow().clone() else {
+                log(&mut p_log, "!!", "not connected");
+                return;
+            };
+            match frame_from(frame_choice.value(), &params.get_body()) {
+                Ok(msg) => {
+                    save_state();
+                    if tx.send(msg).is_err() {
+                        log(&mut p_log, "!!", "not connected");
+                    }
+                }
+                Err(e) => log(&mut p_log, "!!", &e),
+            }
+        });
+
+        let p_outgoing = outgoing.clone();
+        let mut p_log = log_buf.clone();
+        ping_btn.set_callback(move |_| {
+            let sent = p_outgoing
+                .borrow()
+                .as_ref()
+                .is_some_and(|tx| tx.send(Message::Ping(Vec::new().into())).is_ok());
+            if !sent {
+                log(&mut p_log, "!!", "not connected");
+            }
+        });
+
+        let mut p_log = log_buf.clone();
+        clear_btn.set_callback(move |_| p_log.set_text(""));
+
+        Self {
+            id,
+            host,
+            outgoing,
+            state,
+            autosave,
+            actions,
+        }
+    }
+}
+
+impl HasId for WebSocketWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl AppWindow for WebSocketWindow {
+    fn close(&mut self) {
+        self.outgoing.borrow_mut().take();
+        self.autosave.cancel();
+        self.host.close();
+    }
+
+    fn window_state(&self) -> Option<OpenWindow> {
+        Some((self.state)())
+    }
+
+    fn run_action(&mut self, action: Action) {
+        (self.actions)(action)
+    }
+}